@@ -0,0 +1,11 @@
+-- Your SQL goes here
+-- `anchor` is the modelled call a statement follows, e.g. `AddWeaponResult[1]`, or NULL if it comes first of all.
+CREATE TABLE weapon_extra_statements (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	weapon_name TEXT NOT NULL,
+	position INTEGER NOT NULL,
+    statement TEXT NOT NULL,
+	anchor TEXT NULL,
+	CONSTRAINT weapon_extra_statements_FK FOREIGN KEY (weapon_name) REFERENCES weapons(name) ON DELETE CASCADE ON UPDATE CASCADE,
+    UNIQUE(weapon_name, position)
+);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- No foreign key to `weapons`: imports delete and re-insert weapons, and the earlier layers must survive that.
+CREATE TABLE weapon_sources (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	weapon_name TEXT NOT NULL,
+	layer INTEGER NOT NULL,
+	source TEXT NOT NULL,
+	rendered TEXT NOT NULL,
+	UNIQUE(weapon_name, layer)
+);
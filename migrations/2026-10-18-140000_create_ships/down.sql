@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE `ship_extra_statements`;
+DROP TABLE `ship_target_filters`;
+DROP TABLE `ship_abilities`;
+DROP TABLE `ship_weapons`;
+DROP TABLE `ships`;
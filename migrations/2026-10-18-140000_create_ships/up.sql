@@ -0,0 +1,48 @@
+-- Your SQL goes here
+CREATE TABLE ships (
+	name TEXT NOT NULL PRIMARY KEY,
+	max_health FLOAT NULL,
+	build_cost FLOAT NULL,
+	build_time FLOAT NULL,
+	main_engine_max_speed FLOAT NULL,
+	armour_family TEXT NULL
+);
+
+-- The weapon foreign key is only checked on commit, so an import can replace a weapon that's mounted on a ship.
+CREATE TABLE ship_weapons (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	ship_name TEXT NOT NULL,
+	weapon_name TEXT NOT NULL,
+	hardpoint TEXT NOT NULL,
+	extra_arguments TEXT NOT NULL DEFAULT '',
+	CONSTRAINT ship_weapons_FK FOREIGN KEY (ship_name) REFERENCES ships(name) ON DELETE CASCADE ON UPDATE CASCADE,
+	CONSTRAINT ship_weapons_weapons_FK FOREIGN KEY (weapon_name) REFERENCES weapons(name) ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED
+);
+
+CREATE TABLE ship_abilities (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	ship_name TEXT NOT NULL,
+	ability TEXT NOT NULL,
+	arguments TEXT NOT NULL DEFAULT '',
+	CONSTRAINT ship_abilities_FK FOREIGN KEY (ship_name) REFERENCES ships(name) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE TABLE ship_target_filters (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	ship_name TEXT NOT NULL,
+	arguments TEXT NOT NULL,
+	CONSTRAINT ship_target_filters_FK FOREIGN KEY (ship_name) REFERENCES ships(name) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- Every statement that isn't modelled above, including the `StartShipConfig()` line and the property lines, so they're
+-- written back where they were read from. `anchor` is the modelled call a statement follows, e.g.
+-- `StartShipWeaponConfig[1]`, or NULL if it comes first of all.
+CREATE TABLE ship_extra_statements (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	ship_name TEXT NOT NULL,
+	position INTEGER NOT NULL,
+	statement TEXT NOT NULL,
+	anchor TEXT NULL,
+	CONSTRAINT ship_extra_statements_FK FOREIGN KEY (ship_name) REFERENCES ships(name) ON DELETE CASCADE ON UPDATE CASCADE,
+	UNIQUE(ship_name, position)
+);
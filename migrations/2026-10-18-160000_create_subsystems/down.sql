@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE `subsystem_extra_statements`;
+DROP TABLE `subsystem_weapons`;
+DROP TABLE `subsystems`;
@@ -0,0 +1,31 @@
+-- Your SQL goes here
+CREATE TABLE subsystems (
+	name TEXT NOT NULL PRIMARY KEY,
+	max_health FLOAT NULL,
+	cost_to_build FLOAT NULL,
+	time_to_build FLOAT NULL
+);
+
+-- As for `ship_weapons`, the weapon foreign key is only checked on commit.
+CREATE TABLE subsystem_weapons (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	subsystem_name TEXT NOT NULL,
+	weapon_name TEXT NOT NULL,
+	hardpoint TEXT NOT NULL,
+	extra_arguments TEXT NOT NULL DEFAULT '',
+	CONSTRAINT subsystem_weapons_FK FOREIGN KEY (subsystem_name) REFERENCES subsystems(name) ON DELETE CASCADE ON UPDATE CASCADE,
+	CONSTRAINT subsystem_weapons_weapons_FK FOREIGN KEY (weapon_name) REFERENCES weapons(name) ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED
+);
+
+-- Every statement that isn't modelled above, including the `StartSubSystemConfig()` line and the property lines, so
+-- they're written back where they were read from. `anchor` is the modelled call a statement follows, e.g.
+-- `StartSubSystemWeaponConfig[1]`, or NULL if it comes first of all.
+CREATE TABLE subsystem_extra_statements (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	subsystem_name TEXT NOT NULL,
+	position INTEGER NOT NULL,
+	statement TEXT NOT NULL,
+	anchor TEXT NULL,
+	CONSTRAINT subsystem_extra_statements_FK FOREIGN KEY (subsystem_name) REFERENCES subsystems(name) ON DELETE CASCADE ON UPDATE CASCADE,
+	UNIQUE(subsystem_name, position)
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE `missile_extra_statements`;
+DROP TABLE `missiles`;
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE missiles (
+	name TEXT NOT NULL PRIMARY KEY,
+	max_health FLOAT NULL,
+	armour_family TEXT NULL,
+	main_engine_max_speed FLOAT NULL,
+	rotation_max_speed FLOAT NULL
+);
+
+-- Every statement that isn't modelled above, including the `StartMissileConfig()` line and the property lines, so
+-- they're written back where they were read from.
+CREATE TABLE missile_extra_statements (
+	id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+	missile_name TEXT NOT NULL,
+	position INTEGER NOT NULL,
+	statement TEXT NOT NULL,
+	CONSTRAINT missile_extra_statements_FK FOREIGN KEY (missile_name) REFERENCES missiles(name) ON DELETE CASCADE ON UPDATE CASCADE,
+	UNIQUE(missile_name, position)
+);
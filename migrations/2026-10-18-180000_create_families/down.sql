@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE `display_families`;
+DROP TABLE `attack_families`;
+DROP TABLE `armour_families`;
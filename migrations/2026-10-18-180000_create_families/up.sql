@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE armour_families (
+	name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE attack_families (
+	name TEXT NOT NULL PRIMARY KEY
+);
+
+CREATE TABLE display_families (
+	name TEXT NOT NULL PRIMARY KEY
+);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+UPDATE weapon_results SET spawn_weapon_name = '' WHERE spawn_weapon_name IS NULL;
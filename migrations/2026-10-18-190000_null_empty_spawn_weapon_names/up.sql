@@ -0,0 +1,2 @@
+-- Your SQL goes here
+UPDATE weapon_results SET spawn_weapon_name = NULL WHERE spawn_weapon_name = '';
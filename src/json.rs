@@ -0,0 +1,45 @@
+//! Just enough JSON writing for Scaffold's machine-readable output. Each function returns a JSON fragment, so they
+//! can be nested: `object(vec![("names", array(names.iter().map(|n| string(n))))])`.
+
+/// A JSON string, quoted and escaped.
+pub fn string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c)
+        }
+    }
+
+    out.push('"');
+
+    out
+}
+
+/// A JSON string, or `null`.
+pub fn optional_string(value: Option<&str>) -> String {
+    value.map_or_else(|| String::from("null"), string)
+}
+
+/// A JSON array of already-encoded values.
+pub fn array<I: IntoIterator<Item = String>>(values: I) -> String {
+    format!("[{}]", values.into_iter().collect::<Vec<String>>().join(","))
+}
+
+/// A JSON object from keys and already-encoded values, in the order given.
+pub fn object(fields: Vec<(&str, String)>) -> String {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|(key, value)| format!("{}:{}", string(key), value))
+        .collect();
+
+    format!("{{{}}}", fields.join(","))
+}
@@ -1,33 +1,210 @@
 pub mod schema;
 pub mod models;
+pub mod unified_diff;
+pub mod json;
 
 use std::fs;
+use std::collections::HashSet;
+use std::process;
 use std::path::{ Path, PathBuf };
 
-use clap::Parser;
+use clap::{ Parser, Subcommand, ValueEnum };
 
 use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind::ForeignKeyViolation;
+use diesel::result::Error::DatabaseError;
 use diesel_migrations::{ embed_migrations, EmbeddedMigrations, MigrationHarness };
 
+use models::data_file::{ DataFile, NewDataFile, NewDataFileCollection };
+use models::wepn_file::{ ImportOptions, ImportReport, NewWeaponFile, NewWeaponFileCollection, WeaponFile, WeaponFileCollection, WriteOptions, WriteOutcome, backup_file, contents_match };
+use models::ship_file::{ NewShipFile, NewShipFileCollection, ShipFileCollection };
+use models::ship_file::ship::ShipCollection;
+use models::ship_file::ship_weapon::ShipWeaponCollection;
+use models::subs_file::{ NewSubsFile, NewSubsFileCollection, SubsFileCollection };
+use models::subs_file::subsystem::SubsystemCollection;
+use models::subs_file::subsystem_weapon::SubsystemWeaponCollection;
+use models::miss_file::{ NewMissFile, NewMissFileCollection, MissFileCollection };
+use models::miss_file::missile::MissileCollection;
+use models::family_list::{ FamilyList, FAMILY_LIST_PATH };
+use models::wepn_file::weapon::WeaponCollection;
+use models::wepn_file::weapon_penetration::WeaponPenetrationCollection;
+use models::wepn_file::weapon_accuracy::WeaponAccuracyCollection;
+use models::wepn_file::weapon_filter::WeaponFilter;
+use models::wepn_file::weapon_source::WeaponSourceCollection;
+use models::wepn_file::import_error::ImportError;
+use models::wepn_file::weapon_diff::WeaponFileDiff;
+use models::wepn_file::spawn_graph::SpawnGraph;
+use models::wepn_file::weapon_lint::{ LintConfig, LintFinding, Severity, lint };
+use unified_diff::{ unified_diff, DEFAULT_CONTEXT };
+use models::parse_error::ParseError;
+
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Path to the Homeworld RM 'data/' directory for export. If both this and `--import-dir` are the same, the import will be performed first.
+    /// Path to Database file; If no command is given, the database will be migrated only.
     #[arg(long, short)]
-    export_dir: Option<String>,
+    db: String,
 
-    /// Path to the Homeworld RM 'data/' directory for import. If both this and `--export-dir` are the same, the import will be performed first.
-    #[arg(long, short)]
-    import_dir: Option<String>,
+    #[command(subcommand)]
+    command: Option<Command>
+}
 
-    /// Path to Database file; If no directories provided, the database will be migrated only.
-    #[arg(long, short)]
-    db: String
+#[derive(Subcommand)]
+enum Command {
+    /// Import the weapons, ships, subsystems and missiles in one or more Homeworld RM 'data/' directories into the database, in order. Each directory is a layer; later layers override earlier ones.
+    Import {
+        /// Paths to the Homeworld RM 'data/' directories, base game first.
+        #[arg(required = true)]
+        data_dirs: Vec<String>,
+
+        /// Delete every weapon, ship, subsystem, missile and family in the database before importing the first directory, instead of merging the imported ones in by name.
+        #[arg(long)]
+        replace: bool,
+
+        /// Number of rows to insert per statement. Defaults to, and is capped at, as many as SQLite allows.
+        #[arg(long)]
+        chunk_size: Option<usize>
+    },
+
+    /// Export the database to a Homeworld RM 'data/' directory.
+    Export {
+        /// Path to the Homeworld RM 'data/' directory.
+        data_dir: String,
+
+        /// Only export the named weapon. Can be given more than once.
+        #[arg(long, value_name = "WEAPON")]
+        only: Vec<String>,
+
+        /// Only export weapons whose name matches a glob (`hgn_*`), or a regular expression wrapped in slashes (`/^hgn_.*gun$/`).
+        #[arg(long = "match", value_name = "PATTERN")]
+        pattern: Option<String>,
+
+        /// Only export weapons matching an SQL condition on the `weapons` table, e.g. "weapon_fire_type = 'Missile'".
+        #[arg(long = "where", value_name = "SQL")]
+        predicate: Option<String>,
+
+        /// Don't write anything; print a unified diff of each file that would be created or changed.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// With `--dry-run`, only list the new and changed files instead of printing diffs.
+        #[arg(long, requires = "dry_run")]
+        summary: bool,
+
+        /// Treat files that only differ by Scaffold's watermark comment as unchanged.
+        #[arg(long)]
+        ignore_header: bool,
+
+        /// Copy each file that gets overwritten or pruned into this directory first, with a timestamp in its name.
+        #[arg(long)]
+        backup_dir: Option<String>,
+
+        /// Remove *.wepn files from the 'weapon/' directory that don't belong to any weapon in the database. Other files are never touched.
+        #[arg(long)]
+        prune: bool,
+
+        /// Only export weapons that differ from the base (first) import layer, or that aren't in it.
+        #[arg(long)]
+        overrides_only: bool,
+
+        /// Only export weapons that differ from the ones in this Scaffold database, or that aren't in it.
+        #[arg(long, value_name = "DB")]
+        against: Option<String>
+    },
+
+    /// List what's in the database.
+    List {
+        #[command(subcommand)]
+        list: ListCommand
+    },
+
+    /// Print a weapon file as it would be exported.
+    Show {
+        /// Name of the weapon.
+        weapon: String
+    },
+
+    /// Check the database for problems: anything that can't be exported and read back, references to missing missiles, armour families or spawned weapons, and lint rule findings. Optionally checks that every file in a 'data/' directory parses too.
+    Validate {
+        /// Path to a Homeworld RM 'data/' directory to check as well.
+        #[arg(long)]
+        data_dir: Option<String>,
+
+        /// Lint config file, with one `rule-id = error|warning|off` per line.
+        #[arg(long, value_name = "PATH")]
+        lint_config: Option<String>,
+
+        /// Print the problems and lint findings as JSON.
+        #[arg(long)]
+        json: bool
+    },
+
+    /// Compare the database, field by field, with another Scaffold database or a 'data/' directory, and list every value that changes going from this database to the other.
+    Diff {
+        /// Path to the other Scaffold database, or to a Homeworld RM 'data/' directory.
+        #[arg(value_name = "DB_OR_DATA_DIR")]
+        other: String,
+
+        /// Print the differences as JSON.
+        #[arg(long)]
+        json: bool
+    },
+
+    /// Print which weapons spawn which (through `AddWeaponResult`), as a Graphviz DOT graph or JSON adjacency lists. Loops are highlighted, and spawned weapons that don't exist are included.
+    Graph {
+        /// Output format.
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+
+        /// Only include this weapon and the weapons it spawns, directly or not.
+        #[arg(long, value_name = "WEAPON")]
+        root: Option<String>,
+
+        /// Write the graph to this file instead of printing it.
+        #[arg(long, short)]
+        output: Option<String>
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT, e.g. for `dot -Tsvg`.
+    Dot,
+
+    /// JSON adjacency lists.
+    Json
+}
+
+#[derive(Subcommand)]
+enum ListCommand {
+    /// List every weapon name.
+    Weapons,
+
+    /// List every ship name.
+    Ships {
+        /// Only list ships with this weapon mounted on a hardpoint. Names are compared without regard to case.
+        #[arg(long, value_name = "WEAPON")]
+        uses: Option<String>
+    },
+
+    /// List every subsystem name.
+    Subsystems {
+        /// Only list subsystems that mount this weapon. Names are compared without regard to case.
+        #[arg(long, value_name = "WEAPON")]
+        uses: Option<String>
+    },
+
+    /// List every missile name.
+    Missiles,
+
+    /// List the import layers recorded for the weapons, base first.
+    Layers
 }
 
 /// Find files with a specific extension.
+#[allow(clippy::manual_flatten)]
 pub fn find_files_with_extension(root_path: &Path, extension: &str) -> Vec<PathBuf> {
     let mut files = vec![];
     if let Ok(entries) = fs::read_dir(root_path) {
@@ -46,45 +223,878 @@ pub fn find_files_with_extension(root_path: &Path, extension: &str) -> Vec<PathB
     files
 }
 
-/// Establish Connection to Sqlite Database.
+/// Establish Connection to Sqlite Database. Foreign keys are enforced, which SQLite doesn't do unless asked to.
 pub fn establish_connection(db_path: &Path) -> SqliteConnection {
     let database_url: String = format!("file:{}", db_path.display());
 
-    SqliteConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
-}
+    let mut connection = SqliteConnection::establish(&database_url)
+        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
 
-/// Import "data" directory into a Sqlite database.
-pub fn import(connection: &mut SqliteConnection, data_dir: &Path) {
-    use models::wepn_file:: { NewWeaponFileCollection, NewWeaponFile };
+    diesel::sql_query("PRAGMA foreign_keys = ON")
+        .execute(&mut connection)
+        .unwrap_or_else(|_| panic!("Could not enable foreign keys for {}", database_url));
 
-    let extension = "wepn";
-    let files = find_files_with_extension(data_dir, extension);
-    let mut weapon_files: Vec<NewWeaponFile> = Vec::new();
+    connection
+}
+
+/// Parse every file of one kind (e.g. every *.wepn file) in a "data" directory. If any of them are malformed, all of
+/// the errors are returned.
+pub fn read_data_files<C: NewDataFileCollection>(data_dir: &Path) -> Result<C, Vec<ParseError>> {
+    let files = find_files_with_extension(data_dir, C::File::EXTENSION);
+    let mut data_files = Vec::new();
+    let mut errors: Vec<ParseError> = Vec::new();
 
     for file in files {
-        weapon_files.push(NewWeaponFile::from_path(file.as_path()));
+        match C::File::from_path(file.as_path()) {
+            Ok(data_file) => data_files.push(data_file),
+            Err(e) => errors.push(e)
+        }
     }
 
-    let collection = NewWeaponFileCollection::from_vec(weapon_files);
+    if errors.is_empty() {
+        Ok(C::from_vec(data_files))
+    } else {
+        Err(errors)
+    }
+}
 
-    collection.insert(connection);
+/// Parse `scripts/familylist.lua` in a "data" directory, if there is one.
+pub fn read_family_list(data_dir: &Path) -> Result<Option<FamilyList>, Vec<ParseError>> {
+    FamilyList::from_data_dir(data_dir)
+        .transpose()
+        .map_err(|e| vec![e])
 }
 
-/// Export "data" directory from the Sqlite Database to the HWRM Data directory.
-pub fn export(connection: &mut SqliteConnection, data_dir: &Path) {
-    use models::wepn_file::{ WeaponFileCollection };
+/// What `import` did with one "data" directory.
+#[derive(Debug)]
+pub struct LayerReport {
+    pub dir: PathBuf,
+    pub weapons: ImportReport,
+    pub ships: ImportReport,
+    pub subsystems: ImportReport,
+    pub missiles: ImportReport,
+    pub families: Option<FamilyList>
+}
+
+/// Print parse errors to stderr.
+fn print_parse_errors(errors: &[ParseError]) {
+    for error in errors.iter() {
+        eprintln!("{}", error);
+    }
+}
+
+/// Warn about `StartWeaponConfig(...)` and `AddWeaponResult(...)` arguments Scaffold doesn't know the value of.
+/// They're imported as written.
+fn print_unknown_values(weapons: &NewWeaponFileCollection) {
+    for wf in weapons.new_weapon_files.iter() {
+        let results = wf.weapon_results.unknown_values();
+        let unknown = wf.weapon.unknown_values().into_iter().map(|(k, v)| (k.to_string(), v)).chain(results);
+
+        for (field, value) in unknown {
+            eprintln!("warning: {}.wepn: unknown {} `{}`; kept as written", wf.weapon.name, field, value);
+        }
+    }
+}
+
+/// Fail on the first ship or subsystem hardpoint that mounts a weapon that isn't in the database, once every layer is
+/// in. The foreign keys would reject it on commit anyway, but without saying which one. Weapon names are spelled the
+/// way the weapons are first, in case a later layer brought the weapon in.
+fn check_mounted_weapons(connection: &mut SqliteConnection) -> Result<(), ImportError> {
+    ShipWeaponCollection::match_weapon_names(connection).map_err(|e| ImportError::new("ship_weapons", None, e))?;
+    SubsystemWeaponCollection::match_weapon_names(connection).map_err(|e| ImportError::new("subsystem_weapons", None, e))?;
+
+    let missing = |weapon_name: &str, hardpoint: &str| {
+        let message = format!("mounts `{}` on `{}`, but there is no weapon by that name", weapon_name, hardpoint);
+
+        DatabaseError(ForeignKeyViolation, Box::new(message))
+    };
+
+    if let Some(sw) = ShipWeaponCollection::get_dangling(connection).ship_weapons.first() {
+        return Err(ImportError::new("ship_weapons", Some(sw.ship_name.to_string()), missing(&sw.weapon_name, &sw.hardpoint)));
+    }
+
+    if let Some(sw) = SubsystemWeaponCollection::get_dangling(connection).subsystem_weapons.first() {
+        return Err(ImportError::new("subsystem_weapons", Some(sw.subsystem_name.to_string()), missing(&sw.weapon_name, &sw.hardpoint)));
+    }
+
+    Ok(())
+}
+
+/// Import the weapons, ships, subsystems and missiles in "data" directories into a Sqlite database, in order,
+/// recording each one as a layer in `weapon_sources`. `options.replace` only applies to the first directory, and
+/// clears the families too. A directory with a `scripts/familylist.lua` replaces the families in the database.
+/// Nothing is written unless every file parses, and the whole import is rolled back if any of it can't be written or
+/// a ship or subsystem ends up mounting a weapon that isn't in the database.
+/// Exits the process on failure.
+pub fn import(connection: &mut SqliteConnection, data_dirs: &[PathBuf], options: &ImportOptions) -> Vec<LayerReport> {
+    let mut collections = Vec::new();
+    let mut errors = Vec::new();
+
+    for dir in data_dirs.iter() {
+        match (read_data_files::<NewWeaponFileCollection>(dir), read_data_files::<NewShipFileCollection>(dir), read_data_files::<NewSubsFileCollection>(dir), read_data_files::<NewMissFileCollection>(dir), read_family_list(dir)) {
+            (Ok(weapons), Ok(ships), Ok(subsystems), Ok(missiles), Ok(families)) => {
+                collections.push((weapons, ships, subsystems, missiles, families));
+            },
+            (weapons, ships, subsystems, missiles, families) => {
+                errors.extend(weapons.err().unwrap_or_default());
+                errors.extend(ships.err().unwrap_or_default());
+                errors.extend(subsystems.err().unwrap_or_default());
+                errors.extend(missiles.err().unwrap_or_default());
+                errors.extend(families.err().unwrap_or_default());
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        print_parse_errors(&errors);
+
+        eprintln!("Import aborted; {} file(s) could not be parsed.", errors.len());
+
+        process::exit(1);
+    }
+
+    for (weapons, ..) in collections.iter() {
+        print_unknown_values(weapons);
+    }
+
+    let res = connection.transaction(|connection| {
+        let mut reports = Vec::new();
+
+        for (index, (dir, (weapons, ships, subsystems, missiles, families))) in data_dirs.iter().zip(collections).enumerate() {
+            let source = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+
+            let layer_options = ImportOptions {
+                replace: options.replace && index == 0,
+                chunk_size: options.chunk_size,
+                quiet: options.quiet,
+                source: Some(source.display().to_string())
+            };
+
+            match &families {
+                Some(families) => families.insert(connection, &layer_options)?,
+                None if layer_options.replace => FamilyList::delete_all(connection)?,
+                None => {}
+            }
+
+            reports.push(LayerReport {
+                dir: dir.to_path_buf(),
+                weapons: weapons.insert(connection, &layer_options)?,
+                ships: ships.insert(connection, &layer_options)?,
+                subsystems: subsystems.insert(connection, &layer_options)?,
+                missiles: missiles.insert(connection, &layer_options)?,
+                families
+            });
+        }
+
+        check_mounted_weapons(connection)?;
+
+        Ok::<_, ImportError>(reports)
+    });
+
+    res.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("Import rolled back; the database has not been changed.");
+
+        process::exit(1);
+    })
+}
+
+/// Options for `export`.
+#[derive(Debug, Default)]
+pub struct ExportOptions {
+    /// Compare against the files on disk instead of writing.
+    pub dry_run: bool,
+
+    /// With `dry_run`, list the files rather than printing diffs.
+    pub summary: bool,
+
+    /// Treat files that only differ by the watermark as unchanged.
+    pub ignore_header: bool,
+
+    /// Archive overwritten and pruned files here.
+    pub backup_dir: Option<PathBuf>,
+
+    /// Remove *.wepn files with no weapon in the database.
+    pub prune: bool,
+
+    /// Only export weapons that differ from the base import layer.
+    pub overrides_only: bool,
+
+    /// Only export weapons that differ from this reference database.
+    pub against: Option<PathBuf>
+}
+
+/// Open another Scaffold database to compare against. It has to exist and be up to date; it's never migrated or
+/// written to. Exits the process otherwise.
+pub fn open_reference_database(db_path: &Path) -> SqliteConnection {
+    if !db_path.is_file() {
+        eprintln!("Reference database {} does not exist.", db_path.display());
+
+        process::exit(1);
+    }
+
+    let mut connection = establish_connection(db_path);
+
+    if connection.has_pending_migration(MIGRATIONS).unwrap_or(true) {
+        eprintln!("Reference database {} is out of date; open it with `hwrm-scaffold -d {}` first.", db_path.display(), db_path.display());
+
+        process::exit(1);
+    }
+
+    connection
+}
+
+/// Load the weapon files that pass `filter`. Exits the process if the filter's SQL is invalid.
+pub fn get_filtered_weapon_files(connection: &mut SqliteConnection, filter: &WeaponFilter) -> WeaponFileCollection {
+    if filter.is_empty() {
+        return WeaponFileCollection::get_all_weapon_files(connection);
+    }
+
+    let names = filter.get_names(connection).unwrap_or_else(|error| {
+        eprintln!("Could not filter weapons: {}", error);
+
+        process::exit(1);
+    });
+
+    let missing = filter.get_missing(connection).unwrap_or_else(|error| {
+        eprintln!("Could not filter weapons: {}", error);
+
+        process::exit(1);
+    });
+
+    for name in missing {
+        eprintln!("No weapon named `{}`.", name);
+    }
+
+    WeaponFileCollection::get_weapon_files_from_names(connection, names)
+}
+
+/// Export "data" directory from the Sqlite Database to the HWRM Data directory. Only weapons that pass `filter`
+/// (and, with `options.overrides_only` or `options.against`, differ from the base layer or reference database) are
+/// exported, and files that haven't changed are left alone. Ships, subsystems and missiles are exported too, but only
+/// when no weapons are filtered out, and only if their "ship", "subsystem" or "missile" directory exists. With
+/// `options.prune`, stale weapon files are removed afterwards. With `options.dry_run`, nothing is written; each file is
+/// compared with the one on disk instead. Exits the process without writing anything if a ship or subsystem being
+/// exported mounts a weapon that isn't in the database.
+pub fn export(connection: &mut SqliteConnection, data_dir: &Path, filter: &WeaponFilter, options: &ExportOptions) {
+    let mut wepn_files = get_filtered_weapon_files(connection, filter);
+
+    if options.overrides_only {
+        let base = WeaponSourceCollection::get_base_layer(connection);
+
+        if base.is_empty() {
+            eprintln!("No import layers are recorded; exporting every weapon.");
+        }
+
+        wepn_files.retain_differing(&base);
+    }
+
+    if let Some(against) = &options.against {
+        let reference = &mut open_reference_database(against);
+        let names = wepn_files.weapon_files.keys().map(|n| n.to_string()).collect();
+
+        wepn_files.retain_differing(&WeaponFileCollection::get_weapon_files_from_names(reference, names).rendered());
+    }
 
-    let wepn_files = WeaponFileCollection::get_all_weapon_files(connection);
     let weapon_dir = data_dir.join("weapon");
+    let ship_dir = data_dir.join("ship");
+    let subsystem_dir = data_dir.join("subsystem");
+    let missile_dir = data_dir.join("missile");
+
+    let (ship_files, subs_files, miss_files) = if filter.is_empty() && !options.overrides_only && options.against.is_none() {
+        (
+            ShipFileCollection::get_all_ship_files(connection),
+            SubsFileCollection::get_all_subs_files(connection),
+            MissFileCollection::get_all_miss_files(connection)
+        )
+    } else {
+        (ShipFileCollection::default(), SubsFileCollection::default(), MissFileCollection::default())
+    };
+
+    if wepn_files.weapon_files.is_empty() {
+        println!("No weapons to export.");
+    }
+
+    let mut names: Vec<&String> = wepn_files.weapon_files.keys().collect();
+    let mut ship_names: Vec<&String> = ship_files.ship_files.keys().collect();
+    let mut subsystem_names: Vec<&String> = subs_files.subs_files.keys().collect();
+    let mut missile_names: Vec<&String> = miss_files.miss_files.keys().collect();
+
+    names.sort();
+    ship_names.sort();
+    subsystem_names.sort();
+    missile_names.sort();
+
+    if !ship_names.is_empty() && !ship_dir.is_dir() {
+        println!("{} does not exist; skipping {} ship(s).", ship_dir.display(), ship_names.len());
+
+        ship_names.clear();
+    }
+
+    if !subsystem_names.is_empty() && !subsystem_dir.is_dir() {
+        println!("{} does not exist; skipping {} subsystem(s).", subsystem_dir.display(), subsystem_names.len());
+
+        subsystem_names.clear();
+    }
+
+    if !missile_names.is_empty() && !missile_dir.is_dir() {
+        println!("{} does not exist; skipping {} missile(s).", missile_dir.display(), missile_names.len());
+
+        missile_names.clear();
+    }
+
+    let dangling_ship_weapons = if ship_names.is_empty() {
+        ShipWeaponCollection::new()
+    } else {
+        ShipWeaponCollection::get_dangling(connection)
+    };
+
+    let dangling_subsystem_weapons = if subsystem_names.is_empty() {
+        SubsystemWeaponCollection::new()
+    } else {
+        SubsystemWeaponCollection::get_dangling(connection)
+    };
+
+    let dangling = dangling_ship_weapons.ship_weapons.len() + dangling_subsystem_weapons.subsystem_weapons.len();
+
+    if dangling > 0 {
+        for sw in dangling_ship_weapons.ship_weapons.iter() {
+            eprintln!("Ship `{}` mounts `{}` on `{}`, but there is no weapon by that name.", sw.ship_name, sw.weapon_name, sw.hardpoint);
+        }
+
+        for sw in dangling_subsystem_weapons.subsystem_weapons.iter() {
+            eprintln!("Subsystem `{}` mounts `{}` on `{}`, but there is no weapon by that name.", sw.subsystem_name, sw.weapon_name, sw.hardpoint);
+        }
+
+        eprintln!("Export aborted; {} hardpoint(s) refer to missing weapons.", dangling);
+
+        process::exit(1);
+    }
+
+    let stale = if options.prune {
+        find_stale_weapon_files(connection, &weapon_dir)
+    } else {
+        Vec::new()
+    };
+
+    if options.dry_run {
+        let mut files: Vec<(PathBuf, String)> = names
+            .iter()
+            .map(|name| {
+                let wepn = &wepn_files.weapon_files[*name];
+
+                (wepn.path_in_kind_dir(&weapon_dir), wepn.to_string())
+            })
+            .collect();
+
+        files.extend(ship_names.iter().map(|name| {
+            let ship = &ship_files.ship_files[*name];
+
+            (ship.path_in_kind_dir(&ship_dir), ship.to_string())
+        }));
+
+        files.extend(subsystem_names.iter().map(|name| {
+            let subs = &subs_files.subs_files[*name];
+
+            (subs.path_in_kind_dir(&subsystem_dir), subs.to_string())
+        }));
+
+        files.extend(missile_names.iter().map(|name| {
+            let miss = &miss_files.miss_files[*name];
+
+            (miss.path_in_kind_dir(&missile_dir), miss.to_string())
+        }));
+
+        preview_export(&files, data_dir, options);
+
+        for path in stale.iter() {
+            println!("stale... {}", path.display());
+        }
+
+        if options.prune {
+            println!("{} stale file(s) would be removed.", stale.len());
+        }
+
+        return;
+    }
+
+    let write_options = WriteOptions {
+        ignore_header: options.ignore_header,
+        backup_dir: options.backup_dir.clone()
+    };
+
+    let mut outcomes: Vec<(&String, WriteOutcome)> = Vec::new();
+
+    for name in names {
+        let outcome = write_changes_or_exit(&wepn_files.weapon_files[name], &weapon_dir, &write_options);
+
+        outcomes.push((name, outcome));
+    }
+
+    for name in ship_names {
+        let outcome = write_changes_or_exit(&ship_files.ship_files[name], &ship_dir, &write_options);
+
+        outcomes.push((name, outcome));
+    }
+
+    for name in subsystem_names {
+        let outcome = write_changes_or_exit(&subs_files.subs_files[name], &subsystem_dir, &write_options);
+
+        outcomes.push((name, outcome));
+    }
+
+    for name in missile_names {
+        let outcome = write_changes_or_exit(&miss_files.miss_files[name], &missile_dir, &write_options);
+
+        outcomes.push((name, outcome));
+    }
+
+    let mut written = 0;
+    let mut skipped = 0;
+
+    for (name, outcome) in outcomes {
+        match outcome {
+            WriteOutcome::Created => println!("created... {}", name),
+            WriteOutcome::Updated => println!("updated... {}", name),
+            WriteOutcome::Unchanged => {
+                skipped += 1;
+
+                continue;
+            }
+        }
+
+        written += 1;
+    }
+
+    println!("{} written, {} skipped as unchanged.", written, skipped);
+
+    if options.prune {
+        prune(&weapon_dir, &stale, options);
+    }
+}
+
+/// Write out a data file with `DataFile::write_changes_to_kind_dir`. Files written before a failure are left in
+/// place, so this reports the path that couldn't be written and exits.
+fn write_changes_or_exit<F: DataFile>(file: &F, kind_dir: &Path, options: &WriteOptions) -> WriteOutcome {
+    file.write_changes_to_kind_dir(kind_dir, options).unwrap_or_else(|error| {
+        eprintln!("Could not write `{}`: {}", file.path_in_kind_dir(kind_dir).display(), error);
+        eprintln!("Export aborted; files written before this one have been kept.");
+
+        process::exit(1);
+    })
+}
+
+/// Find *.wepn files in the "weapon" directory that don't belong to any weapon in the database. Names are compared
+/// without regard to case, so a file is never treated as stale just because its case differs.
+pub fn find_stale_weapon_files(connection: &mut SqliteConnection, weapon_dir: &Path) -> Vec<PathBuf> {
+    let names: HashSet<String> = WeaponCollection::get_all_weapons(connection)
+        .get_names()
+        .iter()
+        .map(|n| n.to_lowercase())
+        .collect();
+
+    let mut stale: Vec<PathBuf> = find_files_with_extension(weapon_dir, "wepn")
+        .into_iter()
+        .filter(|path| {
+            path.file_stem().is_some_and(|stem| !names.contains(&stem.to_string_lossy().to_lowercase()))
+        })
+        .collect();
+
+    stale.sort();
+
+    stale
+}
+
+/// Remove stale weapon files, backing each one up first if `options.backup_dir` is set. The containment directory is
+/// removed too, but only once it's empty, so any other assets in it are left alone.
+pub fn prune(weapon_dir: &Path, stale: &[PathBuf], options: &ExportOptions) {
+    for path in stale.iter() {
+        if let Some(backup_dir) = &options.backup_dir {
+            if let Err(error) = backup_file(path, backup_dir) {
+                eprintln!("Could not back up stale file `{}`: {}", path.display(), error);
+                eprintln!("Prune aborted; `{}` has been kept.", path.display());
+
+                process::exit(1);
+            }
+        }
+
+        if let Err(error) = fs::remove_file(path) {
+            eprintln!("Could not remove stale file `{}`: {}", path.display(), error);
+
+            process::exit(1);
+        }
+
+        println!("removed... {}", path.display());
+
+        if let Some(dir) = path.parent() {
+            if dir != weapon_dir {
+                fs::remove_dir(dir).ok();
+            }
+        }
+    }
+
+    println!("{} stale file(s) removed.", stale.len());
+}
+
+/// Compare each rendered file (paired with the path it would be written to) with the one on disk, printing a unified
+/// diff for each new or changed file (or just their names, with `options.summary`).
+fn preview_export(files: &[(PathBuf, String)], data_dir: &Path, options: &ExportOptions) {
+    let mut new_files = Vec::new();
+    let mut changed_files = Vec::new();
+    let mut identical = 0;
+
+    for (path, rendered) in files.iter() {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let label = path.strip_prefix(data_dir).unwrap_or(path).display().to_string().replace('\\', "/");
+
+        let existing = fs::read(path).ok().map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+
+        let old = match &existing {
+            Some(contents) if contents_match(rendered, contents, options.ignore_header) => {
+                identical += 1;
+
+                continue;
+            },
+            Some(contents) => {
+                changed_files.push(name.to_string());
+
+                contents.as_str()
+            },
+            None => {
+                new_files.push(name.to_string());
+
+                ""
+            }
+        };
+
+        if !options.summary {
+            let old_label = if existing.is_some() { format!("a/{}", label) } else { String::from("/dev/null") };
+            let diff = unified_diff(old, rendered, &old_label, &format!("b/{}", label), DEFAULT_CONTEXT);
+
+            if diff.is_empty() {
+                println!("{}: line endings differ", label);
+            } else {
+                print!("{}", diff);
+            }
+        }
+    }
+
+    for name in new_files.iter() {
+        println!("new... {}", name);
+    }
+
+    for name in changed_files.iter() {
+        println!("changed... {}", name);
+    }
+
+    println!("{} new, {} changed, {} identical. Nothing was written.", new_files.len(), changed_files.len(), identical);
+}
+
+/// Print the name of every weapon in the database.
+pub fn list_weapons(connection: &mut SqliteConnection) {
+    let mut names = WeaponCollection::get_all_weapons(connection).get_names();
+
+    names.sort();
+
+    for name in names.iter() {
+        println!("{}", name);
+    }
+}
+
+/// Print the name of every ship in the database, or with `uses`, of every ship with that weapon on a hardpoint.
+pub fn list_ships(connection: &mut SqliteConnection, uses: Option<&str>) {
+    let names = match uses {
+        Some(weapon) => ShipWeaponCollection::get_for_weapon(connection, weapon).get_ship_names(),
+        None => {
+            let mut names = ShipCollection::get_all_ships(connection).get_names();
+
+            names.sort();
+
+            names
+        }
+    };
+
+    for name in names.iter() {
+        println!("{}", name);
+    }
+}
+
+/// Print the name of every subsystem in the database, or with `uses`, of every subsystem that mounts that weapon.
+pub fn list_subsystems(connection: &mut SqliteConnection, uses: Option<&str>) {
+    let names = match uses {
+        Some(weapon) => SubsystemWeaponCollection::get_for_weapon(connection, weapon).get_subsystem_names(),
+        None => {
+            let mut names = SubsystemCollection::get_all_subsystems(connection).get_names();
+
+            names.sort();
+
+            names
+        }
+    };
+
+    for name in names.iter() {
+        println!("{}", name);
+    }
+}
+
+/// Print the name of every missile in the database.
+pub fn list_missiles(connection: &mut SqliteConnection) {
+    let mut names = MissileCollection::get_all_missiles(connection).get_names();
+
+    names.sort();
+
+    for name in names.iter() {
+        println!("{}", name);
+    }
+}
+
+/// Print each import layer with its source and how many weapons it provided.
+pub fn list_layers(connection: &mut SqliteConnection) {
+    for (layer, source, count) in WeaponSourceCollection::get_layers(connection) {
+        println!("{}\t{}\t{} weapon(s)", layer, source, count);
+    }
+}
+
+/// Print a weapon file as it would be exported. Exits the process if the weapon doesn't exist.
+pub fn show(connection: &mut SqliteConnection, weapon_name: &str) {
+    match WeaponFile::get_for_weapon(connection, &weapon_name.to_string()) {
+        Some(wepn) => println!("{}", wepn),
+        None => {
+            eprintln!("No weapon named `{}`.", weapon_name);
+
+            process::exit(1);
+        }
+    }
+}
+
+/// What `validate` found: files that can't be read back or refer to something missing, and lint findings.
+#[derive(Debug)]
+pub struct ValidateReport {
+    pub problems: Vec<ParseError>,
+    pub findings: Vec<LintFinding>
+}
+
+impl ValidateReport {
+    /// Number of lint findings with error severity.
+    pub fn lint_errors(&self) -> usize {
+        self.findings.iter().filter(|f| f.severity == Severity::Error).count()
+    }
+
+    /// Does this report make `validate` fail?
+    pub fn failed(&self) -> bool {
+        !self.problems.is_empty() || self.lint_errors() > 0
+    }
+
+    /// The report as JSON.
+    pub fn to_json(&self) -> String {
+        json::object(vec![
+            ("problems", json::array(self.problems.iter().map(|p| p.to_json()))),
+            ("findings", json::array(self.findings.iter().map(|f| f.to_json()))),
+            ("failed", self.failed().to_string())
+        ])
+    }
+}
+
+/// Check that every weapon, ship, subsystem and missile in the database renders to a file that can be read back in,
+/// that every weapon firing a `Missile` names a missile in the database, that every weapon a ship or subsystem mounts
+/// exists, that every armour family in a penetration or accuracy row is in the family list, that every spawned weapon
+/// exists and none of them end up spawning themselves, and that every *.wepn, *.ship, *.subs and *.miss file in
+/// `data_dir` parses. Then runs the lint rules over every weapon. With `quiet`, nothing is printed while checking.
+pub fn validate(connection: &mut SqliteConnection, data_dir: Option<&Path>, lint_config: &LintConfig, quiet: bool) -> ValidateReport {
+    let progress = |message: String| if !quiet { println!("{}", message) };
+    let mut errors = Vec::new();
+
+    let wepn_files = WeaponFileCollection::get_all_weapon_files(connection);
 
     for (name, wepn) in wepn_files.weapon_files.iter() {
-        println!("writing... {}", name);
+        if let Err(e) = NewWeaponFile::from_string(name, &wepn.to_string()) {
+            errors.push(e.with_path(Path::new(&format!("{}.wepn", name))));
+        }
+    }
+
+    progress(format!("checked {} weapon(s) in the database", wepn_files.weapon_files.len()));
+
+    let ship_files = ShipFileCollection::get_all_ship_files(connection);
+
+    for (name, ship) in ship_files.ship_files.iter() {
+        if let Err(e) = NewShipFile::from_string(name, &ship.to_string()) {
+            errors.push(e.with_path(Path::new(&format!("{}.ship", name))));
+        }
+    }
+
+    progress(format!("checked {} ship(s) in the database", ship_files.ship_files.len()));
+
+    let subs_files = SubsFileCollection::get_all_subs_files(connection);
+
+    for (name, subs) in subs_files.subs_files.iter() {
+        if let Err(e) = NewSubsFile::from_string(name, &subs.to_string()) {
+            errors.push(e.with_path(Path::new(&format!("{}.subs", name))));
+        }
+    }
+
+    progress(format!("checked {} subsystem(s) in the database", subs_files.subs_files.len()));
+
+    let miss_files = MissFileCollection::get_all_miss_files(connection);
+
+    for (name, miss) in miss_files.miss_files.iter() {
+        if let Err(e) = NewMissFile::from_string(name, &miss.to_string()) {
+            errors.push(e.with_path(Path::new(&format!("{}.miss", name))));
+        }
+    }
+
+    progress(format!("checked {} missile(s) in the database", miss_files.miss_files.len()));
+
+    for weapon in WeaponCollection::get_missing_missiles(connection) {
+        let message = format!("Fires missile `{}`, but there is no missile by that name.", weapon.weapon_fire_name);
+
+        errors.push(ParseError::new(&message).with_path(Path::new(&format!("{}.wepn", weapon.name))));
+    }
+
+    for sw in ShipWeaponCollection::get_dangling(connection).ship_weapons {
+        let message = format!("Mounts `{}` on `{}`, but there is no weapon by that name.", sw.weapon_name, sw.hardpoint);
+
+        errors.push(ParseError::new(&message).with_path(Path::new(&format!("{}.ship", sw.ship_name))));
+    }
+
+    for sw in SubsystemWeaponCollection::get_dangling(connection).subsystem_weapons {
+        let message = format!("Mounts `{}` on `{}`, but there is no weapon by that name.", sw.weapon_name, sw.hardpoint);
+
+        errors.push(ParseError::new(&message).with_path(Path::new(&format!("{}.subs", sw.subsystem_name))));
+    }
+
+    let spawn_graph = SpawnGraph::from_database(connection);
+
+    for (weapon, spawns) in spawn_graph.dangling.iter() {
+        for spawn in spawns {
+            let message = format!("Spawns `{}`, but there is no weapon by that name.", spawn);
+
+            errors.push(ParseError::new(&message).with_path(Path::new(&format!("{}.wepn", weapon))));
+        }
+    }
+
+    for cycle in spawn_graph.cycles() {
+        let message = format!("Spawns itself: {}", cycle.join(" -> "));
+
+        errors.push(ParseError::new(&message).with_path(Path::new(&format!("{}.wepn", cycle[0]))));
+    }
+
+    let families = FamilyList::get(connection).armour_family_set();
+
+    if families.is_empty() {
+        progress(format!("no armour families in the database; import a 'data/' directory with {} to check them", FAMILY_LIST_PATH));
+    } else {
+        for wp in WeaponPenetrationCollection::get_with_unknown_armour_family(connection, &families).weapon_penetrations {
+            let message = format!("Unknown armour family `{}` in setPenetration.", wp.armor_family);
+
+            errors.push(ParseError::new(&message).with_path(Path::new(&format!("{}.wepn", wp.weapon_name))));
+        }
+
+        for wa in WeaponAccuracyCollection::get_with_unknown_armour_family(connection, &families).weapon_accuracies {
+            let message = format!("Unknown armour family `{}` in setAccuracy.", wa.armor_family);
+
+            errors.push(ParseError::new(&message).with_path(Path::new(&format!("{}.wepn", wa.weapon_name))));
+        }
+
+        progress(format!("checked armour families against {} known families", families.len()));
+    }
+
+    if let Some(dir) = data_dir {
+        match read_data_files::<NewWeaponFileCollection>(dir) {
+            Ok(collection) => progress(format!("checked {} weapon file(s) in {}", collection.new_weapon_files.len(), dir.display())),
+            Err(mut e) => errors.append(&mut e)
+        }
+
+        match read_data_files::<NewShipFileCollection>(dir) {
+            Ok(collection) => progress(format!("checked {} ship file(s) in {}", collection.new_ship_files.len(), dir.display())),
+            Err(mut e) => errors.append(&mut e)
+        }
+
+        match read_data_files::<NewSubsFileCollection>(dir) {
+            Ok(collection) => progress(format!("checked {} subsystem file(s) in {}", collection.new_subs_files.len(), dir.display())),
+            Err(mut e) => errors.append(&mut e)
+        }
 
-        wepn.write_to_weapon_dir(&weapon_dir).expect("Could not write to disk!");
+        match read_data_files::<NewMissFileCollection>(dir) {
+            Ok(collection) => progress(format!("checked {} missile file(s) in {}", collection.new_miss_files.len(), dir.display())),
+            Err(mut e) => errors.append(&mut e)
+        }
+
+        if let Err(mut e) = read_family_list(dir) {
+            errors.append(&mut e);
+        }
+    }
+
+    ValidateReport {
+        findings: lint(&wepn_files, lint_config),
+        problems: errors
     }
 }
 
+/// Load a "data" directory into an in-memory database, so it can be read back the same way as a Scaffold database.
+/// Exits the process if any of the files can't be parsed or loaded.
+pub fn load_data_dir(data_dir: &Path) -> SqliteConnection {
+    let collection = read_data_files::<NewWeaponFileCollection>(data_dir).unwrap_or_else(|errors| {
+        print_parse_errors(&errors);
+
+        eprintln!("Diff aborted; {} file(s) could not be parsed.", errors.len());
+
+        process::exit(1);
+    });
+
+    let mut disk = SqliteConnection::establish(":memory:").expect("Could not open in-memory database!");
+
+    disk.run_pending_migrations(MIGRATIONS).expect("In-memory database could not be migrated!");
+
+    let options = ImportOptions {
+        quiet: true,
+        ..Default::default()
+    };
+
+    collection.insert(&mut disk, &options).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        eprintln!("Diff aborted; the weapon files could not be loaded.");
+
+        process::exit(1);
+    });
+
+    disk
+}
+
+/// The spawn graph of the database, as DOT or JSON. With `root`, only that weapon and what it spawns; otherwise every
+/// weapon that spawns or is spawned. Exits the process if there's no weapon named `root`.
+fn graph(connection: &mut SqliteConnection, format: GraphFormat, root: Option<&str>) -> String {
+    let spawn_graph = SpawnGraph::from_database(connection);
+
+    let spawn_graph = match root {
+        Some(root) => spawn_graph.rooted_at(root).unwrap_or_else(|| {
+            eprintln!("No weapon named `{}`.", root);
+
+            process::exit(1);
+        }),
+        None => spawn_graph.linked()
+    };
+
+    match format {
+        GraphFormat::Dot => spawn_graph.to_dot(),
+        GraphFormat::Json => spawn_graph.to_json()
+    }
+}
+
+/// Compare every weapon in the database, field by field, with `other`: another Scaffold database, or a "data"
+/// directory. Exits the process if `other` can't be loaded.
+pub fn diff(connection: &mut SqliteConnection, other: &Path) -> WeaponFileDiff {
+    let other_connection = &mut if other.is_dir() {
+        load_data_dir(other)
+    } else {
+        open_reference_database(other)
+    };
+
+    let before = WeaponFileCollection::get_all_weapon_files(connection);
+    let after = WeaponFileCollection::get_all_weapon_files(other_connection);
+
+    WeaponFileDiff::compare(&before, &after)
+}
+
 fn main() {
     let args = Args::parse();
 
@@ -92,15 +1102,297 @@ fn main() {
 
     connection.run_pending_migrations(MIGRATIONS).expect("Database could not be migrated!");
 
-    if let Some(dir) = args.import_dir {
-        println!("Importing {} to {}...", dir, args.db);
+    match args.command {
+        Some(Command::Import { data_dirs, replace, chunk_size }) => {
+            println!("Importing {} to {}...", data_dirs.join(", "), args.db);
+
+            let options = ImportOptions {
+                replace,
+                chunk_size,
+                quiet: false,
+                source: None
+            };
+
+            let dirs: Vec<PathBuf> = data_dirs.iter().map(PathBuf::from).collect();
 
-        import(connection, Path::new(&dir));
+            for report in import(connection, &dirs, &options) {
+                if dirs.len() > 1 {
+                    println!("layer {}: {}", report.weapons.layer.unwrap_or_default(), report.dir.display());
+                }
+
+                println!("{}", report.weapons);
+
+                if !report.ships.is_empty() {
+                    println!("ships:");
+                    println!("{}", report.ships);
+                }
+
+                if !report.subsystems.is_empty() {
+                    println!("subsystems:");
+                    println!("{}", report.subsystems);
+                }
+
+                if !report.missiles.is_empty() {
+                    println!("missiles:");
+                    println!("{}", report.missiles);
+                }
+
+                if let Some(families) = &report.families {
+                    println!(
+                        "families: {} armour, {} attack, {} display",
+                        families.armour_families.len(),
+                        families.attack_families.len(),
+                        families.display_families.len()
+                    );
+                }
+            }
+        },
+        Some(Command::Export { data_dir, only, pattern, predicate, dry_run, summary, ignore_header, backup_dir, prune, overrides_only, against }) => {
+            let filter = WeaponFilter::new(only, pattern.as_deref(), predicate).unwrap_or_else(|error| {
+                eprintln!("Invalid --match pattern: {}", error);
+
+                process::exit(1);
+            });
+
+            println!("Exporting {} to {}...", args.db, data_dir);
+
+            let options = ExportOptions {
+                dry_run,
+                summary,
+                ignore_header,
+                backup_dir: backup_dir.map(PathBuf::from),
+                prune,
+                overrides_only,
+                against: against.map(PathBuf::from)
+            };
+
+            export(connection, Path::new(&data_dir), &filter, &options);
+        },
+        Some(Command::List { list: ListCommand::Weapons }) => {
+            list_weapons(connection);
+        },
+        Some(Command::List { list: ListCommand::Ships { uses } }) => {
+            list_ships(connection, uses.as_deref());
+        },
+        Some(Command::List { list: ListCommand::Subsystems { uses } }) => {
+            list_subsystems(connection, uses.as_deref());
+        },
+        Some(Command::List { list: ListCommand::Missiles }) => {
+            list_missiles(connection);
+        },
+        Some(Command::List { list: ListCommand::Layers }) => {
+            list_layers(connection);
+        },
+        Some(Command::Show { weapon }) => {
+            show(connection, &weapon);
+        },
+        Some(Command::Validate { data_dir, lint_config, json }) => {
+            let lint_config = match lint_config {
+                Some(path) => LintConfig::from_path(Path::new(&path)).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+
+                    process::exit(1);
+                }),
+                None => LintConfig::default()
+            };
+
+            let report = validate(connection, data_dir.as_deref().map(Path::new), &lint_config, json);
+
+            if json {
+                println!("{}", report.to_json());
+            } else {
+                print_parse_errors(&report.problems);
+
+                for finding in report.findings.iter() {
+                    eprintln!("{}", finding);
+                }
+
+                if !report.problems.is_empty() {
+                    eprintln!("{} problem(s) found.", report.problems.len());
+                }
+
+                if !report.findings.is_empty() {
+                    eprintln!("{} lint error(s), {} warning(s).", report.lint_errors(), report.findings.len() - report.lint_errors());
+                }
+
+                if !report.failed() {
+                    println!("No problems found.");
+                }
+            }
+
+            if report.failed() {
+                process::exit(1);
+            }
+        },
+        Some(Command::Diff { other, json }) => {
+            if json {
+                println!("{}", diff(connection, Path::new(&other)).to_json());
+            } else {
+                println!("Comparing {} with {}...", args.db, other);
+
+                println!("{}", diff(connection, Path::new(&other)));
+            }
+        },
+        Some(Command::Graph { format, root, output }) => {
+            let rendered = graph(connection, format, root.as_deref());
+
+            match output {
+                Some(path) => fs::write(&path, format!("{}\n", rendered)).unwrap_or_else(|error| {
+                    eprintln!("Could not write `{}`: {}", path, error);
+
+                    process::exit(1);
+                }),
+                None => println!("{}", rendered)
+            }
+        },
+        None => {}
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::testing;
+
+    /// A "weapon" directory with a file for every `(path, contents)`.
+    fn weapon_dir(test: &str, files: &[&str]) -> PathBuf {
+        let weapon_dir = testing::temp_dir(test).join("weapon");
+
+        for file in files {
+            let path = weapon_dir.join(file);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, file).unwrap();
+        }
+
+        weapon_dir
+    }
+
+    fn relative(weapon_dir: &Path, paths: &[PathBuf]) -> Vec<String> {
+        paths.iter().map(|p| p.strip_prefix(weapon_dir).unwrap().display().to_string().replace('\\', "/")).collect()
+    }
+
+    #[test]
+    fn only_wepn_files_without_a_weapon_are_stale() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("hgn_flak", ""), ("Kus_IonCannon", "")]);
+
+        let weapon_dir = weapon_dir("only_wepn_files_without_a_weapon_are_stale", &[
+            "hgn_flak/hgn_flak.wepn",
+            "kus_ioncannon/KUS_IONCANNON.wepn",
+            "old/old.wepn",
+            "old/old.lua",
+            "old/old.wepn.bak",
+            "loose.wepn",
+            "notes.txt"
+        ]);
+
+        let stale = find_stale_weapon_files(connection, &weapon_dir);
+
+        assert_eq!(relative(&weapon_dir, &stale), vec!["loose.wepn".to_string(), "old/old.wepn".to_string()]);
+    }
+
+    #[test]
+    fn pruning_leaves_other_files_and_directories_that_are_not_empty() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("hgn_flak", "")]);
+
+        let weapon_dir = weapon_dir("pruning_leaves_other_files_and_directories_that_are_not_empty", &[
+            "hgn_flak/hgn_flak.wepn",
+            "gone/gone.wepn",
+            "old/old.wepn",
+            "old/old.lua",
+            "loose.wepn",
+            "notes.txt"
+        ]);
+
+        let stale = find_stale_weapon_files(connection, &weapon_dir);
+
+        prune(&weapon_dir, &stale, &ExportOptions::default());
+
+        assert!(weapon_dir.join("hgn_flak/hgn_flak.wepn").is_file());
+        assert!(!weapon_dir.join("gone").exists());
+        assert!(!weapon_dir.join("old/old.wepn").exists());
+        assert!(weapon_dir.join("old/old.lua").is_file());
+        assert!(!weapon_dir.join("loose.wepn").exists());
+        assert!(weapon_dir.join("notes.txt").is_file());
+        assert!(find_stale_weapon_files(connection, &weapon_dir).is_empty());
+    }
+
+    #[test]
+    fn pruning_backs_up_stale_files_first() {
+        let connection = &mut testing::database();
+        let weapon_dir = weapon_dir("pruning_backs_up_stale_files_first", &["gone/gone.wepn"]);
+        let backup_dir = weapon_dir.with_file_name("backups");
+
+        let options = ExportOptions {
+            backup_dir: Some(backup_dir.to_path_buf()),
+            ..Default::default()
+        };
+
+        prune(&weapon_dir, &find_stale_weapon_files(connection, &weapon_dir), &options);
+
+        let backups = find_files_with_extension(&backup_dir, "bak");
+
+        assert!(!weapon_dir.join("gone").exists());
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(&backups[0]).unwrap(), "gone/gone.wepn");
+    }
+
+    /// A "data" directory whose `scripts/familylist.lua` has `armour_families`, or that has none if `None`.
+    fn data_dir(test: &str, armour_families: Option<&str>) -> PathBuf {
+        let data_dir = testing::temp_dir(test);
+
+        if let Some(armour_families) = armour_families {
+            let path = FamilyList::path_in_data_dir(&data_dir);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, format!("armourFamily = {{ {} }}", armour_families)).unwrap();
+        }
+
+        data_dir
+    }
+
+    #[test]
+    fn layers_without_a_family_list_keep_the_families() {
+        let connection = &mut testing::database();
+        let with_families = data_dir("layers_without_a_family_list_keep_the_families_1", Some("\"Fighter\""));
+        let without = data_dir("layers_without_a_family_list_keep_the_families_2", None);
+
+        import(connection, &[with_families, without.to_path_buf()], &testing::quiet());
+        import(connection, &[without], &testing::quiet());
+
+        assert_eq!(FamilyList::get(connection).armour_families, vec!["Fighter"]);
+    }
+
+    #[test]
+    fn later_family_lists_replace_earlier_ones() {
+        let connection = &mut testing::database();
+        let first = data_dir("later_family_lists_replace_earlier_ones_1", Some("\"Fighter\""));
+        let second = data_dir("later_family_lists_replace_earlier_ones_2", Some("\"Capital\""));
+
+        import(connection, &[first, second], &testing::quiet());
+
+        assert_eq!(FamilyList::get(connection).armour_families, vec!["Capital"]);
+    }
+
+    #[test]
+    fn replacing_without_a_family_list_clears_the_families() {
+        let connection = &mut testing::database();
+        let with_families = data_dir("replacing_without_a_family_list_clears_the_families_1", Some("\"Fighter\""));
+        let without = data_dir("replacing_without_a_family_list_clears_the_families_2", None);
+
+        import(connection, &[with_families], &testing::quiet());
+
+        let options = ImportOptions {
+            replace: true,
+            ..testing::quiet()
+        };
 
-    if let Some(dir) = args.export_dir {
-        println!("Exporting {} to {}...", args.db, dir);
+        import(connection, &[without], &options);
 
-        export(connection, Path::new(&dir));
+        assert!(FamilyList::get(connection).armour_families.is_empty());
     }
 }
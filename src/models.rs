@@ -1 +1,193 @@
+/// Insert `$rows` into `$table` in batches of `$options.rows_per_insert($columns)` rows, reporting progress unless
+/// `$options.quiet`. Returns an `ImportError` from the enclosing function if a batch is rejected; to report which
+/// weapon (or ship) was at fault, that batch is retried one row at a time until one fails. This only ever runs inside
+/// an import transaction, so none of the retried rows are kept.
+macro_rules! insert_rows {
+    ($connection:expr, $table:expr, $table_name:literal, $columns:literal, $rows:expr, $options:expr, $owner_name:expr) => {
+        let chunk_size = $options.rows_per_insert($columns);
+        let total = $rows.len();
+        let mut inserted = 0;
+
+        for chunk in $rows.chunks(chunk_size) {
+            if let Err(e) = diesel::insert_into($table).values(chunk.to_vec()).execute($connection) {
+                if !$options.quiet {
+                    println!();
+                }
+
+                let owner_name = chunk
+                    .iter()
+                    .find(|row| diesel::insert_into($table).values(**row).execute($connection).is_err())
+                    .map($owner_name);
+
+                return Err(ImportError::new($table_name, owner_name, e));
+            }
+
+            inserted += chunk.len();
+
+            if !$options.quiet {
+                print!("\rinserting {}... {}/{}", $table_name, inserted, total);
+                std::io::stdout().flush().ok();
+            }
+        }
+
+        if total > 0 && !$options.quiet {
+            println!();
+        }
+    };
+}
+
+/// Delete from each table in turn, returning an `ImportError` naming the table from the enclosing function if a delete
+/// fails. With `$names`, only the rows whose `$column` is one of them; otherwise every row.
+macro_rules! delete_rows {
+    ($connection:expr, $names:expr, $($table:ident . $column:ident),+ $(,)?) => {
+        $(
+            diesel::delete($table::table.filter($table::$column.eq_any($names)))
+                .execute($connection)
+                .map_err(|e| ImportError::new(stringify!($table), None, e))?;
+        )+
+    };
+    ($connection:expr, $($table:ident),+ $(,)?) => {
+        $(
+            diesel::delete($table::table)
+                .execute($connection)
+                .map_err(|e| ImportError::new(stringify!($table), None, e))?;
+        )+
+    };
+}
+
+/// Define an enum for a string argument the game only accepts a few values for. Each variant is paired with the
+/// text the game uses for it; any other text becomes `Other`, exactly as written, so mods with their own values
+/// still round-trip. Parsing never fails, `Display` writes the text back out, and the enum is stored as `Text`.
+macro_rules! string_enum {
+    ($(#[$meta:meta])* $name:ident { $($variant:ident => $text:literal),+ $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, diesel::AsExpression, diesel::FromSqlRow)]
+        #[diesel(sql_type = diesel::sql_types::Text)]
+        pub enum $name {
+            $($variant,)+
+            /// A value Scaffold doesn't know, kept as written.
+            Other(String)
+        }
+
+        impl $name {
+            /// The value as the game writes it.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $text,)+
+                    $name::Other(text) => text
+                }
+            }
+
+            /// Is this a value Scaffold doesn't know?
+            pub fn is_other(&self) -> bool {
+                matches!(self, $name::Other(_))
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::Other(String::new())
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.as_str())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(text: &str) -> Self {
+                match text {
+                    $($text => $name::$variant,)+
+                    other => $name::Other(other.to_string())
+                }
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = std::convert::Infallible;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Ok(Self::from(s))
+            }
+        }
+
+        impl diesel::serialize::ToSql<diesel::sql_types::Text, diesel::sqlite::Sqlite> for $name {
+            fn to_sql<'b>(&'b self, out: &mut diesel::serialize::Output<'b, '_, diesel::sqlite::Sqlite>) -> diesel::serialize::Result {
+                out.set_value(self.as_str());
+                Ok(diesel::serialize::IsNull::No)
+            }
+        }
+
+        impl diesel::deserialize::FromSql<diesel::sql_types::Text, diesel::sqlite::Sqlite> for $name {
+            fn from_sql(bytes: diesel::backend::RawValue<'_, diesel::sqlite::Sqlite>) -> diesel::deserialize::Result<Self> {
+                let text = <String as diesel::deserialize::FromSql<diesel::sql_types::Text, diesel::sqlite::Sqlite>>::from_sql(bytes)?;
+
+                Ok(Self::from(text.as_str()))
+            }
+        }
+    };
+}
+
+pub mod lua;
+pub mod parse_error;
+pub mod data_file;
 pub mod wepn_file;
+pub mod ship_file;
+pub mod subs_file;
+pub mod miss_file;
+pub mod family_list;
+
+/// Databases and files for tests.
+#[cfg(test)]
+pub mod testing {
+    use std::fs;
+    use std::path::{ Path, PathBuf };
+    use diesel::prelude::*;
+    use diesel_migrations::MigrationHarness;
+    use super::data_file::{ NewDataFile, NewDataFileCollection };
+    use super::wepn_file::{ ImportOptions, ImportReport, NewWeaponFile, NewWeaponFileCollection };
+
+    /// `StartWeaponConfig(...)` for a plain gun, to start a weapon file with.
+    pub const WEAPON: &str = "StartWeaponConfig(NewWeaponType,\"Gimble\",\"Bullet\",\"Flak\",\"Normal\",1800,2200,0,0,0,0,1,1,1,0.5,0.5,2,0,0,60,60,1,\"Normal\",0,0,0);";
+
+    /// An empty, migrated database in memory, connected to the way `main` connects to one.
+    pub fn database() -> SqliteConnection {
+        let mut connection = crate::establish_connection(Path::new(":memory:"));
+
+        connection.run_pending_migrations(crate::MIGRATIONS).unwrap();
+
+        connection
+    }
+
+    /// Import options that don't print progress.
+    pub fn quiet() -> ImportOptions {
+        ImportOptions {
+            quiet: true,
+            ..Default::default()
+        }
+    }
+
+    /// A weapon file named `name`: `WEAPON` followed by `body`.
+    pub fn weapon_file(name: &str, body: &str) -> NewWeaponFile {
+        NewWeaponFile::from_string(name, &format!("{}\n{}", WEAPON, body)).unwrap()
+    }
+
+    /// Import `(name, body)` weapon files, merging them in by name.
+    pub fn import_weapons(connection: &mut SqliteConnection, weapons: &[(&str, &str)]) -> ImportReport {
+        let files = weapons.iter().map(|(name, body)| weapon_file(name, body)).collect();
+
+        NewWeaponFileCollection::from_vec(files).insert(connection, &quiet()).unwrap()
+    }
+
+    /// An empty directory for `test` to write to, under the system's temporary directory.
+    pub fn temp_dir(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hwrm-scaffold-{}-{}", std::process::id(), test));
+
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+}
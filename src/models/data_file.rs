@@ -0,0 +1,629 @@
+use std::path::{ Path, PathBuf };
+use std::fs;
+use std::io;
+use std::fmt;
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use super::wepn_file::{ ImportOptions, ImportReport, WriteOptions, WriteOutcome, WEAPON_FILE_HEADER, backup_file, contents_match, write_atomically };
+use super::lua::{ self, Argument, Call, Chunk, Statement, StatementKind, Value };
+use super::parse_error::ParseError;
+use super::wepn_file::import_error::ImportError;
+
+/// The anchor of the `index`th modelled call to `call`, e.g. `AddWeaponResult[2]`. Extra statements are stored with
+/// the anchor of the modelled call before them, so they're written back after that call wherever it ends up.
+pub fn anchor(call: &str, index: usize) -> String {
+    format!("{}[{}]", call, index)
+}
+
+/// The statements in `chunk`, less the Scaffold watermark if the file starts with it, so it doesn't pile up on every
+/// round-trip.
+pub fn without_watermark(chunk: &Chunk) -> &[Statement] {
+    let header: Vec<&str> = WEAPON_FILE_HEADER.lines().collect();
+
+    let header_len = chunk.statements
+        .iter()
+        .zip(header.iter())
+        .take_while(|(s, h)| matches!(&s.kind, StatementKind::Comment(c) if c == *h))
+        .count();
+
+    if header_len == header.len() {
+        &chunk.statements[header_len..]
+    } else {
+        &chunk.statements[..]
+    }
+}
+
+/// Pair the source of every statement that isn't a modelled call with the anchor of the modelled call before it, or
+/// `None` before the first one. `modelled` is asked about each call in turn. Comments inside a modelled call are
+/// kept too, right after it, since the call itself is written back from the model.
+pub fn anchored_extras(statements: &[Statement], source: &str, mut modelled: impl FnMut(&Call) -> bool) -> Vec<(Option<String>, String)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut current: Option<String> = None;
+    let mut extras = Vec::new();
+
+    for statement in statements.iter() {
+        match statement.as_call() {
+            Some(call) if modelled(call) => {
+                let count = counts.entry(call.name.as_str()).or_insert(0);
+
+                current = Some(anchor(&call.name, *count));
+                *count += 1;
+
+                for comment in statement.comments.iter() {
+                    extras.push((current.clone(), comment.to_string()));
+                }
+            },
+            _ => extras.push((current.clone(), statement.span.text(source).to_string()))
+        }
+    }
+
+    extras
+}
+
+/// Lay out a file's statements. `modelled` are the statements written from the model, each paired with its anchor
+/// and the section it belongs to; each is followed by the extra statements anchored to it, in order. Extras without
+/// an anchor go first. An extra whose call is gone follows the last call of the same name instead, or the last
+/// statement if there are none. Statements in the same section are on consecutive lines, and sections are separated
+/// by a blank line. Extras are in `extra_section` if there is one; otherwise they join the section of the call they
+/// follow.
+pub fn lay_out(modelled: &[(String, &str, String)], extras: &[(Option<&str>, String)], extra_section: Option<&str>) -> String {
+    let call_of = |anchor: &str| anchor.split('[').next().unwrap_or(anchor).to_string();
+
+    // Index of the modelled statement each extra follows.
+    let follows: Vec<Option<usize>> = extras
+        .iter()
+        .map(|(anchor, _)| {
+            let anchor = (*anchor)?;
+
+            modelled
+                .iter()
+                .position(|(a, ..)| a == anchor)
+                .or_else(|| modelled.iter().rposition(|(a, ..)| call_of(a) == call_of(anchor)))
+                .or(modelled.len().checked_sub(1))
+        })
+        .collect();
+
+    let mut statements: Vec<(&str, &str)> = extras
+        .iter()
+        .zip(follows.iter())
+        .filter(|(_, follows)| follows.is_none())
+        .map(|((_, extra), _)| (extra_section.unwrap_or_default(), extra.as_str()))
+        .collect();
+
+    for (index, (_, section, statement)) in modelled.iter().enumerate() {
+        statements.push((section, statement));
+
+        for ((_, extra), _) in extras.iter().zip(follows.iter()).filter(|(_, follows)| **follows == Some(index)) {
+            statements.push((extra_section.unwrap_or(section), extra));
+        }
+    }
+
+    let mut joined = String::new();
+
+    for (i, (section, statement)) in statements.iter().enumerate() {
+        if i > 0 {
+            joined.push_str(if statements[i - 1].0 == *section { "\r\n" } else { "\r\n\r\n" });
+        }
+
+        joined.push_str(statement);
+    }
+
+    joined
+}
+
+///
+/// Property Model
+///
+/// A model (e.g. `Ship`) read from `<Type>.<property> = <value>` assignments following a `<Type> = Start...Config()`
+/// line. Those lines are kept with the extra statements, so the model is written back in the same place and form as
+/// it was read; only the values come from the model. See `fill_in_properties`.
+///
+pub trait PropertyModel {
+    /// The start line, the way Scaffold writes it when a file doesn't have one, e.g. `NewShipType = StartShipConfig()`.
+    const START: &'static str;
+
+    /// Is `statement` the start line?
+    fn is_start(statement: &Statement) -> bool;
+
+    /// If `statement` sets one of the modelled properties to a value Scaffold can store, return the property and the
+    /// argument holding the value.
+    fn property(statement: &Statement) -> Option<(&'static str, &Argument)>;
+
+    /// Each property that's set, in the order Scaffold writes them, with its value as Lua (see `lua_value`).
+    fn values(&self) -> Vec<(&'static str, String)>;
+
+    /// The line setting `property` to `value`, for a property the file didn't set.
+    fn statement(property: &str, value: &str) -> String;
+}
+
+/// A value a `PropertyModel` stores, as Lua: a number the way it's stored, or a double quoted string.
+pub fn lua_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => Some((*n as f32).to_string()),
+        Value::String(s) => Some(format!("\"{}\"", s)),
+        _ => None
+    }
+}
+
+/// Put `model`'s values into the lines it was read from, which are among the anchored `extras`. The first start line
+/// and the first line setting each property are the ones that were read, as when importing. Each of those is kept as
+/// written if its value hasn't changed, has the new value put in its place otherwise, and is dropped if the property
+/// is no longer set. Properties the file didn't set follow the start line, which goes first if it's missing too.
+pub fn fill_in_properties<'a, P: PropertyModel>(model: &P, extras: &[(Option<&'a str>, String)]) -> Vec<(Option<&'a str>, String)> {
+    let values = model.values();
+
+    let statements: Vec<Option<Statement>> = extras
+        .iter()
+        .map(|(_, extra)| lua::parse(extra).ok().filter(|c| c.statements.len() == 1).and_then(|c| c.statements.into_iter().next()))
+        .collect();
+
+    let mut start = None;
+    let mut lines: HashMap<&str, usize> = HashMap::new();
+
+    for (index, statement) in statements.iter().enumerate() {
+        match statement {
+            Some(statement) if P::is_start(statement) => {
+                start.get_or_insert(index);
+            },
+            Some(statement) => {
+                if let Some((property, _)) = P::property(statement) {
+                    lines.entry(property).or_insert(index);
+                }
+            },
+            None => {}
+        }
+    }
+
+    let unwritten: Vec<String> = values
+        .iter()
+        .filter(|(property, _)| !lines.contains_key(property))
+        .map(|(property, value)| P::statement(property, value))
+        .collect();
+
+    let mut filled = Vec::new();
+
+    if start.is_none() {
+        filled.push((None, P::START.to_string()));
+        filled.extend(unwritten.iter().map(|s| (None, s.to_string())));
+    }
+
+    for (index, ((anchor, extra), statement)) in extras.iter().zip(statements.iter()).enumerate() {
+        let read = statement
+            .as_ref()
+            .and_then(P::property)
+            .filter(|(property, _)| lines.get(property) == Some(&index));
+
+        match read {
+            Some((property, argument)) => match values.iter().find(|(p, _)| *p == property) {
+                Some((_, value)) if lua_value(&argument.value).as_ref() == Some(value) => filled.push((*anchor, extra.to_string())),
+                Some((_, value)) => {
+                    let span = &argument.span;
+
+                    filled.push((*anchor, format!("{}{}{}", &extra[..span.start], value, &extra[span.end..])));
+                },
+                None => {}
+            },
+            None => filled.push((*anchor, extra.to_string()))
+        }
+
+        if start == Some(index) {
+            filled.extend(unwritten.iter().map(|s| (*anchor, s.to_string())));
+        }
+    }
+
+    filled
+}
+
+///
+/// Data File
+///
+/// A file Scaffold writes out to a "data" directory, e.g. a `WeaponFile`. Each one lives in a containment directory
+/// named after it, inside the directory for its kind, e.g. "data/weapon/<name>/<name>.wepn". `Display` renders it.
+///
+pub trait DataFile: fmt::Display {
+    /// The file's extension, e.g. "wepn".
+    const EXTENSION: &'static str;
+
+    /// The name of the weapon (or ship, ...), which the file and its containment directory are named after.
+    fn name(&self) -> &str;
+
+    /// Write the file to disk. See `write_atomically`.
+    fn write_to_disk(&self, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            return Err(io::Error::other("Cannot write a data file to a directory"));
+        }
+
+        write_atomically(path, &self.to_string())
+    }
+
+    /// Write the file out to a directory.
+    fn write_to_dir(&self, dir: &Path) -> io::Result<()> {
+        if dir.is_dir() {
+            self.write_to_disk(&dir.join(format!("{}.{}", self.name(), Self::EXTENSION)))
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, "Not a directory"))
+        }
+    }
+
+    /// Path that `write_to_kind_dir` writes this file to, e.g. "<kind_dir>/<name>/<name>.wepn".
+    fn path_in_kind_dir(&self, kind_dir: &Path) -> PathBuf {
+        kind_dir
+            .join(self.name())
+            .join(format!("{}.{}", self.name(), Self::EXTENSION))
+    }
+
+    /// Does `contents` match what this file renders to? With `ignore_header`, the watermark is left out of the
+    /// comparison.
+    fn matches_contents(&self, contents: &str, ignore_header: bool) -> bool {
+        contents_match(&self.to_string(), contents, ignore_header)
+    }
+
+    /// Write out the file to the directory for its kind (e.g. "weapon"), unless the file already there has the same
+    /// contents. Leaves unchanged files alone so their modification times stay put. With `options.backup_dir`, the
+    /// file being replaced is archived there first.
+    fn write_changes_to_kind_dir(&self, kind_dir: &Path, options: &WriteOptions) -> io::Result<WriteOutcome> {
+        let outcome = match fs::read(self.path_in_kind_dir(kind_dir)) {
+            Ok(bytes) if self.matches_contents(&String::from_utf8_lossy(&bytes), options.ignore_header) => {
+                return Ok(WriteOutcome::Unchanged);
+            },
+            Ok(_) => WriteOutcome::Updated,
+            Err(_) => WriteOutcome::Created
+        };
+
+        if let (WriteOutcome::Updated, Some(backup_dir)) = (outcome, &options.backup_dir) {
+            self.backup_from_kind_dir(kind_dir, backup_dir)?;
+        }
+
+        self.write_to_kind_dir(kind_dir)?;
+
+        Ok(outcome)
+    }
+
+    /// Copy the current file in the directory for its kind into `backup_dir`. See `backup_file`.
+    fn backup_from_kind_dir(&self, kind_dir: &Path, backup_dir: &Path) -> io::Result<PathBuf> {
+        backup_file(&self.path_in_kind_dir(kind_dir), backup_dir)
+    }
+
+    /// Write out the file to the directory for its kind. This creates the containment directory as well.
+    fn write_to_kind_dir(&self, kind_dir: &Path) -> io::Result<()> {
+        if !kind_dir.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "Not a directory"));
+        }
+
+        let containment_dir = kind_dir.join(self.name());
+
+        if !containment_dir.exists() {
+            fs::create_dir(&containment_dir)?;
+        }
+
+        self.write_to_dir(containment_dir.as_path())
+    }
+}
+
+///
+/// New Data File
+///
+/// A file read in from a "data" directory, ready to be inserted, e.g. a `NewWeaponFile`.
+///
+pub trait NewDataFile: Sized {
+    /// The file's extension, e.g. "wepn".
+    const EXTENSION: &'static str;
+
+    /// Parse the body of a file, named `name`.
+    fn from_string(name: &str, contents: &str) -> Result<Self, ParseError>;
+
+    /// Parse the file at `path`. It's named after the file, less its extension, which has to be valid UTF-8.
+    fn from_path(path: &Path) -> Result<Self, ParseError> {
+        let name = path.file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .ok_or_else(|| ParseError::new("File name is not valid UTF-8").with_path(path))?
+                        .to_string();
+
+        let bytes = fs::read(path)
+                        .map_err(|e| ParseError::new(&format!("Could not read file: {}", e)).with_path(path))?;
+
+        // Some files shipped with the game aren't valid UTF-8 (e.g. Latin-1 comments), which is harmless.
+        let contents = String::from_utf8_lossy(&bytes);
+
+        Self::from_string(&name, &contents).map_err(|e| e.with_path(path))
+    }
+}
+
+///
+/// New Data File Collection
+///
+/// Every `NewDataFile` of one kind read from a "data" directory, and how to put them in the database in place of
+/// what's there.
+///
+pub trait NewDataFileCollection: Sized {
+    type File: NewDataFile;
+
+    /// Create from a Vector of new files.
+    fn from_vec(files: Vec<Self::File>) -> Self;
+
+    /// Get list of names in the collection.
+    fn get_names(&self) -> Vec<String>;
+
+    /// Get the name of every file of this kind in the database.
+    fn get_all_names(connection: &mut SqliteConnection) -> Vec<String>;
+
+    /// Render the named files from the database, keyed by name.
+    fn render(connection: &mut SqliteConnection, names: &[String]) -> HashMap<String, String>;
+
+    /// Delete the named rows, and everything belonging to them.
+    fn delete_named(connection: &mut SqliteConnection, names: &[String]) -> Result<(), ImportError>;
+
+    /// Delete every row of this kind from the database.
+    fn delete_all(connection: &mut SqliteConnection) -> Result<(), ImportError>;
+
+    /// Insert every row in the collection, `options.chunk_size` rows per statement.
+    fn insert_rows(&self, connection: &mut SqliteConnection, options: &ImportOptions) -> Result<(), ImportError>;
+
+    /// Called once the collection has been inserted, with the files as they now render, to record anything else
+    /// about the import. Does nothing by default.
+    fn after_insert(_connection: &mut SqliteConnection, _options: &ImportOptions, _after: &HashMap<String, String>, _report: &mut ImportReport) -> Result<(), ImportError> {
+        Ok(())
+    }
+
+    /// Consumes the collection, inserts into database. By default, files are merged in by name: any file in the
+    /// collection replaces its previous version, and ones only found in the database are kept. With
+    /// `options.replace`, every one is deleted first.
+    /// Runs in a single transaction; if anything fails, the database is left untouched.
+    fn insert(self, connection: &mut SqliteConnection, options: &ImportOptions) -> Result<ImportReport, ImportError> {
+        connection.transaction(|connection| {
+            let names = self.get_names();
+            let name_chunk_size = options.rows_per_insert(1);
+            let before = render_in_chunks::<Self>(connection, &names, name_chunk_size);
+
+            let removed = if options.replace {
+                let all_names = Self::get_all_names(connection);
+
+                Self::delete_all(connection)?;
+
+                all_names.into_iter().filter(|n| !names.contains(n)).collect()
+            } else {
+                for chunk in names.chunks(name_chunk_size) {
+                    Self::delete_named(connection, chunk)?;
+                }
+
+                Vec::new()
+            };
+
+            self.insert_rows(connection, options)?;
+
+            let after = render_in_chunks::<Self>(connection, &names, name_chunk_size);
+            let mut report = ImportReport::compare(&before, &after, removed);
+
+            Self::after_insert(connection, options, &after, &mut report)?;
+
+            Ok(report)
+        })
+    }
+}
+
+/// Render the named files from the database, a batch of names at a time.
+fn render_in_chunks<C: NewDataFileCollection>(connection: &mut SqliteConnection, names: &[String], chunk_size: usize) -> HashMap<String, String> {
+    let mut rendered = HashMap::new();
+
+    for chunk in names.chunks(chunk_size) {
+        rendered.extend(C::render(connection, chunk));
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::lua;
+    use crate::models::testing;
+    use crate::models::wepn_file::{ NewWeaponFileCollection, WeaponFileCollection };
+
+    fn extras(source: &str, calls: &[&str]) -> Vec<(Option<String>, String)> {
+        let chunk = lua::parse(source).unwrap();
+
+        anchored_extras(without_watermark(&chunk), source, |call| calls.contains(&call.name.as_str()))
+    }
+
+    #[test]
+    fn extras_are_anchored_to_the_call_before_them() {
+        let source = "-- top\nf(1)\n-- after f\ng(2, -- inside g\n  3)\nh()\nf(4)\n-- after second f";
+
+        assert_eq!(extras(source, &["f", "g"]), vec![
+            (None, "-- top".to_string()),
+            (Some("f[0]".to_string()), "-- after f".to_string()),
+            (Some("g[0]".to_string()), "-- inside g".to_string()),
+            (Some("g[0]".to_string()), "h()".to_string()),
+            (Some("f[1]".to_string()), "-- after second f".to_string())
+        ]);
+    }
+
+    #[test]
+    fn extras_follow_their_call_wherever_it_is_written() {
+        let modelled = vec![
+            (anchor("f", 0), "f", "f(1)".to_string()),
+            (anchor("g", 0), "g", "g(2, 3)".to_string())
+        ];
+
+        let extras = vec![
+            (None, "-- top".to_string()),
+            (Some("g[0]"), "-- after g".to_string()),
+            (Some("f[0]"), "-- after f".to_string()),
+            (Some("f[3]"), "-- after a missing f".to_string()),
+            (Some("k[0]"), "-- after k".to_string())
+        ];
+
+        assert_eq!(
+            lay_out(&modelled, &extras, None),
+            "-- top\r\n\r\nf(1)\r\n-- after f\r\n-- after a missing f\r\n\r\ng(2, 3)\r\n-- after g\r\n-- after k"
+        );
+
+        assert_eq!(
+            lay_out(&modelled, &extras[..3], Some("extra")),
+            "-- top\r\n\r\nf(1)\r\n\r\n-- after f\r\n\r\ng(2, 3)\r\n\r\n-- after g"
+        );
+    }
+
+    #[test]
+    fn properties_are_written_back_where_they_were_read() {
+        use crate::models::ship_file::ship::Ship;
+
+        let ship = Ship {
+            name: "tst".to_string(),
+            max_health: Some(7.5),
+            build_cost: Some(35.0),
+            build_time: Some(3.0),
+            ..Default::default()
+        };
+
+        let extras = vec![
+            (None, "NewShipType = StartShipConfig()".to_string()),
+            (None, "NewShipType.armourFamily = \"Frigate\"".to_string()),
+            (None, "NewShipType.maxhealth=getShipNum(NewShipType,\"maxhealth\",5)".to_string()),
+            (Some("addAbility[0]"), "NewShipType.buildCost = 35.0".to_string()),
+            (Some("addAbility[0]"), "NewShipType.buildCost = 40".to_string())
+        ];
+
+        assert_eq!(fill_in_properties(&ship, &extras), vec![
+            (None, "NewShipType = StartShipConfig()".to_string()),
+            (None, "NewShipType.buildTime = 3".to_string()),
+            (None, "NewShipType.maxhealth=getShipNum(NewShipType,\"maxhealth\",7.5)".to_string()),
+            (Some("addAbility[0]"), "NewShipType.buildCost = 35.0".to_string()),
+            (Some("addAbility[0]"), "NewShipType.buildCost = 40".to_string())
+        ]);
+
+        assert_eq!(fill_in_properties(&ship, &[]), vec![
+            (None, "NewShipType = StartShipConfig()".to_string()),
+            (None, "NewShipType.maxhealth = getShipNum(NewShipType, \"maxhealth\", 7.5)".to_string()),
+            (None, "NewShipType.buildCost = 35".to_string()),
+            (None, "NewShipType.buildTime = 3".to_string())
+        ]);
+    }
+
+    fn weapon_names(connection: &mut SqliteConnection) -> Vec<String> {
+        let mut names: Vec<String> = WeaponFileCollection::get_all_weapon_files(connection).weapon_files.into_keys().collect();
+
+        names.sort();
+
+        names
+    }
+
+    #[test]
+    fn imports_merge_in_by_name() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("a", ""), ("b", "")]);
+
+        let report = testing::import_weapons(connection, &[
+            ("b", "setAccuracy(NewWeaponType,1,{Fighter=0.5});"),
+            ("c", "")
+        ]);
+
+        assert_eq!(report.added, vec!["c"]);
+        assert_eq!(report.changed, vec!["b"]);
+        assert!(report.unchanged.is_empty());
+        assert!(report.removed.is_empty());
+        assert_eq!(weapon_names(connection), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn reimporting_a_weapon_as_it_was_leaves_it_unchanged() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("a", "-- comment"), ("b", "")]);
+
+        let report = testing::import_weapons(connection, &[("a", "-- comment")]);
+
+        assert!(report.added.is_empty());
+        assert!(report.changed.is_empty());
+        assert_eq!(report.unchanged, vec!["a"]);
+        assert_eq!(report.to_string().lines().last(), Some("0 added, 0 changed, 1 unchanged, 0 removed."));
+    }
+
+    #[test]
+    fn replacing_removes_weapons_that_are_not_imported() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("a", ""), ("b", "")]);
+
+        let options = ImportOptions {
+            replace: true,
+            ..testing::quiet()
+        };
+
+        let report = NewWeaponFileCollection::from_vec(vec![testing::weapon_file("b", ""), testing::weapon_file("c", "")])
+            .insert(connection, &options)
+            .unwrap();
+
+        assert_eq!(report.added, vec!["c"]);
+        assert_eq!(report.unchanged, vec!["b"]);
+        assert_eq!(report.removed, vec!["a"]);
+        assert_eq!(weapon_names(connection), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn a_weapon_that_cannot_be_written_rolls_back_the_whole_import() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("a", "")]);
+
+        let before = WeaponFileCollection::get_all_weapon_files(connection).rendered();
+        let files = vec![
+            testing::weapon_file("a", "setAccuracy(NewWeaponType,1,{Fighter=0.5});"),
+            testing::weapon_file("bad", "setPenetration(NewWeaponType,5,1,{Fighter=1},{Fighter=2});"),
+            testing::weapon_file("c", "")
+        ];
+
+        let error = NewWeaponFileCollection::from_vec(files).insert(connection, &testing::quiet()).unwrap_err();
+
+        assert_eq!(error.table.as_deref(), Some("weapon_penetrations"));
+        assert_eq!(error.weapon_name.as_deref(), Some("bad"));
+        assert!(error.to_string().starts_with("Could not write to `weapon_penetrations` for `bad`: "));
+        assert_eq!(WeaponFileCollection::get_all_weapon_files(connection).rendered(), before);
+    }
+
+    #[test]
+    fn imports_in_chunks_of_chunk_size_rows() {
+        let connection = &mut testing::database();
+        let options = ImportOptions {
+            chunk_size: Some(2),
+            ..testing::quiet()
+        };
+
+        let files = ["a", "b", "c", "d", "e"].iter()
+            .map(|name| testing::weapon_file(name, "setPenetration(NewWeaponType,5,1,{Fighter=1},{Frigate=0.5});"))
+            .collect();
+
+        let report = NewWeaponFileCollection::from_vec(files).insert(connection, &options).unwrap();
+
+        assert_eq!(report.added, vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(weapon_names(connection), vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn a_rejected_chunk_is_retried_row_by_row_to_find_the_weapon() {
+        let connection = &mut testing::database();
+        let options = ImportOptions {
+            chunk_size: Some(3),
+            ..testing::quiet()
+        };
+
+        let files = vec![
+            testing::weapon_file("a", "setPenetration(NewWeaponType,5,1,{Fighter=1});"),
+            testing::weapon_file("b", "setPenetration(NewWeaponType,5,1,{Fighter=1});"),
+            testing::weapon_file("c", "setPenetration(NewWeaponType,5,1,{Fighter=1});"),
+            testing::weapon_file("d", "setPenetration(NewWeaponType,5,1,{Fighter=1});"),
+            testing::weapon_file("bad", "setPenetration(NewWeaponType,5,1,{Fighter=1},{Fighter=2});")
+        ];
+
+        // The second chunk of penetrations is `d`'s and both of `bad`'s, so the first row of it isn't the one at fault.
+
+        let error = NewWeaponFileCollection::from_vec(files).insert(connection, &options).unwrap_err();
+
+        assert_eq!(error.table.as_deref(), Some("weapon_penetrations"));
+        assert_eq!(error.weapon_name.as_deref(), Some("bad"));
+        assert!(weapon_names(connection).is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+use std::path::{ Path, PathBuf };
+use std::io::prelude::*;
+use std::fs;
+use std::collections::HashSet;
+
+use diesel::prelude::*;
+
+use crate::schema::{ armour_families, attack_families, display_families };
+use super::wepn_file::ImportOptions;
+use super::lua::{ self, Field, StatementKind, Value };
+use super::parse_error::ParseError;
+use super::wepn_file::import_error::ImportError;
+
+/// Where the family list lives, relative to a "data" directory.
+pub const FAMILY_LIST_PATH: &str = "scripts/familylist.lua";
+
+///
+/// Armour Family
+///
+/// A name `setPenetration` and `setAccuracy` can refer to.
+///
+#[derive(Queryable, Selectable, Identifiable, Insertable, Debug)]
+#[diesel(table_name = armour_families)]
+#[diesel(primary_key(name))]
+pub struct ArmourFamily {
+    pub name: String
+}
+
+///
+/// Attack Family
+///
+#[derive(Queryable, Selectable, Identifiable, Insertable, Debug)]
+#[diesel(table_name = attack_families)]
+#[diesel(primary_key(name))]
+pub struct AttackFamily {
+    pub name: String
+}
+
+///
+/// Display Family
+///
+#[derive(Queryable, Selectable, Identifiable, Insertable, Debug)]
+#[diesel(table_name = display_families)]
+#[diesel(primary_key(name))]
+pub struct DisplayFamily {
+    pub name: String
+}
+
+/// Name of a family entry; either `{ name = "Fighter", ... }` or just `"Fighter"`.
+fn family_name(field: &Field) -> Option<&str> {
+    match &field.value {
+        Value::String(name) => Some(name),
+        Value::Table(fields) => fields
+            .iter()
+            .find(|f| f.name() == Some("name"))
+            .and_then(|f| f.value.as_str()),
+        _ => None
+    }
+}
+
+///
+/// Family List (scripts/familylist.lua)
+///
+/// The armour, attack and display families the game knows about, in the order they're listed. Other kinds of family
+/// in the file are ignored. This is reference data; Scaffold reads it but never writes it back.
+///
+#[derive(Default, Debug)]
+pub struct FamilyList {
+    pub armour_families: Vec<String>,
+    pub attack_families: Vec<String>,
+    pub display_families: Vec<String>
+}
+
+impl FamilyList {
+    /// Path of the family list in a "data" directory.
+    pub fn path_in_data_dir(data_dir: &Path) -> PathBuf {
+        data_dir.join(FAMILY_LIST_PATH)
+    }
+
+    /// Read `scripts/familylist.lua` from a "data" directory, if it has one.
+    pub fn from_data_dir(data_dir: &Path) -> Option<Result<Self, ParseError>> {
+        let path = Self::path_in_data_dir(data_dir);
+
+        if path.is_file() {
+            Some(Self::from_path(&path))
+        } else {
+            None
+        }
+    }
+
+    /// Create new `FamilyList` from `Path`
+    pub fn from_path(path: &Path) -> Result<Self, ParseError> {
+        let bytes = fs::read(path)
+                        .map_err(|e| ParseError::new(&format!("Could not read file: {}", e)).with_path(path))?;
+
+        let contents = String::from_utf8_lossy(&bytes);
+
+        Self::from_string(&contents).map_err(|e| e.with_path(path))
+    }
+
+    /// Create new `FamilyList` from the body of a familylist.lua file. Each of `armourFamily`, `attackFamily` and
+    /// `displayFamily` is a table of entries with a `name`.
+    pub fn from_string(contents: &str) -> Result<Self, ParseError> {
+        let chunk = lua::parse(contents)?;
+        let mut family_list = Self::default();
+
+        for statement in chunk.statements.iter() {
+            let StatementKind::Assignment(assignment) = &statement.kind else {
+                continue;
+            };
+
+            if assignment.targets.len() != 1 || assignment.values.len() != 1 {
+                continue;
+            }
+
+            let target = assignment.targets[0].as_str();
+
+            let families = match target {
+                "armourFamily" => &mut family_list.armour_families,
+                "attackFamily" => &mut family_list.attack_families,
+                "displayFamily" => &mut family_list.display_families,
+                _ => continue
+            };
+
+            let fields = assignment.values[0].value
+                .as_table()
+                .ok_or_else(|| ParseError::new(&format!("`{}` is not a table", target)))?;
+
+            for field in fields {
+                let name = family_name(field)
+                    .ok_or_else(|| ParseError::new(&format!("Family without a name in `{}`", target)))?;
+
+                if !families.iter().any(|f| f == name) {
+                    families.push(name.to_string());
+                }
+            }
+        }
+
+        Ok(family_list)
+    }
+
+    /// Get the family list from the Database. Names are sorted, since the original order isn't kept.
+    pub fn get(connection: &mut SqliteConnection) -> Self {
+        let mut armour = armour_families::table
+                            .select(armour_families::name)
+                            .load::<String>(connection)
+                            .expect("Could not load armour_families table!");
+
+        let mut attack = attack_families::table
+                            .select(attack_families::name)
+                            .load::<String>(connection)
+                            .expect("Could not load attack_families table!");
+
+        let mut display = display_families::table
+                            .select(display_families::name)
+                            .load::<String>(connection)
+                            .expect("Could not load display_families table!");
+
+        armour.sort();
+        attack.sort();
+        display.sort();
+
+        Self {
+            armour_families: armour,
+            attack_families: attack,
+            display_families: display
+        }
+    }
+
+    /// Lowercased armour family names, for comparing without regard to case the way the game does.
+    pub fn armour_family_set(&self) -> HashSet<String> {
+        self.armour_families.iter().map(|f| f.to_lowercase()).collect()
+    }
+
+    /// Delete every family from the database.
+    pub fn delete_all(connection: &mut SqliteConnection) -> Result<(), ImportError> {
+        delete_rows!(connection, armour_families, attack_families, display_families);
+
+        Ok(())
+    }
+
+    /// Insert the family list into the database, replacing every family already there. Meant to run inside the
+    /// import transaction.
+    pub fn insert(&self, connection: &mut SqliteConnection, options: &ImportOptions) -> Result<(), ImportError> {
+        Self::delete_all(connection)?;
+
+        let armour: Vec<ArmourFamily> = self.armour_families.iter().map(|name| ArmourFamily { name: name.to_string() }).collect();
+        let attack: Vec<AttackFamily> = self.attack_families.iter().map(|name| AttackFamily { name: name.to_string() }).collect();
+        let display: Vec<DisplayFamily> = self.display_families.iter().map(|name| DisplayFamily { name: name.to_string() }).collect();
+
+        let armour_to_insert: Vec<&ArmourFamily> = armour.iter().collect();
+        let attack_to_insert: Vec<&AttackFamily> = attack.iter().collect();
+        let display_to_insert: Vec<&DisplayFamily> = display.iter().collect();
+
+        insert_rows!(connection, armour_families::table, "armour_families", 1, armour_to_insert, options, |x| x.name.to_string());
+        insert_rows!(connection, attack_families::table, "attack_families", 1, attack_to_insert, options, |x| x.name.to_string());
+        insert_rows!(connection, display_families::table, "display_families", 1, display_to_insert, options, |x| x.name.to_string());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing;
+
+    const FAMILY_LIST: &str = r#"
+armourFamily = {
+    { name = "Fighter", },
+    { name = "Frigate", },
+    "ResArmour",
+}
+attackFamily = {
+    { name = "Fighter", },
+    { name = "Capital", },
+}
+displayFamily = {
+    { name = "Fighter", },
+}
+"#;
+
+    #[test]
+    fn reads_armour_attack_and_display_families_in_order() {
+        let family_list = FamilyList::from_string(FAMILY_LIST).unwrap();
+
+        assert_eq!(family_list.armour_families, vec!["Fighter", "Frigate", "ResArmour"]);
+        assert_eq!(family_list.attack_families, vec!["Fighter", "Capital"]);
+        assert_eq!(family_list.display_families, vec!["Fighter"]);
+    }
+
+    #[test]
+    fn ignores_other_families_and_statements() {
+        let family_list = FamilyList::from_string(&format!("{}{}", r#"
+dofilepath("data:scripts/familylist_extra.lua")
+collisionFamily = { { name = "Small", }, }
+addArmourFamily("Hidden")
+local armourFamilies = 3
+armourFamily, attackFamily = {}, {}
+"#, FAMILY_LIST)).unwrap();
+
+        assert_eq!(family_list.armour_families, vec!["Fighter", "Frigate", "ResArmour"]);
+        assert_eq!(family_list.attack_families, vec!["Fighter", "Capital"]);
+    }
+
+    #[test]
+    fn lists_a_family_once() {
+        let family_list = FamilyList::from_string("armourFamily = { \"Fighter\", { name = \"Fighter\" } }").unwrap();
+
+        assert_eq!(family_list.armour_families, vec!["Fighter"]);
+    }
+
+    #[test]
+    fn rejects_families_that_are_not_tables_or_have_no_name() {
+        assert!(FamilyList::from_string("armourFamily = \"Fighter\"").is_err());
+        assert!(FamilyList::from_string("attackFamily = { { size = 3 } }").is_err());
+    }
+
+    #[test]
+    fn inserting_replaces_the_families_in_the_database() {
+        let connection = &mut testing::database();
+
+        FamilyList::from_string(FAMILY_LIST).unwrap().insert(connection, &testing::quiet()).unwrap();
+        FamilyList::from_string("armourFamily = { \"Capital\" }").unwrap().insert(connection, &testing::quiet()).unwrap();
+
+        let family_list = FamilyList::get(connection);
+
+        assert_eq!(family_list.armour_families, vec!["Capital"]);
+        assert!(family_list.attack_families.is_empty());
+        assert!(family_list.display_families.is_empty());
+    }
+}
@@ -0,0 +1,1409 @@
+//! Lua Tokenizer & Parser
+//!
+//! Homeworld data files (*.wepn, *.ship, ...) are plain Lua scripts made up of top level function calls and
+//! assignments. This module turns a file body into a list of `Statement`s, where function calls have their
+//! arguments parsed into `Value`s, so the models don't need to pick apart the raw text themselves.
+
+use std::fmt;
+
+/// Lua keywords; these can never be used as names.
+const KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while"
+];
+
+/// Multi-character symbols, longest first so they win over their prefixes.
+const SYMBOLS: [&str; 33] = [
+    "...", "==", "~=", "<=", ">=", "<<", ">>", "//", "..", "::",
+    "+", "-", "*", "/", "%", "^", "#", "&", "~", "|", "<", ">", "=",
+    "(", ")", "{", "}", "[", "]", ";", ":", ",", "."
+];
+
+/// Error raised when a body cannot be tokenized or parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuaError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize
+}
+
+impl fmt::Display for LuaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for LuaError {}
+
+/// Error raised when an argument of a call is missing or isn't of the expected type. `line` and `column` point at the
+/// argument, or at the call if the argument is missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentError {
+    pub call: String,
+    pub index: usize,
+    pub message: String,
+    pub line: usize,
+    pub column: usize
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {} (`{}` argument {})", self.line, self.column, self.message, self.call, self.index)
+    }
+}
+
+impl std::error::Error for ArgumentError {}
+
+/// Location of a token or statement in the source. `start` and `end` are byte offsets, `line` and `column` are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+impl Span {
+    /// The source text covered by this span.
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+/// Value of the digits of a hexadecimal number after `0x`. Like Lua, allows a fraction and a binary exponent, e.g.
+/// `1.8p3`.
+fn hex_value(digits: &str) -> Option<f64> {
+    let (mantissa, exponent) = match digits.find(['p', 'P']) {
+        Some(index) => (&digits[..index], digits[index + 1..].parse::<i32>().ok()?),
+        None => (digits, 0)
+    };
+
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+
+    let mut value = 0.0;
+    let mut scale = 1.0 / 16.0;
+
+    for c in whole.chars() {
+        value = value * 16.0 + c.to_digit(16)? as f64;
+    }
+
+    for c in fraction.chars() {
+        value += c.to_digit(16)? as f64 * scale;
+        scale /= 16.0;
+    }
+
+    Some(value * 2f64.powi(exponent))
+}
+
+///
+/// Tokens
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Name(String),
+    Number(f64),
+    String(String),
+    Symbol(&'static str),
+    Comment(String),
+    Eof
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span
+}
+
+impl Token {
+    /// Is this token the given symbol?
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.kind, TokenKind::Symbol(s) if s == symbol)
+    }
+
+    /// Is this token the given keyword?
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.kind, TokenKind::Name(n) if n == keyword)
+    }
+}
+
+/// Splits a body into `Token`s. Comments are kept as tokens so statements can be reproduced.
+pub struct Tokenizer<'a> {
+    source: &'a str,
+    position: usize,
+    line: usize,
+    column: usize
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            position: 0,
+            line: 1,
+            column: 1
+        }
+    }
+
+    /// Tokenize the whole body. The last token is always `TokenKind::Eof`.
+    pub fn tokenize(mut self) -> Result<Vec<Token>, LuaError> {
+        let mut tokens = Vec::new();
+
+        loop {
+            let token = self.next_token()?;
+            let eof = token.kind == TokenKind::Eof;
+
+            tokens.push(token);
+
+            if eof {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+
+        self.position += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> LuaError {
+        LuaError {
+            message: message.to_string(),
+            line: self.line,
+            column: self.column
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == '\u{feff}' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, LuaError> {
+        self.skip_whitespace();
+
+        let start = self.position;
+        let line = self.line;
+        let column = self.column;
+
+        let kind = match self.peek() {
+            None => TokenKind::Eof,
+            Some('-') if self.peek_nth(1) == Some('-') => self.comment()?,
+            Some(c) if c.is_alphabetic() || c == '_' => self.name(),
+            Some(c) if c.is_ascii_digit() => self.number()?,
+            Some('.') if self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) => self.number()?,
+            Some(q) if q == '"' || q == '\'' => self.quoted_string(q)?,
+            Some('[') if matches!(self.peek_nth(1), Some('[') | Some('=')) && self.long_bracket_level().is_some() => {
+                TokenKind::String(self.long_bracket()?)
+            },
+            Some(_) => self.symbol()?
+        };
+
+        Ok(Token {
+            kind,
+            span: Span {
+                start,
+                end: self.position,
+                line,
+                column
+            }
+        })
+    }
+
+    fn comment(&mut self) -> Result<TokenKind, LuaError> {
+        let start = self.position;
+
+        self.bump();
+        self.bump();
+
+        if self.peek() == Some('[') && self.long_bracket_level().is_some() {
+            self.long_bracket()?;
+        } else {
+            while let Some(c) = self.peek() {
+                if c == '\n' || (c == '\r' && self.peek_nth(1) == Some('\n')) {
+                    break;
+                }
+
+                self.bump();
+            }
+        }
+
+        Ok(TokenKind::Comment(self.source[start..self.position].to_string()))
+    }
+
+    fn name(&mut self) -> TokenKind {
+        let start = self.position;
+
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        TokenKind::Name(self.source[start..self.position].to_string())
+    }
+
+    fn number(&mut self) -> Result<TokenKind, LuaError> {
+        let start = self.position;
+        let hex = self.peek() == Some('0') && matches!(self.peek_nth(1), Some('x') | Some('X'));
+
+        if hex {
+            self.bump();
+            self.bump();
+        }
+
+        while let Some(c) = self.peek() {
+            let exponent = if hex { c == 'p' || c == 'P' } else { c == 'e' || c == 'E' };
+
+            if exponent && matches!(self.peek_nth(1), Some('+') | Some('-')) {
+                self.bump();
+                self.bump();
+            } else if c == '.' && self.peek_nth(1) == Some('.') {
+                // In `1..x` the `..` is the concatenation operator, not part of the number.
+                break;
+            } else if c.is_ascii_alphanumeric() || c == '.' {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        let text = &self.source[start..self.position];
+
+        let value = if hex {
+            hex_value(&text[2..])
+        } else {
+            text.parse::<f64>().ok()
+        };
+
+        match value {
+            Some(n) => Ok(TokenKind::Number(n)),
+            None => Err(LuaError {
+                message: format!("Malformed number `{}`", text),
+                line: self.line,
+                column: self.column - text.chars().count()
+            })
+        }
+    }
+
+    fn quoted_string(&mut self, quote: char) -> Result<TokenKind, LuaError> {
+        let mut value = String::new();
+        let unfinished = self.error("Unfinished string");
+
+        self.bump();
+
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(unfinished),
+                Some(c) if c == quote => break,
+                Some('\\') => {
+                    match self.bump() {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some('r') => value.push('\r'),
+                        Some('a') => value.push('\u{07}'),
+                        Some('b') => value.push('\u{08}'),
+                        Some('f') => value.push('\u{0c}'),
+                        Some('v') => value.push('\u{0b}'),
+                        Some('\n') => value.push('\n'),
+                        Some(d) if d.is_ascii_digit() => {
+                            let mut code = d.to_digit(10).unwrap_or_default();
+
+                            for _ in 0..2 {
+                                match self.peek().and_then(|c| c.to_digit(10)) {
+                                    Some(digit) => {
+                                        code = code * 10 + digit;
+                                        self.bump();
+                                    },
+                                    None => break
+                                }
+                            }
+
+                            value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(unfinished)
+                    }
+                },
+                Some(c) => value.push(c)
+            }
+        }
+
+        Ok(TokenKind::String(value))
+    }
+
+    /// If positioned at `[[` or `[==[`, return the number of `=`.
+    fn long_bracket_level(&self) -> Option<usize> {
+        let rest = self.rest().strip_prefix('[')?;
+        let level = rest.chars().take_while(|c| *c == '=').count();
+
+        if rest[level..].starts_with('[') {
+            Some(level)
+        } else {
+            None
+        }
+    }
+
+    /// Consume a long bracket `[==[ ... ]==]`, returning its contents.
+    fn long_bracket(&mut self) -> Result<String, LuaError> {
+        let level = self.long_bracket_level().unwrap_or_default();
+        let close = format!("]{}]", "=".repeat(level));
+
+        for _ in 0..level + 2 {
+            self.bump();
+        }
+
+        // A newline directly after the opening bracket is skipped.
+        if self.peek() == Some('\r') {
+            self.bump();
+        }
+
+        if self.peek() == Some('\n') {
+            self.bump();
+        }
+
+        let start = self.position;
+
+        match self.rest().find(&close) {
+            Some(offset) => {
+                let end = start + offset;
+
+                while self.position < end + close.len() {
+                    self.bump();
+                }
+
+                Ok(self.source[start..end].to_string())
+            },
+            None => Err(self.error("Unfinished long string or comment"))
+        }
+    }
+
+    fn symbol(&mut self) -> Result<TokenKind, LuaError> {
+        for symbol in SYMBOLS {
+            if self.rest().starts_with(symbol) {
+                for _ in 0..symbol.len() {
+                    self.bump();
+                }
+
+                return Ok(TokenKind::Symbol(symbol));
+            }
+        }
+
+        Err(self.error(&format!("Unexpected character `{}`", self.peek().unwrap_or_default())))
+    }
+}
+
+///
+/// Syntax Tree
+///
+/// A parsed Lua value. Anything that can't be reduced to a constant is kept as source text in `Expression`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Identifier(String),
+    Table(Vec<Field>),
+    Call(Box<Call>),
+    Expression(String)
+}
+
+impl Value {
+    /// String value, if this is a string literal.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None
+        }
+    }
+
+    /// Numeric value, if this is a number.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None
+        }
+    }
+
+    /// Integer value, if this is a whole number.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Number(n) if n.fract() == 0.0 => Some(*n as i64),
+            _ => None
+        }
+    }
+
+    /// Table fields, if this is a table constructor.
+    pub fn as_table(&self) -> Option<&[Field]> {
+        match self {
+            Value::Table(fields) => Some(fields),
+            _ => None
+        }
+    }
+
+    /// Name, if this is a (possibly dotted) identifier.
+    pub fn as_identifier(&self) -> Option<&str> {
+        match self {
+            Value::Identifier(s) => Some(s),
+            _ => None
+        }
+    }
+}
+
+/// Key of a table field; `{ a = 1 }` has a `Name` key, `{ [1] = 1 }` a `Value` key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Key {
+    Name(String),
+    Value(Value)
+}
+
+/// Table constructor field. Positional fields have no key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub key: Option<Key>,
+    pub value: Value
+}
+
+impl Field {
+    /// Name of the field, if it was written as `name = value`.
+    pub fn name(&self) -> Option<&str> {
+        match &self.key {
+            Some(Key::Name(n)) => Some(n),
+            _ => None
+        }
+    }
+}
+
+/// Argument to a function call, with the location it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    pub value: Value,
+    pub span: Span
+}
+
+/// Function call, e.g. `setAngles(NewWeaponType, 5, -45, 45, 0, 90)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub name: String,
+    pub args: Vec<Argument>,
+    pub span: Span
+}
+
+impl Call {
+    /// Get the argument at `index`, if present.
+    pub fn arg(&self, index: usize) -> Option<&Argument> {
+        self.args.get(index)
+    }
+
+    /// Get the value of the argument at `index`, if present.
+    pub fn value(&self, index: usize) -> Option<&Value> {
+        self.args.get(index).map(|a| &a.value)
+    }
+
+    /// Error for the argument at `index`.
+    pub fn argument_error(&self, index: usize, message: &str) -> ArgumentError {
+        let span = self.arg(index).map_or(self.span, |a| a.span);
+
+        ArgumentError {
+            call: self.name.to_string(),
+            index,
+            message: message.to_string(),
+            line: span.line,
+            column: span.column
+        }
+    }
+
+    /// String argument at `index`.
+    pub fn string_arg(&self, index: usize) -> Result<String, ArgumentError> {
+        match self.value(index) {
+            Some(Value::String(s)) => Ok(s.to_string()),
+            Some(_) => Err(self.argument_error(index, "Expected a string")),
+            None => Err(self.argument_error(index, "Missing string argument"))
+        }
+    }
+
+    /// Number argument at `index`.
+    pub fn number_arg(&self, index: usize) -> Result<f32, ArgumentError> {
+        match self.value(index) {
+            Some(Value::Number(n)) => Ok(*n as f32),
+            Some(_) => Err(self.argument_error(index, "Expected a number")),
+            None => Err(self.argument_error(index, "Missing number argument"))
+        }
+    }
+
+    /// Whole number argument at `index`.
+    pub fn integer_arg(&self, index: usize) -> Result<i32, ArgumentError> {
+        match self.value(index) {
+            Some(v) => v
+                .as_integer()
+                .map(|n| n as i32)
+                .ok_or_else(|| self.argument_error(index, "Expected a whole number")),
+            None => Err(self.argument_error(index, "Missing whole number argument"))
+        }
+    }
+
+    /// Table argument at `index`.
+    pub fn table_arg(&self, index: usize) -> Result<&[Field], ArgumentError> {
+        match self.value(index) {
+            Some(Value::Table(fields)) => Ok(fields),
+            Some(_) => Err(self.argument_error(index, "Expected a table")),
+            None => Err(self.argument_error(index, "Missing table argument"))
+        }
+    }
+}
+
+/// Assignment, e.g. `NewShipType.maxhealth = 1200`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub local: bool,
+    pub targets: Vec<String>,
+    pub values: Vec<Argument>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    Call(Call),
+    Assignment(Assignment),
+    Comment(String),
+    /// Any other statement (blocks, function definitions, returns, ...). Only its source is kept.
+    Other
+}
+
+/// Top level statement with the location of its source, including any trailing `;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+
+    /// Comments inside the statement, e.g. between the arguments of a call, which `kind` leaves out.
+    pub comments: Vec<String>
+}
+
+impl Statement {
+    /// The call, if this statement is a function call.
+    pub fn as_call(&self) -> Option<&Call> {
+        match &self.kind {
+            StatementKind::Call(call) => Some(call),
+            _ => None
+        }
+    }
+
+    /// The assignment, if this statement is an assignment.
+    pub fn as_assignment(&self) -> Option<&Assignment> {
+        match &self.kind {
+            StatementKind::Assignment(assignment) => Some(assignment),
+            _ => None
+        }
+    }
+}
+
+/// A parsed body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub statements: Vec<Statement>
+}
+
+impl Chunk {
+    /// Iterate over all top level function calls.
+    pub fn calls(&self) -> impl Iterator<Item = &Call> {
+        self.statements.iter().filter_map(|s| s.as_call())
+    }
+
+    /// Iterate over all top level calls to the function `name`.
+    pub fn calls_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Call> {
+        self.calls().filter(move |c| c.name == name)
+    }
+
+    /// First top level call to the function `name`.
+    pub fn find_call(&self, name: &str) -> Option<&Call> {
+        self.calls().find(|c| c.name == name)
+    }
+}
+
+/// Parse a body into a `Chunk`.
+pub fn parse(source: &str) -> Result<Chunk, LuaError> {
+    let tokens = Tokenizer::new(source).tokenize()?;
+
+    Parser::new(source, tokens).chunk()
+}
+
+///
+/// Parser
+///
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize
+}
+
+/// Binary operator precedence, (left, right) binding power, from the Lua manual.
+fn binary_precedence(symbol: &str) -> Option<(u8, u8)> {
+    match symbol {
+        "or" => Some((1, 1)),
+        "and" => Some((2, 2)),
+        "<" | ">" | "<=" | ">=" | "~=" | "==" => Some((3, 3)),
+        "|" => Some((4, 4)),
+        "~" => Some((5, 5)),
+        "&" => Some((6, 6)),
+        "<<" | ">>" => Some((7, 7)),
+        ".." => Some((9, 8)),
+        "+" | "-" => Some((10, 10)),
+        "*" | "/" | "//" | "%" => Some((11, 11)),
+        "^" => Some((14, 13)),
+        _ => None
+    }
+}
+
+/// Binding power of unary operators.
+const UNARY_PRECEDENCE: u8 = 12;
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str, tokens: Vec<Token>) -> Self {
+        Self {
+            source,
+            tokens,
+            position: 0
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position]
+    }
+
+    /// Index of the next token that isn't a comment. Comments are only significant between statements.
+    fn next_index(&self) -> usize {
+        let mut index = self.position;
+
+        while matches!(self.tokens[index].kind, TokenKind::Comment(_)) {
+            index += 1;
+        }
+
+        index
+    }
+
+    /// Next token, skipping comments.
+    fn current(&self) -> &Token {
+        &self.tokens[self.next_index()]
+    }
+
+    fn advance(&mut self) -> Token {
+        let index = self.next_index();
+        let token = self.tokens[index].clone();
+
+        self.position = if token.kind == TokenKind::Eof { index } else { index + 1 };
+
+        token
+    }
+
+    /// End offset of the last consumed token.
+    fn last_end(&self) -> usize {
+        self.tokens[..self.position]
+            .iter()
+            .rev()
+            .find(|t| !matches!(t.kind, TokenKind::Comment(_)))
+            .map_or(0, |t| t.span.end)
+    }
+
+    fn check_symbol(&self, symbol: &str) -> bool {
+        self.current().is_symbol(symbol)
+    }
+
+    fn check_keyword(&self, keyword: &str) -> bool {
+        self.current().is_keyword(keyword)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        if self.check_symbol(symbol) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error_at(token: &Token, message: String) -> LuaError {
+        LuaError {
+            message,
+            line: token.span.line,
+            column: token.span.column
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> LuaError {
+        let token = self.current().clone();
+
+        let found = match &token.kind {
+            TokenKind::Name(n) => format!("`{}`", n),
+            TokenKind::Number(_) | TokenKind::String(_) => format!("`{}`", &self.source[token.span.start..token.span.end]),
+            TokenKind::Symbol(s) => format!("`{}`", s),
+            TokenKind::Comment(_) => "comment".to_string(),
+            TokenKind::Eof => "end of file".to_string()
+        };
+
+        Self::error_at(&token, format!("Expected {}, found {}", expected, found))
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<Token, LuaError> {
+        if self.check_symbol(symbol) {
+            Ok(self.advance())
+        } else {
+            Err(self.unexpected(&format!("`{}`", symbol)))
+        }
+    }
+
+    fn expect_name(&mut self) -> Result<String, LuaError> {
+        match &self.current().kind {
+            TokenKind::Name(n) if !KEYWORDS.contains(&n.as_str()) => {
+                let name = n.to_string();
+                self.advance();
+                Ok(name)
+            },
+            _ => Err(self.unexpected("a name"))
+        }
+    }
+
+    fn span_from(&self, start: &Token) -> Span {
+        Span {
+            start: start.span.start,
+            end: self.last_end().max(start.span.start),
+            line: start.span.line,
+            column: start.span.column
+        }
+    }
+
+    fn text(&self, span: &Span) -> &'a str {
+        &self.source[span.start..span.end]
+    }
+
+    fn chunk(&mut self) -> Result<Chunk, LuaError> {
+        let mut statements: Vec<Statement> = Vec::new();
+
+        loop {
+            let token = self.peek().clone();
+
+            match &token.kind {
+                TokenKind::Eof => break,
+                TokenKind::Comment(text) => {
+                    self.position += 1;
+
+                    statements.push(Statement {
+                        kind: StatementKind::Comment(text.to_string()),
+                        span: token.span,
+                        comments: Vec::new()
+                    });
+                },
+                TokenKind::Symbol(";") => {
+                    // Stray separator; fold it into the previous statement so it's reproduced.
+                    self.position += 1;
+
+                    if let Some(last) = statements.last_mut() {
+                        last.span.end = token.span.end;
+                    }
+                },
+                _ => {
+                    let mut statement = self.statement()?;
+
+                    if self.peek().is_symbol(";") {
+                        statement.span.end = self.advance().span.end;
+                    }
+
+                    statements.push(statement);
+                }
+            }
+        }
+
+        Ok(Chunk {
+            statements
+        })
+    }
+
+    fn statement(&mut self) -> Result<Statement, LuaError> {
+        let first = self.next_index();
+        let start = self.current().clone();
+
+        let kind = match &start.kind {
+            TokenKind::Name(n) if n == "local" => self.local_statement()?,
+            TokenKind::Name(n) if ["function", "do", "if", "while", "for", "repeat"].contains(&n.as_str()) => {
+                self.skip_block()?;
+                StatementKind::Other
+            },
+            TokenKind::Name(n) if n == "return" => {
+                self.advance();
+
+                if !self.check_keyword("end") && !self.check_symbol(";") && self.current().kind != TokenKind::Eof {
+                    self.expression_list()?;
+                }
+
+                StatementKind::Other
+            },
+            TokenKind::Name(n) if n == "break" => {
+                self.advance();
+                StatementKind::Other
+            },
+            TokenKind::Name(n) if n == "goto" => {
+                self.advance();
+                self.expect_name()?;
+                StatementKind::Other
+            },
+            TokenKind::Symbol("::") => {
+                self.advance();
+                self.expect_name()?;
+                self.expect_symbol("::")?;
+                StatementKind::Other
+            },
+            TokenKind::Name(_) | TokenKind::Symbol("(") => self.expression_statement()?,
+            _ => return Err(self.unexpected("a statement"))
+        };
+
+        let comments = self.tokens[first..self.position]
+            .iter()
+            .filter_map(|t| match &t.kind {
+                TokenKind::Comment(text) => Some(text.to_string()),
+                _ => None
+            })
+            .collect();
+
+        Ok(Statement {
+            kind,
+            span: self.span_from(&start),
+            comments
+        })
+    }
+
+    fn local_statement(&mut self) -> Result<StatementKind, LuaError> {
+        self.advance();
+
+        if self.check_keyword("function") {
+            self.skip_block()?;
+
+            return Ok(StatementKind::Other);
+        }
+
+        let mut targets = vec![self.expect_name()?];
+
+        self.skip_attribute()?;
+
+        while self.eat_symbol(",") {
+            targets.push(self.expect_name()?);
+            self.skip_attribute()?;
+        }
+
+        let values = if self.eat_symbol("=") {
+            self.expression_list()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(StatementKind::Assignment(Assignment {
+            local: true,
+            targets,
+            values
+        }))
+    }
+
+    /// Skip a Lua 5.4 variable attribute such as `<const>`.
+    fn skip_attribute(&mut self) -> Result<(), LuaError> {
+        if self.eat_symbol("<") {
+            self.expect_name()?;
+            self.expect_symbol(">")?;
+        }
+
+        Ok(())
+    }
+
+    fn expression_statement(&mut self) -> Result<StatementKind, LuaError> {
+        let start = self.current().clone();
+        let first = self.suffixed_expression()?;
+
+        if self.check_symbol("=") || self.check_symbol(",") {
+            let mut targets = vec![first];
+
+            while self.eat_symbol(",") {
+                targets.push(self.suffixed_expression()?);
+            }
+
+            self.expect_symbol("=")?;
+
+            let values = self.expression_list()?;
+
+            let names: Option<Vec<String>> = targets
+                .iter()
+                .map(|t| t.as_identifier().map(|s| s.to_string()))
+                .collect();
+
+            return Ok(match names {
+                Some(targets) => StatementKind::Assignment(Assignment {
+                    local: false,
+                    targets,
+                    values
+                }),
+                None => StatementKind::Other
+            });
+        }
+
+        match first {
+            Value::Call(call) => Ok(StatementKind::Call(*call)),
+            _ => Err(Self::error_at(&start, format!("Expected a function call or assignment, found `{}`", self.text(&self.span_from(&start)))))
+        }
+    }
+
+    /// Skip a block statement, starting at its opening keyword, up to and including its matching `end` (or `until <expression>`).
+    fn skip_block(&mut self) -> Result<(), LuaError> {
+        let start = self.current().clone();
+        let mut depth = 0;
+
+        loop {
+            let token = self.advance();
+
+            match &token.kind {
+                TokenKind::Eof => return Err(Self::error_at(&start, "Block is never closed with `end`".to_string())),
+                TokenKind::Name(n) if ["function", "if", "do", "repeat"].contains(&n.as_str()) => depth += 1,
+                TokenKind::Name(n) if n == "end" || n == "until" => {
+                    depth -= 1;
+
+                    if n == "until" {
+                        self.expression()?;
+                    }
+
+                    if depth <= 0 {
+                        return Ok(());
+                    }
+                },
+                _ => {}
+            }
+        }
+    }
+
+    fn expression_list(&mut self) -> Result<Vec<Argument>, LuaError> {
+        let mut list = vec![self.argument()?];
+
+        while self.eat_symbol(",") {
+            list.push(self.argument()?);
+        }
+
+        Ok(list)
+    }
+
+    fn argument(&mut self) -> Result<Argument, LuaError> {
+        let start = self.current().clone();
+        let value = self.expression()?;
+
+        Ok(Argument {
+            value,
+            span: self.span_from(&start)
+        })
+    }
+
+    fn expression(&mut self) -> Result<Value, LuaError> {
+        self.binary_expression(0)
+    }
+
+    fn binary_expression(&mut self, min_precedence: u8) -> Result<Value, LuaError> {
+        let start = self.current().clone();
+
+        let unary = match &start.kind {
+            TokenKind::Symbol(s) if ["-", "#", "~"].contains(s) => Some(*s),
+            TokenKind::Name(n) if n == "not" => Some("not"),
+            _ => None
+        };
+
+        let mut left = if let Some(op) = unary {
+            self.advance();
+
+            let operand = self.binary_expression(UNARY_PRECEDENCE)?;
+
+            match (op, operand) {
+                ("-", Value::Number(n)) => Value::Number(-n),
+                _ => Value::Expression(self.text(&self.span_from(&start)).to_string())
+            }
+        } else {
+            self.simple_expression()?
+        };
+
+        loop {
+            let op = match &self.current().kind {
+                TokenKind::Symbol(s) => s.to_string(),
+                TokenKind::Name(n) if n == "and" || n == "or" => n.to_string(),
+                _ => break
+            };
+
+            let right_precedence = match binary_precedence(&op) {
+                Some((left, right)) if left > min_precedence => right,
+                _ => break
+            };
+
+            self.advance();
+
+            let right = self.binary_expression(right_precedence)?;
+
+            left = match (&left, &right) {
+                (Value::Number(a), Value::Number(b)) if ["+", "-", "*", "/", "%", "^"].contains(&op.as_str()) => {
+                    Value::Number(match op.as_str() {
+                        "+" => a + b,
+                        "-" => a - b,
+                        "*" => a * b,
+                        "/" => a / b,
+                        "%" => a - (a / b).floor() * b,
+                        _ => a.powf(*b)
+                    })
+                },
+                _ => Value::Expression(self.text(&self.span_from(&start)).to_string())
+            };
+        }
+
+        Ok(left)
+    }
+
+    fn simple_expression(&mut self) -> Result<Value, LuaError> {
+        let token = self.current().clone();
+
+        let value = match &token.kind {
+            TokenKind::Number(n) => Value::Number(*n),
+            TokenKind::String(s) => Value::String(s.to_string()),
+            TokenKind::Name(n) if n == "nil" => Value::Nil,
+            TokenKind::Name(n) if n == "true" => Value::Boolean(true),
+            TokenKind::Name(n) if n == "false" => Value::Boolean(false),
+            TokenKind::Name(n) if n == "function" => {
+                self.skip_block()?;
+
+                return Ok(Value::Expression(self.text(&self.span_from(&token)).to_string()));
+            },
+            TokenKind::Symbol("...") => Value::Expression("...".to_string()),
+            TokenKind::Symbol("{") => return self.table(),
+            _ => return self.suffixed_expression()
+        };
+
+        self.advance();
+
+        Ok(value)
+    }
+
+    /// Names, field accesses, calls and parenthesized expressions.
+    fn suffixed_expression(&mut self) -> Result<Value, LuaError> {
+        let start = self.current().clone();
+
+        let mut value = if self.eat_symbol("(") {
+            let inner = self.expression()?;
+            self.expect_symbol(")")?;
+
+            match inner {
+                Value::Expression(_) | Value::Call(_) => Value::Expression(self.text(&self.span_from(&start)).to_string()),
+                constant => constant
+            }
+        } else {
+            Value::Identifier(self.expect_name()?)
+        };
+
+        loop {
+            let token = self.current().clone();
+
+            match &token.kind {
+                TokenKind::Symbol(".") => {
+                    self.advance();
+                    let field = self.expect_name()?;
+
+                    value = match value {
+                        Value::Identifier(name) => Value::Identifier(format!("{}.{}", name, field)),
+                        _ => Value::Expression(self.text(&self.span_from(&start)).to_string())
+                    };
+                },
+                TokenKind::Symbol("[") => {
+                    self.advance();
+                    self.expression()?;
+                    self.expect_symbol("]")?;
+
+                    value = Value::Expression(self.text(&self.span_from(&start)).to_string());
+                },
+                TokenKind::Symbol(":") => {
+                    self.advance();
+                    let method = self.expect_name()?;
+                    let args = self.call_arguments()?;
+
+                    value = self.make_call(value, Some(method), args, &start);
+                },
+                TokenKind::Symbol("(") | TokenKind::Symbol("{") | TokenKind::String(_) => {
+                    let args = self.call_arguments()?;
+
+                    value = self.make_call(value, None, args, &start);
+                },
+                _ => break
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn make_call(&self, callee: Value, method: Option<String>, args: Vec<Argument>, start: &Token) -> Value {
+        let span = self.span_from(start);
+
+        match callee {
+            Value::Identifier(name) => {
+                let name = match method {
+                    Some(m) => format!("{}:{}", name, m),
+                    None => name
+                };
+
+                Value::Call(Box::new(Call {
+                    name,
+                    args,
+                    span
+                }))
+            },
+            _ => Value::Expression(self.text(&span).to_string())
+        }
+    }
+
+    fn call_arguments(&mut self) -> Result<Vec<Argument>, LuaError> {
+        let token = self.current().clone();
+
+        match &token.kind {
+            TokenKind::String(s) => {
+                self.advance();
+
+                Ok(vec![Argument {
+                    value: Value::String(s.to_string()),
+                    span: token.span
+                }])
+            },
+            TokenKind::Symbol("{") => {
+                let value = self.table()?;
+
+                Ok(vec![Argument {
+                    value,
+                    span: self.span_from(&token)
+                }])
+            },
+            _ => {
+                self.expect_symbol("(")?;
+
+                let mut args = Vec::new();
+
+                while !self.check_symbol(")") {
+                    args.push(self.argument()?);
+
+                    // Homeworld files commonly leave a trailing `,` before `)`, which the game accepts.
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+
+                self.expect_symbol(")")?;
+
+                Ok(args)
+            }
+        }
+    }
+
+    fn table(&mut self) -> Result<Value, LuaError> {
+        self.expect_symbol("{")?;
+
+        let mut fields = Vec::new();
+
+        while !self.check_symbol("}") {
+            let key = if self.check_symbol("[") {
+                self.advance();
+                let key = self.expression()?;
+                self.expect_symbol("]")?;
+                self.expect_symbol("=")?;
+
+                Some(Key::Value(key))
+            } else if matches!(self.current().kind, TokenKind::Name(_)) && self.next_is_assign() {
+                let name = self.expect_name()?;
+                self.expect_symbol("=")?;
+
+                Some(Key::Name(name))
+            } else {
+                None
+            };
+
+            let value = self.expression()?;
+
+            fields.push(Field {
+                key,
+                value
+            });
+
+            if !self.eat_symbol(",") && !self.eat_symbol(";") {
+                break;
+            }
+        }
+
+        self.expect_symbol("}")?;
+
+        Ok(Value::Table(fields))
+    }
+
+    /// Is the token after the current one a single `=` (as in `{ name = value }`)?
+    fn next_is_assign(&self) -> bool {
+        self.tokens[self.next_index() + 1..]
+            .iter()
+            .find(|t| !matches!(t.kind, TokenKind::Comment(_)))
+            .is_some_and(|t| t.is_symbol("="))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        Tokenizer::new(source).tokenize().unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn commas_inside_strings_stay_in_the_argument() {
+        let chunk = parse(r#"AddWeaponResult(NewWeaponType, "Hit, Miss", 'a,b', 1)"#).unwrap();
+        let call = chunk.find_call("AddWeaponResult").unwrap();
+
+        assert_eq!(call.args.len(), 4);
+        assert_eq!(call.value(1), Some(&Value::String("Hit, Miss".to_string())));
+        assert_eq!(call.value(2), Some(&Value::String("a,b".to_string())));
+        assert_eq!(call.value(3), Some(&Value::Number(1.0)));
+    }
+
+    #[test]
+    fn calls_can_span_several_lines() {
+        let source = "setPenetration(NewWeaponType,\n    5,\n    1,\n    {Fighter=0.5},\n    {Corvette=1})\nsetAngles(NewWeaponType, 5, -45, 45, 0, 90)";
+        let chunk = parse(source).unwrap();
+        let call = chunk.find_call("setPenetration").unwrap();
+
+        assert_eq!(call.args.len(), 5);
+        assert_eq!(call.value(4).and_then(|v| v.as_table()).map(|t| t[0].name()), Some(Some("Corvette")));
+        assert_eq!(chunk.statements[0].span.line, 1);
+        assert_eq!(chunk.statements[1].span.line, 6);
+    }
+
+    #[test]
+    fn line_comments_are_statements() {
+        let chunk = parse("-- a comment\nsetMiscValues(NewWeaponType, 1, 2)").unwrap();
+
+        assert_eq!(chunk.statements[0].kind, StatementKind::Comment("-- a comment".to_string()));
+        assert!(chunk.statements[1].as_call().is_some());
+    }
+
+    #[test]
+    fn block_comments_are_statements() {
+        let source = "--[[ setAccuracy(NewWeaponType, 0.5)\n   still commented ]]\n--[==[ ]] ]==]\nsetMiscValues(NewWeaponType, 1, 2)";
+        let chunk = parse(source).unwrap();
+
+        assert_eq!(chunk.statements.len(), 3);
+        assert_eq!(chunk.statements[0].kind, StatementKind::Comment("--[[ setAccuracy(NewWeaponType, 0.5)\n   still commented ]]".to_string()));
+        assert_eq!(chunk.statements[1].kind, StatementKind::Comment("--[==[ ]] ]==]".to_string()));
+        assert_eq!(chunk.calls().count(), 1);
+    }
+
+    #[test]
+    fn trailing_comments_follow_their_statement() {
+        let chunk = parse("setMiscValues(NewWeaponType, 1, 2) -- recoil\r\nsetAngles(NewWeaponType, 5, -45, 45, 0, 90);").unwrap();
+
+        assert_eq!(chunk.statements.len(), 3);
+        assert!(chunk.statements[0].as_call().is_some());
+        assert_eq!(chunk.statements[1].kind, StatementKind::Comment("-- recoil".to_string()));
+        assert_eq!(chunk.statements[1].span.line, 1);
+        assert!(chunk.statements[2].as_call().is_some());
+    }
+
+    #[test]
+    fn comments_inside_calls_are_kept_with_the_statement() {
+        let chunk = parse("setPenetration(NewWeaponType, 5, 1, -- defaults\n    {Fighter=0.5}) -- after").unwrap();
+
+        assert_eq!(chunk.find_call("setPenetration").unwrap().args.len(), 4);
+        assert_eq!(chunk.statements[0].comments, vec!["-- defaults".to_string()]);
+        assert_eq!(chunk.statements[1].kind, StatementKind::Comment("-- after".to_string()));
+    }
+
+    #[test]
+    fn numbers_stop_before_concatenation() {
+        assert_eq!(kinds("1..x"), vec![
+            TokenKind::Number(1.0),
+            TokenKind::Symbol(".."),
+            TokenKind::Name("x".to_string()),
+            TokenKind::Eof
+        ]);
+
+        assert_eq!(kinds("1.5..2"), vec![
+            TokenKind::Number(1.5),
+            TokenKind::Symbol(".."),
+            TokenKind::Number(2.0),
+            TokenKind::Eof
+        ]);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(kinds(".5 3. 1e3 2.5E-2"), vec![
+            TokenKind::Number(0.5),
+            TokenKind::Number(3.0),
+            TokenKind::Number(1000.0),
+            TokenKind::Number(0.025),
+            TokenKind::Eof
+        ]);
+    }
+
+    #[test]
+    fn hex_numbers() {
+        assert_eq!(kinds("0xff 0X10 0x1.8p3 0x.8 0xAp-1"), vec![
+            TokenKind::Number(255.0),
+            TokenKind::Number(16.0),
+            TokenKind::Number(12.0),
+            TokenKind::Number(0.5),
+            TokenKind::Number(5.0),
+            TokenKind::Eof
+        ]);
+    }
+
+    #[test]
+    fn malformed_numbers_are_errors() {
+        assert!(Tokenizer::new("1.2.3").tokenize().is_err());
+        assert!(Tokenizer::new("0x").tokenize().is_err());
+        assert!(Tokenizer::new("12abc").tokenize().is_err());
+    }
+
+    #[test]
+    fn constant_arithmetic_is_folded() {
+        let chunk = parse("x = -2 * 3 + 1\ny = 1 .. 2").unwrap();
+
+        assert_eq!(chunk.statements[0].as_assignment().unwrap().values[0].value, Value::Number(-5.0));
+        assert_eq!(chunk.statements[1].as_assignment().unwrap().values[0].value, Value::Expression("1 .. 2".to_string()));
+    }
+
+    #[test]
+    fn unfinished_strings_report_their_line() {
+        let error = parse("x = 1\ny = \"oops\n").unwrap_err();
+
+        assert_eq!((error.line, error.column), (2, 5));
+    }
+}
@@ -0,0 +1,210 @@
+pub mod missile;
+pub mod missile_extra_statement;
+
+use std::io::prelude::*;
+use std::fmt;
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use missile::{ Missile, MissileCollection };
+use missile_extra_statement::{ MissileExtraStatementCollection, NewMissileExtraStatement, NewMissileExtraStatementCollection };
+use super::wepn_file::{ ImportOptions, SQLITE_MAX_VARIABLES, WEAPON_FILE_HEADER };
+use super::data_file::{ DataFile, NewDataFile, NewDataFileCollection, fill_in_properties, lay_out };
+use super::lua;
+use super::parse_error::ParseError;
+use super::wepn_file::import_error::ImportError;
+
+/// Missile files carry the same watermark as weapon files.
+pub const MISS_FILE_HEADER: &str = WEAPON_FILE_HEADER;
+
+///
+/// Missile File (*.miss)
+///
+/// The projectile a `Missile` weapon launches. Unlike ships and subsystems, a missile has no weapons of its own, so
+/// the file is just its properties.
+///
+#[derive(Debug)]
+pub struct MissFile {
+    pub missile: Missile,
+    pub missile_extra_statements: MissileExtraStatementCollection
+}
+
+impl fmt::Display for MissFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let extras: Vec<(Option<&str>, String)> = self.missile_extra_statements.missile_extra_statements
+            .iter()
+            .map(|extra| (None, extra.to_string()))
+            .collect();
+
+        // The properties are written back among the extra statements, in the lines they were read from.
+        let extras = fill_in_properties(&self.missile, &extras);
+
+        write!(f, "{}{}", MISS_FILE_HEADER, lay_out(&[], &extras, None))
+    }
+}
+
+impl MissFile {
+    // Get the Missile File for a specific missile name. Possible that it can't be found.
+    pub fn get_for_missile(connection: &mut SqliteConnection, missile_name: &str) -> Option<Self> {
+        let missile = Missile::get_for_missile(connection, missile_name)?;
+
+        Some(Self {
+            missile,
+            missile_extra_statements: MissileExtraStatementCollection::get_for_missile(connection, missile_name)
+        })
+    }
+}
+
+impl DataFile for MissFile {
+    const EXTENSION: &'static str = "miss";
+
+    fn name(&self) -> &str {
+        &self.missile.name
+    }
+}
+
+#[derive(Default)]
+pub struct MissFileCollection {
+    pub miss_files: HashMap<String, MissFile>
+}
+
+impl MissFileCollection {
+    /// Create MissFileCollection from a MissileCollection
+    pub fn from_missile_collection(connection: &mut SqliteConnection, mut missiles: MissileCollection) -> Self {
+        let missile_names = missiles.get_names();
+        let mut miss_files = HashMap::new();
+
+        let mut all_missile_extra_statements = MissileExtraStatementCollection::get_for_missiles(connection, &missile_names).key_by_missile_name();
+
+        for (name, missile) in missiles.missiles.drain() {
+            miss_files.insert(name.to_string(), MissFile {
+                missile,
+                missile_extra_statements: all_missile_extra_statements.remove(&name).unwrap_or_default()
+            });
+        }
+
+        Self {
+            miss_files
+        }
+    }
+
+    /// Get all Missile Files from the Database.
+    pub fn get_all_miss_files(connection: &mut SqliteConnection) -> Self {
+        let missiles = MissileCollection::get_all_missiles(connection);
+
+        Self::from_missile_collection(connection, missiles)
+    }
+
+    /// Get missile files from a list of missile_name Strings. Loaded `SQLITE_MAX_VARIABLES` names at a time.
+    pub fn get_miss_files_from_names(connection: &mut SqliteConnection, missile_names: Vec<String>) -> Self {
+        let mut miss_files = HashMap::new();
+
+        for chunk in missile_names.chunks(SQLITE_MAX_VARIABLES) {
+            let missiles = MissileCollection::get_missiles_from_names(connection, chunk.to_vec());
+
+            miss_files.extend(Self::from_missile_collection(connection, missiles).miss_files);
+        }
+
+        Self {
+            miss_files
+        }
+    }
+
+    /// Render every missile file, keyed by missile name.
+    pub fn rendered(&self) -> HashMap<String, String> {
+        self.miss_files
+            .iter()
+            .map(|(name, miss)| (name.to_string(), miss.to_string()))
+            .collect()
+    }
+}
+
+///
+/// NewMissFile
+///
+#[derive(Debug)]
+pub struct NewMissFile {
+    pub missile: Missile,
+    pub missile_extra_statements: NewMissileExtraStatementCollection
+}
+
+impl NewDataFile for NewMissFile {
+    const EXTENSION: &'static str = "miss";
+
+    /// Create new `NewMissFile` from the body of a *.miss file.
+    fn from_string(missile_name: &str, contents: &str) -> Result<Self, ParseError> {
+        let chunk = lua::parse(contents)?;
+
+        Ok(Self {
+            missile: Missile::from_chunk(missile_name, &chunk)?,
+            missile_extra_statements: NewMissileExtraStatementCollection::from_chunk(missile_name, &chunk, contents)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct NewMissFileCollection {
+    pub new_miss_files: Vec<NewMissFile>
+}
+
+impl NewDataFileCollection for NewMissFileCollection {
+    type File = NewMissFile;
+
+    /// Create from a Vector of NewMissFile
+    fn from_vec(new_miss_files: Vec<NewMissFile>) -> Self {
+        Self {
+            new_miss_files
+        }
+    }
+
+    /// Get list of missile names in the collection.
+    fn get_names(&self) -> Vec<String> {
+        self.new_miss_files.iter().map(|mf| mf.missile.name.to_string()).collect()
+    }
+
+    fn get_all_names(connection: &mut SqliteConnection) -> Vec<String> {
+        MissileCollection::get_all_missiles(connection).get_names()
+    }
+
+    fn render(connection: &mut SqliteConnection, names: &[String]) -> HashMap<String, String> {
+        MissFileCollection::get_miss_files_from_names(connection, names.to_vec()).rendered()
+    }
+
+    fn delete_named(connection: &mut SqliteConnection, names: &[String]) -> Result<(), ImportError> {
+        use crate::schema::{ missiles, missile_extra_statements };
+
+        delete_rows!(connection, names,
+            missile_extra_statements.missile_name,
+            missiles.name
+        );
+
+        Ok(())
+    }
+
+    fn delete_all(connection: &mut SqliteConnection) -> Result<(), ImportError> {
+        use crate::schema::{ missiles, missile_extra_statements };
+
+        delete_rows!(connection, missile_extra_statements, missiles);
+
+        Ok(())
+    }
+
+    fn insert_rows(&self, connection: &mut SqliteConnection, options: &ImportOptions) -> Result<(), ImportError> {
+        use crate::schema::{ missiles, missile_extra_statements };
+
+        let mut missiles_to_insert: Vec<&Missile> = Vec::new();
+        let mut missile_extra_statements_to_insert: Vec<&NewMissileExtraStatement> = Vec::new();
+
+        for miss_file in self.new_miss_files.iter() {
+            missiles_to_insert.push(&miss_file.missile);
+
+            miss_file.missile_extra_statements.missile_extra_statements.iter().for_each(|x| missile_extra_statements_to_insert.push(x));
+        }
+
+        insert_rows!(connection, missiles::table, "missiles", 5, missiles_to_insert, options, |x| x.name.to_string());
+        insert_rows!(connection, missile_extra_statements::table, "missile_extra_statements", 3, missile_extra_statements_to_insert, options, |x| x.missile_name.to_string());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use diesel::prelude::*;
+use crate::schema::{missiles};
+use crate::models::lua::{ Argument, Chunk, Statement, StatementKind, Value };
+use crate::models::data_file::PropertyModel;
+use crate::models::parse_error::ParseError;
+use std::collections::HashMap;
+
+/// The `NewMissileType` properties Scaffold models, in the order it writes them. Any other property is kept as a `MissileExtraStatement`.
+pub const MISSILE_PROPERTIES: [&str; 4] = [
+    "maxhealth",
+    "armourFamily",
+    "mainEngineMaxSpeed",
+    "rotationMaxSpeed"
+];
+
+///
+/// Missile
+///
+/// The entity a `Missile` weapon fires. Weapons refer to it by `weapon_fire_name`.
+///
+#[derive(Queryable, Selectable, Identifiable, Default, Insertable, Debug)]
+#[diesel(table_name = missiles)]
+#[diesel(primary_key(name))]
+pub struct Missile {
+    pub name: String,
+    pub max_health: Option<f32>,
+    pub armour_family: Option<String>,
+    pub main_engine_max_speed: Option<f32>,
+    pub rotation_max_speed: Option<f32>
+}
+
+impl Missile {
+    /// Create a `Missile` Model from a `name` and the parsed statements in `chunk`. Only the first assignment to
+    /// each property is used.
+    pub fn from_chunk(name: &str, chunk: &Chunk) -> Result<Self, ParseError> {
+        if !chunk.statements.iter().any(Self::is_start) {
+            return Err(ParseError::new("Missing `NewMissileType = StartMissileConfig()`"));
+        }
+
+        let mut missile = Self {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        for statement in chunk.statements.iter() {
+            let Some((property, Argument { value, .. })) = Self::property(statement) else {
+                continue;
+            };
+
+            let number = value.as_number().map(|n| n as f32);
+
+            match property {
+                "maxhealth" if missile.max_health.is_none() => missile.max_health = number,
+                "armourFamily" if missile.armour_family.is_none() => missile.armour_family = value.as_str().map(|s| s.to_string()),
+                "mainEngineMaxSpeed" if missile.main_engine_max_speed.is_none() => missile.main_engine_max_speed = number,
+                "rotationMaxSpeed" if missile.rotation_max_speed.is_none() => missile.rotation_max_speed = number,
+                _ => {}
+            }
+        }
+
+        Ok(missile)
+    }
+
+    /// Get the Missile for a specific missile name. Possible that it can't be found.
+    pub fn get_for_missile(connection: &mut SqliteConnection, missile_name: &str) -> Option<Self> {
+        use crate::schema::missiles::dsl::*;
+
+        let res = missiles
+                    .filter(name.eq(missile_name))
+                    .first(connection)
+                    .optional();
+
+        res.unwrap_or_default()
+    }
+}
+
+impl PropertyModel for Missile {
+    const START: &'static str = "NewMissileType = StartMissileConfig()";
+
+    /// Is `statement` the `NewMissileType = StartMissileConfig()` line?
+    fn is_start(statement: &Statement) -> bool {
+        match statement.as_assignment() {
+            Some(assignment) => {
+                assignment.targets == ["NewMissileType"]
+                    && matches!(assignment.values.first().map(|a| &a.value), Some(Value::Call(call)) if call.name == "StartMissileConfig")
+            },
+            None => false
+        }
+    }
+
+    /// If `statement` sets one of the `MISSILE_PROPERTIES` to a plain number (or string, for `armourFamily`), return
+    /// the property and its value. Anything else is left to `MissileExtraStatement`.
+    fn property(statement: &Statement) -> Option<(&'static str, &Argument)> {
+        let StatementKind::Assignment(assignment) = &statement.kind else {
+            return None;
+        };
+
+        if assignment.targets.len() != 1 || assignment.values.len() != 1 {
+            return None;
+        }
+
+        let name = assignment.targets[0].strip_prefix("NewMissileType.")?;
+        let property = MISSILE_PROPERTIES.iter().find(|p| **p == name)?;
+        let argument = &assignment.values[0];
+
+        let usable = match *property {
+            "armourFamily" => argument.value.as_str().is_some(),
+            _ => argument.value.as_number().is_some()
+        };
+
+        if usable {
+            Some((property, argument))
+        } else {
+            None
+        }
+    }
+
+    fn values(&self) -> Vec<(&'static str, String)> {
+        let values = [
+            ("maxhealth", self.max_health.map(|v| v.to_string())),
+            ("armourFamily", self.armour_family.as_ref().map(|v| format!("\"{}\"", v))),
+            ("mainEngineMaxSpeed", self.main_engine_max_speed.map(|v| v.to_string())),
+            ("rotationMaxSpeed", self.rotation_max_speed.map(|v| v.to_string()))
+        ];
+
+        values.into_iter().filter_map(|(property, value)| value.map(|v| (property, v))).collect()
+    }
+
+    fn statement(property: &str, value: &str) -> String {
+        format!("NewMissileType.{} = {}", property, value)
+    }
+}
+
+pub struct MissileCollection {
+    pub missiles: HashMap<String, Missile>
+}
+
+impl MissileCollection {
+    /// Get all Missiles from the Database.
+    pub fn get_all_missiles(connection: &mut SqliteConnection) -> Self {
+        use crate::schema::missiles::dsl::*;
+
+        let missiles_vec = missiles
+                            .load::<Missile>(connection)
+                            .expect("Could not load missiles table!");
+
+        Self {
+            missiles: missiles_vec.into_iter().map(|m| (m.name.to_string(), m)).collect()
+        }
+    }
+
+    /// Get missiles from a list of missile_name Strings
+    pub fn get_missiles_from_names(connection: &mut SqliteConnection, missile_names: Vec<String>) -> Self {
+        use crate::schema::missiles::dsl::*;
+
+        let missiles_vec = missiles
+                            .filter(name.eq_any(missile_names))
+                            .load::<Missile>(connection)
+                            .expect("Could not load missiles table!");
+
+        Self {
+            missiles: missiles_vec.into_iter().map(|m| (m.name.to_string(), m)).collect()
+        }
+    }
+
+    /// Get list of Names
+    pub fn get_names(&self) -> Vec<String> {
+        self.missiles.keys().map(|n| n.to_string()).collect()
+    }
+}
@@ -0,0 +1,133 @@
+use diesel::prelude::*;
+use crate::schema::{missile_extra_statements};
+use super::missile::Missile;
+use crate::models::lua::Chunk;
+use crate::models::data_file::without_watermark;
+use std::fmt;
+use std::collections::HashMap;
+
+///
+/// Missile Extra Statement
+///
+/// Every statement in a *.miss file (`NewMissileType` properties, comments, ...), stored verbatim in the order it was
+/// found. The `StartMissileConfig()` line and the lines `Missile` is read from are given the `Missile`'s values when
+/// written back (see `data_file::fill_in_properties`).
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Missile, foreign_key = missile_name))]
+#[diesel(table_name = missile_extra_statements)]
+pub struct MissileExtraStatement {
+    pub id: i32,
+    pub missile_name: String,
+    pub position: i32,
+    pub statement: String
+}
+
+impl fmt::Display for MissileExtraStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.statement)
+    }
+}
+
+/// Array of `MissileExtraStatement`, ordered by `position`.
+#[derive(Debug)]
+pub struct MissileExtraStatementCollection {
+    pub missile_extra_statements: Vec<MissileExtraStatement>
+}
+
+impl Default for MissileExtraStatementCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MissileExtraStatementCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            missile_extra_statements: Vec::new()
+        }
+    }
+
+    /// Get extra statements for a specific missile name.
+    pub fn get_for_missile(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::missile_extra_statements::dsl::*;
+
+        let vec = missile_extra_statements
+                    .filter(missile_name.eq(name))
+                    .order(position.asc())
+                    .load::<MissileExtraStatement>(connection)
+                    .expect("Error loading missile extra statements!");
+
+        Self {
+            missile_extra_statements: vec
+        }
+    }
+
+    /// Get extra statements for a list of missile names.
+    pub fn get_for_missiles(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::missile_extra_statements::dsl::*;
+
+        let vec = missile_extra_statements
+                    .filter(missile_name.eq_any(names))
+                    .order(position.asc())
+                    .load::<MissileExtraStatement>(connection)
+                    .expect("Error loading missile extra statements!");
+
+        Self {
+            missile_extra_statements: vec
+        }
+    }
+
+    /// Consume the collection, divide into HashMap by `missile_name`. Keeps the `position` order.
+    pub fn key_by_missile_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for ss in self.missile_extra_statements {
+            map
+                .entry(ss.missile_name.to_string())
+                .or_default()
+                .missile_extra_statements.push(ss);
+        }
+
+        map
+    }
+}
+
+///
+/// NewMissileExtraStatement
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = missile_extra_statements)]
+pub struct NewMissileExtraStatement {
+    pub missile_name: String,
+    pub position: i32,
+    pub statement: String
+}
+
+/// Array of `NewMissileExtraStatement` with helper methods.
+#[derive(Debug)]
+pub struct NewMissileExtraStatementCollection {
+    pub missile_extra_statements: Vec<NewMissileExtraStatement>
+}
+
+impl NewMissileExtraStatementCollection {
+    /// Collect every statement in `chunk`. A missile file has no calls Scaffold models, so nothing is left out but
+    /// the Scaffold watermark, which is skipped so it doesn't pile up on every round-trip.
+    pub fn from_chunk(missile_name: &str, chunk: &Chunk, source: &str) -> Self {
+        let missile_extra_statements = without_watermark(chunk)
+            .iter()
+            .enumerate()
+            .map(|(position, statement)| NewMissileExtraStatement {
+                missile_name: missile_name.to_string(),
+                position: position as i32,
+                statement: statement.span.text(source).to_string()
+            })
+            .collect();
+
+        Self {
+            missile_extra_statements
+        }
+    }
+}
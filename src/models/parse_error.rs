@@ -0,0 +1,104 @@
+use std::fmt;
+use std::path::{ Path, PathBuf };
+use super::lua::{ ArgumentError, LuaError, Call };
+use crate::json;
+
+///
+/// Parse Error
+///
+/// Describes why a data file could not be read, and where. `line` and `column` are 1-based; 0 means the error
+/// applies to the whole file (e.g. a required call is missing).
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub call: Option<String>,
+    pub argument: Option<usize>,
+    pub message: String
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+
+        if self.line > 0 {
+            write!(f, ":{}:{}", self.line, self.column)?;
+        }
+
+        write!(f, ": {}", self.message)?;
+
+        match (&self.call, self.argument) {
+            (Some(call), Some(argument)) => write!(f, " (`{}` argument {})", call, argument),
+            (Some(call), None) => write!(f, " (in `{}`)", call),
+            _ => Ok(())
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<LuaError> for ParseError {
+    fn from(error: LuaError) -> Self {
+        Self {
+            path: PathBuf::new(),
+            line: error.line,
+            column: error.column,
+            call: None,
+            argument: None,
+            message: error.message
+        }
+    }
+}
+
+impl From<ArgumentError> for ParseError {
+    fn from(error: ArgumentError) -> Self {
+        Self {
+            path: PathBuf::new(),
+            line: error.line,
+            column: error.column,
+            call: Some(error.call),
+            argument: Some(error.index),
+            message: error.message
+        }
+    }
+}
+
+impl ParseError {
+    /// Error that isn't tied to a location in the file.
+    pub fn new(message: &str) -> Self {
+        Self {
+            path: PathBuf::new(),
+            line: 0,
+            column: 0,
+            call: None,
+            argument: None,
+            message: message.to_string()
+        }
+    }
+
+    /// Error for argument `index` of `call`. Points at the argument, or at the call if the argument is missing.
+    pub fn argument(call: &Call, index: usize, message: &str) -> Self {
+        Self::from(call.argument_error(index, message))
+    }
+
+    /// The error as a JSON object. `line` and `column` are 0 when the error applies to the whole file; `call` and
+    /// `argument` are null unless it's about a call or one of its arguments.
+    pub fn to_json(&self) -> String {
+        json::object(vec![
+            ("path", json::string(&self.path.display().to_string())),
+            ("line", self.line.to_string()),
+            ("column", self.column.to_string()),
+            ("call", json::optional_string(self.call.as_deref())),
+            ("argument", self.argument.map_or(String::from("null"), |a| a.to_string())),
+            ("message", json::string(&self.message))
+        ])
+    }
+
+    /// Attach the path of the file being parsed.
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = path.to_path_buf();
+        self
+    }
+}
@@ -0,0 +1,269 @@
+pub mod ship;
+pub mod ship_weapon;
+pub mod ship_ability;
+pub mod ship_target_filter;
+pub mod ship_extra_statement;
+
+use std::io::prelude::*;
+use std::fmt;
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use ship::{ Ship, ShipCollection };
+use ship_weapon::{ ShipWeaponCollection, NewShipWeapon, NewShipWeaponCollection };
+use ship_ability::{ ShipAbilityCollection, NewShipAbility, NewShipAbilityCollection };
+use ship_target_filter::{ ShipTargetFilterCollection, NewShipTargetFilter, NewShipTargetFilterCollection };
+use ship_extra_statement::{ ShipExtraStatementCollection, NewShipExtraStatement, NewShipExtraStatementCollection };
+use super::wepn_file::{ ImportOptions, SQLITE_MAX_VARIABLES, WEAPON_FILE_HEADER };
+use super::data_file::{ DataFile, NewDataFile, NewDataFileCollection, anchor, fill_in_properties, lay_out };
+use super::lua;
+use super::parse_error::ParseError;
+use super::wepn_file::import_error::ImportError;
+
+/// Ship files carry the same watermark as weapon files.
+pub const SHIP_FILE_HEADER: &str = WEAPON_FILE_HEADER;
+
+/// The lua calls Scaffold models, besides `StartShipConfig()` and the `SHIP_PROPERTIES`. Anything else is kept as a
+/// `ShipExtraStatement`.
+pub const SHIP_FILE_CALLS: [&str; 3] = [
+    "addAbility",
+    "StartShipWeaponConfig",
+    "setTargetFilter"
+];
+
+///
+/// Ship File (*.ship)
+///
+#[derive(Debug)]
+pub struct ShipFile {
+    pub ship: Ship,
+    pub ship_abilities: ShipAbilityCollection,
+    pub ship_weapons: ShipWeaponCollection,
+    pub ship_target_filters: ShipTargetFilterCollection,
+    pub ship_extra_statements: ShipExtraStatementCollection
+}
+
+impl fmt::Display for ShipFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Each modelled call is paired with its anchor and the section it belongs to; see `lay_out`.
+        let mut modelled: Vec<(String, &str, String)> = Vec::new();
+
+        for (index, ability) in self.ship_abilities.ship_abilities.iter().enumerate() {
+            modelled.push((anchor("addAbility", index), "abilities", ability.to_string()));
+        }
+
+        for (index, weapon) in self.ship_weapons.ship_weapons.iter().enumerate() {
+            modelled.push((anchor("StartShipWeaponConfig", index), "weapons", weapon.to_string()));
+        }
+
+        for (index, filter) in self.ship_target_filters.ship_target_filters.iter().enumerate() {
+            modelled.push((anchor("setTargetFilter", index), "filters", filter.to_string()));
+        }
+
+        let extras: Vec<(Option<&str>, String)> = self.ship_extra_statements.ship_extra_statements
+            .iter()
+            .map(|extra| (extra.anchor.as_deref(), extra.to_string()))
+            .collect();
+
+        // The properties are written back among the extra statements, in the lines they were read from. Extras join
+        // the section of the call they follow, so a file's own grouping is kept.
+        let extras = fill_in_properties(&self.ship, &extras);
+
+        write!(f, "{}{}", SHIP_FILE_HEADER, lay_out(&modelled, &extras, None))
+    }
+}
+
+impl ShipFile {
+    // Get the Ship File for a specific ship name. Possible that it can't be found.
+    pub fn get_for_ship(connection: &mut SqliteConnection, ship_name: &str) -> Option<Self> {
+        let ship = Ship::get_for_ship(connection, ship_name)?;
+
+        Some(Self {
+            ship,
+            ship_abilities: ShipAbilityCollection::get_for_ship(connection, ship_name),
+            ship_weapons: ShipWeaponCollection::get_for_ship(connection, ship_name),
+            ship_target_filters: ShipTargetFilterCollection::get_for_ship(connection, ship_name),
+            ship_extra_statements: ShipExtraStatementCollection::get_for_ship(connection, ship_name)
+        })
+    }
+}
+
+impl DataFile for ShipFile {
+    const EXTENSION: &'static str = "ship";
+
+    fn name(&self) -> &str {
+        &self.ship.name
+    }
+}
+
+#[derive(Default)]
+pub struct ShipFileCollection {
+    pub ship_files: HashMap<String, ShipFile>
+}
+
+impl ShipFileCollection {
+    /// Create ShipFileCollection from a ShipCollection
+    pub fn from_ship_collection(connection: &mut SqliteConnection, mut ships: ShipCollection) -> Self {
+        let ship_names = ships.get_names();
+        let mut ship_files = HashMap::new();
+
+        let mut all_ship_abilities = ShipAbilityCollection::get_for_ships(connection, &ship_names).key_by_ship_name();
+        let mut all_ship_weapons = ShipWeaponCollection::get_for_ships(connection, &ship_names).key_by_ship_name();
+        let mut all_ship_target_filters = ShipTargetFilterCollection::get_for_ships(connection, &ship_names).key_by_ship_name();
+        let mut all_ship_extra_statements = ShipExtraStatementCollection::get_for_ships(connection, &ship_names).key_by_ship_name();
+
+        for (name, ship) in ships.ships.drain() {
+            ship_files.insert(name.to_string(), ShipFile {
+                ship,
+                ship_abilities: all_ship_abilities.remove(&name).unwrap_or_default(),
+                ship_weapons: all_ship_weapons.remove(&name).unwrap_or_default(),
+                ship_target_filters: all_ship_target_filters.remove(&name).unwrap_or_default(),
+                ship_extra_statements: all_ship_extra_statements.remove(&name).unwrap_or_default()
+            });
+        }
+
+        Self {
+            ship_files
+        }
+    }
+
+    /// Get all Ship Files from the Database.
+    pub fn get_all_ship_files(connection: &mut SqliteConnection) -> Self {
+        let ships = ShipCollection::get_all_ships(connection);
+
+        Self::from_ship_collection(connection, ships)
+    }
+
+    /// Get ship files from a list of ship_name Strings. Loaded `SQLITE_MAX_VARIABLES` names at a time.
+    pub fn get_ship_files_from_names(connection: &mut SqliteConnection, ship_names: Vec<String>) -> Self {
+        let mut ship_files = HashMap::new();
+
+        for chunk in ship_names.chunks(SQLITE_MAX_VARIABLES) {
+            let ships = ShipCollection::get_ships_from_names(connection, chunk.to_vec());
+
+            ship_files.extend(Self::from_ship_collection(connection, ships).ship_files);
+        }
+
+        Self {
+            ship_files
+        }
+    }
+
+    /// Render every ship file, keyed by ship name.
+    pub fn rendered(&self) -> HashMap<String, String> {
+        self.ship_files
+            .iter()
+            .map(|(name, ship)| (name.to_string(), ship.to_string()))
+            .collect()
+    }
+}
+
+///
+/// NewShipFile
+///
+#[derive(Debug)]
+pub struct NewShipFile {
+    pub ship: Ship,
+    pub ship_abilities: NewShipAbilityCollection,
+    pub ship_weapons: NewShipWeaponCollection,
+    pub ship_target_filters: NewShipTargetFilterCollection,
+    pub ship_extra_statements: NewShipExtraStatementCollection
+}
+
+impl NewDataFile for NewShipFile {
+    const EXTENSION: &'static str = "ship";
+
+    /// Create new `NewShipFile` from the body of a *.ship file.
+    fn from_string(ship_name: &str, contents: &str) -> Result<Self, ParseError> {
+        let chunk = lua::parse(contents)?;
+
+        Ok(Self {
+            ship: Ship::from_chunk(ship_name, &chunk)?,
+            ship_abilities: NewShipAbilityCollection::from_chunk(ship_name, &chunk, contents)?,
+            ship_weapons: NewShipWeaponCollection::from_chunk(ship_name, &chunk, contents)?,
+            ship_target_filters: NewShipTargetFilterCollection::from_chunk(ship_name, &chunk, contents),
+            ship_extra_statements: NewShipExtraStatementCollection::from_chunk(ship_name, &chunk, contents)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct NewShipFileCollection {
+    pub new_ship_files: Vec<NewShipFile>
+}
+
+impl NewDataFileCollection for NewShipFileCollection {
+    type File = NewShipFile;
+
+    /// Create from a Vector of NewShipFile
+    fn from_vec(new_ship_files: Vec<NewShipFile>) -> Self {
+        Self {
+            new_ship_files
+        }
+    }
+
+    /// Get list of ship names in the collection.
+    fn get_names(&self) -> Vec<String> {
+        self.new_ship_files.iter().map(|sf| sf.ship.name.to_string()).collect()
+    }
+
+    fn get_all_names(connection: &mut SqliteConnection) -> Vec<String> {
+        ShipCollection::get_all_ships(connection).get_names()
+    }
+
+    fn render(connection: &mut SqliteConnection, names: &[String]) -> HashMap<String, String> {
+        ShipFileCollection::get_ship_files_from_names(connection, names.to_vec()).rendered()
+    }
+
+    fn delete_named(connection: &mut SqliteConnection, names: &[String]) -> Result<(), ImportError> {
+        use crate::schema::{ ships, ship_weapons, ship_abilities, ship_target_filters, ship_extra_statements };
+
+        delete_rows!(connection, names,
+            ship_extra_statements.ship_name,
+            ship_target_filters.ship_name,
+            ship_abilities.ship_name,
+            ship_weapons.ship_name,
+            ships.name
+        );
+
+        Ok(())
+    }
+
+    fn delete_all(connection: &mut SqliteConnection) -> Result<(), ImportError> {
+        use crate::schema::{ ships, ship_weapons, ship_abilities, ship_target_filters, ship_extra_statements };
+
+        delete_rows!(connection, ship_extra_statements, ship_target_filters, ship_abilities, ship_weapons, ships);
+
+        Ok(())
+    }
+
+    fn insert_rows(&self, connection: &mut SqliteConnection, options: &ImportOptions) -> Result<(), ImportError> {
+        use crate::schema::{ ships, ship_weapons, ship_abilities, ship_target_filters, ship_extra_statements };
+
+        let mut ships_to_insert: Vec<&Ship> = Vec::new();
+        let mut ship_weapons_to_insert: Vec<&NewShipWeapon> = Vec::new();
+        let mut ship_abilities_to_insert: Vec<&NewShipAbility> = Vec::new();
+        let mut ship_target_filters_to_insert: Vec<&NewShipTargetFilter> = Vec::new();
+        let mut ship_extra_statements_to_insert: Vec<&NewShipExtraStatement> = Vec::new();
+
+        for ship_file in self.new_ship_files.iter() {
+            ships_to_insert.push(&ship_file.ship);
+
+            ship_file.ship_weapons.ship_weapons.iter().for_each(|x| ship_weapons_to_insert.push(x));
+            ship_file.ship_abilities.ship_abilities.iter().for_each(|x| ship_abilities_to_insert.push(x));
+            ship_file.ship_target_filters.ship_target_filters.iter().for_each(|x| ship_target_filters_to_insert.push(x));
+            ship_file.ship_extra_statements.ship_extra_statements.iter().for_each(|x| ship_extra_statements_to_insert.push(x));
+        }
+
+        insert_rows!(connection, ships::table, "ships", 6, ships_to_insert, options, |x| x.name.to_string());
+        insert_rows!(connection, ship_weapons::table, "ship_weapons", 4, ship_weapons_to_insert, options, |x| x.ship_name.to_string());
+        insert_rows!(connection, ship_abilities::table, "ship_abilities", 3, ship_abilities_to_insert, options, |x| x.ship_name.to_string());
+        insert_rows!(connection, ship_target_filters::table, "ship_target_filters", 2, ship_target_filters_to_insert, options, |x| x.ship_name.to_string());
+        insert_rows!(connection, ship_extra_statements::table, "ship_extra_statements", 4, ship_extra_statements_to_insert, options, |x| x.ship_name.to_string());
+
+        ShipWeaponCollection::match_weapon_names(connection).map_err(|e| ImportError::new("ship_weapons", None, e))?;
+
+        Ok(())
+    }
+}
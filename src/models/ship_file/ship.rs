@@ -0,0 +1,184 @@
+use diesel::prelude::*;
+use crate::schema::{ships};
+use crate::models::lua::{ Argument, Chunk, Statement, StatementKind, Value };
+use crate::models::data_file::PropertyModel;
+use crate::models::parse_error::ParseError;
+use std::collections::HashMap;
+
+/// The `NewShipType` properties Scaffold models, in the order it writes them. Any other property is kept as a
+/// `ShipExtraStatement`.
+pub const SHIP_PROPERTIES: [&str; 5] = [
+    "maxhealth",
+    "buildCost",
+    "buildTime",
+    "mainEngineMaxSpeed",
+    "armourFamily"
+];
+
+///
+/// Ship
+///
+#[derive(Queryable, Selectable, Identifiable, Default, Insertable, Debug)]
+#[diesel(table_name = ships)]
+#[diesel(primary_key(name))]
+pub struct Ship {
+    pub name: String,
+    pub max_health: Option<f32>,
+    pub build_cost: Option<f32>,
+    pub build_time: Option<f32>,
+    pub main_engine_max_speed: Option<f32>,
+    pub armour_family: Option<String>
+}
+
+impl Ship {
+    /// Create a `Ship` Model from a `name` and the parsed statements in `chunk`. Only the first assignment to each
+    /// property is used.
+    pub fn from_chunk(name: &str, chunk: &Chunk) -> Result<Self, ParseError> {
+        if !chunk.statements.iter().any(Self::is_start) {
+            return Err(ParseError::new("Missing `NewShipType = StartShipConfig()`"));
+        }
+
+        let mut ship = Self {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        for statement in chunk.statements.iter() {
+            let Some((property, Argument { value, .. })) = Self::property(statement) else {
+                continue;
+            };
+
+            let number = value.as_number().map(|n| n as f32);
+
+            match property {
+                "maxhealth" if ship.max_health.is_none() => ship.max_health = number,
+                "buildCost" if ship.build_cost.is_none() => ship.build_cost = number,
+                "buildTime" if ship.build_time.is_none() => ship.build_time = number,
+                "mainEngineMaxSpeed" if ship.main_engine_max_speed.is_none() => ship.main_engine_max_speed = number,
+                "armourFamily" if ship.armour_family.is_none() => ship.armour_family = value.as_str().map(|s| s.to_string()),
+                _ => {}
+            }
+        }
+
+        Ok(ship)
+    }
+
+    /// Get the Ship for a specific ship name. Possible that it can't be found.
+    pub fn get_for_ship(connection: &mut SqliteConnection, ship_name: &str) -> Option<Self> {
+        use crate::schema::ships::dsl::*;
+
+        let res = ships
+                    .filter(name.eq(ship_name))
+                    .first(connection)
+                    .optional();
+
+        res.unwrap_or_default()
+    }
+}
+
+impl PropertyModel for Ship {
+    const START: &'static str = "NewShipType = StartShipConfig()";
+
+    /// Is `statement` the `NewShipType = StartShipConfig()` line?
+    fn is_start(statement: &Statement) -> bool {
+        match statement.as_assignment() {
+            Some(assignment) => {
+                assignment.targets == ["NewShipType"]
+                    && matches!(assignment.values.first().map(|a| &a.value), Some(Value::Call(call)) if call.name == "StartShipConfig")
+            },
+            None => false
+        }
+    }
+
+    /// If `statement` sets one of the `SHIP_PROPERTIES` to a value Scaffold can store, return the property and its
+    /// value. Values wrapped in `getShipNum(NewShipType, "<property>", <value>)` or `getShipStr(...)` are unwrapped;
+    /// anything else that isn't a plain number (or string, for `armourFamily`) is left to `ShipExtraStatement`.
+    fn property(statement: &Statement) -> Option<(&'static str, &Argument)> {
+        let StatementKind::Assignment(assignment) = &statement.kind else {
+            return None;
+        };
+
+        if assignment.targets.len() != 1 || assignment.values.len() != 1 {
+            return None;
+        }
+
+        let name = assignment.targets[0].strip_prefix("NewShipType.")?;
+        let property = SHIP_PROPERTIES.iter().find(|p| **p == name)?;
+
+        let argument = match &assignment.values[0].value {
+            Value::Call(call) if call.name == "getShipNum" || call.name == "getShipStr" => call.arg(2)?,
+            _ => &assignment.values[0]
+        };
+
+        let usable = match *property {
+            "armourFamily" => argument.value.as_str().is_some(),
+            _ => argument.value.as_number().is_some()
+        };
+
+        if usable {
+            Some((property, argument))
+        } else {
+            None
+        }
+    }
+
+    fn values(&self) -> Vec<(&'static str, String)> {
+        let values = [
+            ("maxhealth", self.max_health.map(|v| v.to_string())),
+            ("buildCost", self.build_cost.map(|v| v.to_string())),
+            ("buildTime", self.build_time.map(|v| v.to_string())),
+            ("mainEngineMaxSpeed", self.main_engine_max_speed.map(|v| v.to_string())),
+            ("armourFamily", self.armour_family.as_ref().map(|v| format!("\"{}\"", v)))
+        ];
+
+        values.into_iter().filter_map(|(property, value)| value.map(|v| (property, v))).collect()
+    }
+
+    /// `maxhealth`, `mainEngineMaxSpeed` and `armourFamily` are wrapped in `getShipNum(...)` or `getShipStr(...)`, the
+    /// way the stock files write them.
+    fn statement(property: &str, value: &str) -> String {
+        match property {
+            "maxhealth" | "mainEngineMaxSpeed" => format!("NewShipType.{} = getShipNum(NewShipType, \"{}\", {})", property, property, value),
+            "armourFamily" => format!("NewShipType.{} = getShipStr(NewShipType, \"{}\", {})", property, property, value),
+            _ => format!("NewShipType.{} = {}", property, value)
+        }
+    }
+}
+
+pub struct ShipCollection {
+    pub ships: HashMap<String, Ship>
+}
+
+impl ShipCollection {
+    /// Get all Ships from the Database.
+    pub fn get_all_ships(connection: &mut SqliteConnection) -> Self {
+        use crate::schema::ships::dsl::*;
+
+        let ships_vec = ships
+                            .load::<Ship>(connection)
+                            .expect("Could not load ships table!");
+
+        Self {
+            ships: ships_vec.into_iter().map(|s| (s.name.to_string(), s)).collect()
+        }
+    }
+
+    /// Get ships from a list of ship_name Strings
+    pub fn get_ships_from_names(connection: &mut SqliteConnection, ship_names: Vec<String>) -> Self {
+        use crate::schema::ships::dsl::*;
+
+        let ships_vec = ships
+                            .filter(name.eq_any(ship_names))
+                            .load::<Ship>(connection)
+                            .expect("Could not load ships table!");
+
+        Self {
+            ships: ships_vec.into_iter().map(|s| (s.name.to_string(), s)).collect()
+        }
+    }
+
+    /// Get list of Names
+    pub fn get_names(&self) -> Vec<String> {
+        self.ships.keys().map(|n| n.to_string()).collect()
+    }
+}
@@ -0,0 +1,138 @@
+use diesel::prelude::*;
+use crate::schema::{ship_abilities};
+use super::ship::Ship;
+use super::ship_weapon::arguments_from;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
+use std::fmt;
+use std::collections::HashMap;
+
+///
+/// Ship Ability
+///
+/// An `addAbility(NewShipType, "<ability>", ...)` call. The ability's own arguments vary from one ability to the
+/// next, so they're kept verbatim in `arguments`.
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Ship, foreign_key = ship_name))]
+#[diesel(table_name = ship_abilities)]
+pub struct ShipAbility {
+    pub id: i32,
+    pub ship_name: String,
+    pub ability: String,
+    pub arguments: String
+}
+
+impl fmt::Display for ShipAbility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "addAbility(NewShipType, \"{}\"", self.ability)?;
+
+        if !self.arguments.is_empty() {
+            write!(f, ", {}", self.arguments)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// Array of `ShipAbility`, in the order they were imported.
+#[derive(Debug)]
+pub struct ShipAbilityCollection {
+    pub ship_abilities: Vec<ShipAbility>
+}
+
+impl Default for ShipAbilityCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShipAbilityCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            ship_abilities: Vec::new()
+        }
+    }
+
+    /// Get abilities for a specific ship name.
+    pub fn get_for_ship(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::ship_abilities::dsl::*;
+
+        let vec = ship_abilities
+                    .filter(ship_name.eq(name))
+                    .order(id.asc())
+                    .load::<ShipAbility>(connection)
+                    .expect("Error loading ship abilities!");
+
+        Self {
+            ship_abilities: vec
+        }
+    }
+
+    /// Get abilities for a list of ship names.
+    pub fn get_for_ships(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::ship_abilities::dsl::*;
+
+        let vec = ship_abilities
+                    .filter(ship_name.eq_any(names))
+                    .order(id.asc())
+                    .load::<ShipAbility>(connection)
+                    .expect("Error loading ship abilities!");
+
+        Self {
+            ship_abilities: vec
+        }
+    }
+
+    /// Consume the collection, divide into HashMap by `ship_name`. Keeps the original order.
+    pub fn key_by_ship_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for sa in self.ship_abilities {
+            map
+                .entry(sa.ship_name.to_string())
+                .or_default()
+                .ship_abilities.push(sa);
+        }
+
+        map
+    }
+}
+
+///
+/// NewShipAbility
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = ship_abilities)]
+pub struct NewShipAbility {
+    pub ship_name: String,
+    pub ability: String,
+    pub arguments: String
+}
+
+/// Array of `NewShipAbility` with helper methods.
+#[derive(Debug)]
+pub struct NewShipAbilityCollection {
+    pub ship_abilities: Vec<NewShipAbility>
+}
+
+impl NewShipAbilityCollection {
+    /// Build from every parsed `addAbility(...)` lua call in `chunk`.
+    pub fn from_chunk(ship_name: &str, chunk: &Chunk, source: &str) -> Result<Self, ParseError> {
+        let mut ship_abilities = Vec::new();
+
+        for call in chunk.calls_named("addAbility") {
+            ship_abilities.push(NewShipAbility {
+                ship_name: ship_name.to_string(),
+                ability: call.string_arg(1)?,
+                arguments: arguments_from(call, 2, source)
+            });
+        }
+
+        Ok(Self {
+            ship_abilities
+        })
+    }
+}
@@ -0,0 +1,141 @@
+use diesel::prelude::*;
+use crate::schema::{ship_extra_statements};
+use super::ship::Ship;
+use super::SHIP_FILE_CALLS;
+use crate::models::lua::{ Call, Chunk };
+use crate::models::data_file::{ anchored_extras, without_watermark };
+use std::fmt;
+use std::collections::HashMap;
+
+///
+/// Ship Extra Statement
+///
+/// Any statement in a *.ship file that isn't one of the `SHIP_FILE_CALLS` (`NewShipType` properties, `LoadModel`,
+/// `addShield`, comments, ...). Stored verbatim with the `anchor` of the modelled call it follows (see
+/// `data_file::anchor`), so it can be written back where it was found; `position` orders the statements. The
+/// `StartShipConfig()` line and the lines `Ship` is read from are kept here too, and given the `Ship`'s values when
+/// written back (see `data_file::fill_in_properties`).
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Ship, foreign_key = ship_name))]
+#[diesel(table_name = ship_extra_statements)]
+pub struct ShipExtraStatement {
+    pub id: i32,
+    pub ship_name: String,
+    pub position: i32,
+    pub statement: String,
+    pub anchor: Option<String>
+}
+
+impl fmt::Display for ShipExtraStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.statement)
+    }
+}
+
+/// Array of `ShipExtraStatement`, ordered by `position`.
+#[derive(Debug)]
+pub struct ShipExtraStatementCollection {
+    pub ship_extra_statements: Vec<ShipExtraStatement>
+}
+
+impl Default for ShipExtraStatementCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShipExtraStatementCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            ship_extra_statements: Vec::new()
+        }
+    }
+
+    /// Get extra statements for a specific ship name.
+    pub fn get_for_ship(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::ship_extra_statements::dsl::*;
+
+        let vec = ship_extra_statements
+                    .filter(ship_name.eq(name))
+                    .order(position.asc())
+                    .load::<ShipExtraStatement>(connection)
+                    .expect("Error loading ship extra statements!");
+
+        Self {
+            ship_extra_statements: vec
+        }
+    }
+
+    /// Get extra statements for a list of ship names.
+    pub fn get_for_ships(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::ship_extra_statements::dsl::*;
+
+        let vec = ship_extra_statements
+                    .filter(ship_name.eq_any(names))
+                    .order(position.asc())
+                    .load::<ShipExtraStatement>(connection)
+                    .expect("Error loading ship extra statements!");
+
+        Self {
+            ship_extra_statements: vec
+        }
+    }
+
+    /// Consume the collection, divide into HashMap by `ship_name`. Keeps the `position` order.
+    pub fn key_by_ship_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for ss in self.ship_extra_statements {
+            map
+                .entry(ss.ship_name.to_string())
+                .or_default()
+                .ship_extra_statements.push(ss);
+        }
+
+        map
+    }
+}
+
+///
+/// NewShipExtraStatement
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = ship_extra_statements)]
+pub struct NewShipExtraStatement {
+    pub ship_name: String,
+    pub position: i32,
+    pub statement: String,
+    pub anchor: Option<String>
+}
+
+/// Array of `NewShipExtraStatement` with helper methods.
+#[derive(Debug)]
+pub struct NewShipExtraStatementCollection {
+    pub ship_extra_statements: Vec<NewShipExtraStatement>
+}
+
+impl NewShipExtraStatementCollection {
+    /// Collect every statement in `chunk` but the calls in `SHIP_FILE_CALLS`, each anchored to the call before it.
+    /// The Scaffold watermark is skipped so it doesn't pile up on every round-trip.
+    pub fn from_chunk(ship_name: &str, chunk: &Chunk, source: &str) -> Self {
+        let modelled = |call: &Call| SHIP_FILE_CALLS.contains(&call.name.as_str());
+
+        let ship_extra_statements = anchored_extras(without_watermark(chunk), source, modelled)
+            .into_iter()
+            .enumerate()
+            .map(|(position, (anchor, statement))| NewShipExtraStatement {
+                ship_name: ship_name.to_string(),
+                position: position as i32,
+                statement,
+                anchor
+            })
+            .collect();
+
+        Self {
+            ship_extra_statements
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use diesel::prelude::*;
+use crate::schema::{ship_target_filters};
+use super::ship::Ship;
+use super::ship_weapon::arguments_from;
+use crate::models::lua::Chunk;
+use std::fmt;
+use std::collections::HashMap;
+
+///
+/// Ship Target Filter
+///
+/// A `setTargetFilter(NewShipType, ...)` call. Its arguments are kept verbatim in `arguments`.
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Ship, foreign_key = ship_name))]
+#[diesel(table_name = ship_target_filters)]
+pub struct ShipTargetFilter {
+    pub id: i32,
+    pub ship_name: String,
+    pub arguments: String
+}
+
+impl fmt::Display for ShipTargetFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.arguments.is_empty() {
+            write!(f, "setTargetFilter(NewShipType)")
+        } else {
+            write!(f, "setTargetFilter(NewShipType, {})", self.arguments)
+        }
+    }
+}
+
+/// Array of `ShipTargetFilter`, in the order they were imported.
+#[derive(Debug)]
+pub struct ShipTargetFilterCollection {
+    pub ship_target_filters: Vec<ShipTargetFilter>
+}
+
+impl Default for ShipTargetFilterCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShipTargetFilterCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            ship_target_filters: Vec::new()
+        }
+    }
+
+    /// Get target filters for a specific ship name.
+    pub fn get_for_ship(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::ship_target_filters::dsl::*;
+
+        let vec = ship_target_filters
+                    .filter(ship_name.eq(name))
+                    .order(id.asc())
+                    .load::<ShipTargetFilter>(connection)
+                    .expect("Error loading ship target filters!");
+
+        Self {
+            ship_target_filters: vec
+        }
+    }
+
+    /// Get target filters for a list of ship names.
+    pub fn get_for_ships(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::ship_target_filters::dsl::*;
+
+        let vec = ship_target_filters
+                    .filter(ship_name.eq_any(names))
+                    .order(id.asc())
+                    .load::<ShipTargetFilter>(connection)
+                    .expect("Error loading ship target filters!");
+
+        Self {
+            ship_target_filters: vec
+        }
+    }
+
+    /// Consume the collection, divide into HashMap by `ship_name`. Keeps the original order.
+    pub fn key_by_ship_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for st in self.ship_target_filters {
+            map
+                .entry(st.ship_name.to_string())
+                .or_default()
+                .ship_target_filters.push(st);
+        }
+
+        map
+    }
+}
+
+///
+/// NewShipTargetFilter
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = ship_target_filters)]
+pub struct NewShipTargetFilter {
+    pub ship_name: String,
+    pub arguments: String
+}
+
+/// Array of `NewShipTargetFilter` with helper methods.
+#[derive(Debug)]
+pub struct NewShipTargetFilterCollection {
+    pub ship_target_filters: Vec<NewShipTargetFilter>
+}
+
+impl NewShipTargetFilterCollection {
+    /// Build from every parsed `setTargetFilter(...)` lua call in `chunk`.
+    pub fn from_chunk(ship_name: &str, chunk: &Chunk, source: &str) -> Self {
+        let ship_target_filters = chunk
+            .calls_named("setTargetFilter")
+            .map(|call| NewShipTargetFilter {
+                ship_name: ship_name.to_string(),
+                arguments: arguments_from(call, 1, source)
+            })
+            .collect();
+
+        Self {
+            ship_target_filters
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use diesel::prelude::*;
+use crate::schema::{ship_weapons};
+use super::ship::Ship;
+use crate::models::wepn_file::weapon::{ Weapon, WeaponCollection };
+use crate::models::lua::{ Call, Chunk };
+use crate::models::parse_error::ParseError;
+use std::fmt;
+use std::collections::{ HashMap, HashSet };
+
+/// Source text of every argument of `call` from `start` on, joined with `, `.
+pub fn arguments_from(call: &Call, start: usize, source: &str) -> String {
+    call.args
+        .iter()
+        .skip(start)
+        .map(|a| a.span.text(source))
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
+
+///
+/// Ship Weapon
+///
+/// A `StartShipWeaponConfig(...)` hardpoint binding: which weapon is mounted on which hardpoint. Any arguments after
+/// the hardpoint are kept verbatim in `extra_arguments`. `weapon_name` is a foreign key to `weapons.name`. The game
+/// matches weapon names without regard to case, so on import it's spelled the way the weapon is.
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Ship, foreign_key = ship_name))]
+#[diesel(belongs_to(Weapon, foreign_key = weapon_name))]
+#[diesel(table_name = ship_weapons)]
+pub struct ShipWeapon {
+    pub id: i32,
+    pub ship_name: String,
+    pub weapon_name: String,
+    pub hardpoint: String,
+    pub extra_arguments: String
+}
+
+impl fmt::Display for ShipWeapon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StartShipWeaponConfig(NewShipType, \"{}\", \"{}\"", self.weapon_name, self.hardpoint)?;
+
+        if !self.extra_arguments.is_empty() {
+            write!(f, ", {}", self.extra_arguments)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// Array of `ShipWeapon`, in the order they were imported.
+#[derive(Debug)]
+pub struct ShipWeaponCollection {
+    pub ship_weapons: Vec<ShipWeapon>
+}
+
+impl Default for ShipWeaponCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShipWeaponCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            ship_weapons: Vec::new()
+        }
+    }
+
+    /// Get weapon bindings for a specific ship name.
+    pub fn get_for_ship(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::ship_weapons::dsl::*;
+
+        let vec = ship_weapons
+                    .filter(ship_name.eq(name))
+                    .order(id.asc())
+                    .load::<ShipWeapon>(connection)
+                    .expect("Error loading ship weapons!");
+
+        Self {
+            ship_weapons: vec
+        }
+    }
+
+    /// Get weapon bindings for a list of ship names.
+    pub fn get_for_ships(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::ship_weapons::dsl::*;
+
+        let vec = ship_weapons
+                    .filter(ship_name.eq_any(names))
+                    .order(id.asc())
+                    .load::<ShipWeapon>(connection)
+                    .expect("Error loading ship weapons!");
+
+        Self {
+            ship_weapons: vec
+        }
+    }
+
+    /// Get the weapon bindings that use `weapon`, compared without regard to case.
+    pub fn get_for_weapon(connection: &mut SqliteConnection, weapon: &str) -> Self {
+        use crate::schema::ship_weapons::dsl::*;
+
+        let vec = ship_weapons
+                    .order(id.asc())
+                    .load::<ShipWeapon>(connection)
+                    .expect("Error loading ship weapons!");
+
+        Self {
+            ship_weapons: vec.into_iter().filter(|sw| sw.weapon_name.eq_ignore_ascii_case(weapon)).collect()
+        }
+    }
+
+    /// Get the weapon bindings whose weapon isn't in the `weapons` table, compared without regard to case.
+    pub fn get_dangling(connection: &mut SqliteConnection) -> Self {
+        use crate::schema::ship_weapons::dsl::*;
+
+        let names: HashSet<String> = WeaponCollection::get_all_weapons(connection)
+            .get_names()
+            .iter()
+            .map(|n| n.to_lowercase())
+            .collect();
+
+        let vec = ship_weapons
+                    .order((ship_name.asc(), id.asc()))
+                    .load::<ShipWeapon>(connection)
+                    .expect("Error loading ship weapons!");
+
+        Self {
+            ship_weapons: vec.into_iter().filter(|sw| !names.contains(&sw.weapon_name.to_lowercase())).collect()
+        }
+    }
+
+    /// Spell each weapon name the way the weapon it refers to is spelled, where the two only differ in case, so the
+    /// foreign key to `weapons.name` holds. Names that don't match any weapon are left alone.
+    pub fn match_weapon_names(connection: &mut SqliteConnection) -> QueryResult<()> {
+        use crate::schema::ship_weapons::dsl::*;
+
+        let names: HashMap<String, String> = WeaponCollection::get_all_weapons(connection)
+            .get_names()
+            .into_iter()
+            .map(|n| (n.to_lowercase(), n))
+            .collect();
+
+        for sw in ship_weapons.load::<ShipWeapon>(connection)? {
+            match names.get(&sw.weapon_name.to_lowercase()) {
+                Some(name) if *name != sw.weapon_name => {
+                    diesel::update(ship_weapons.find(sw.id)).set(weapon_name.eq(name)).execute(connection)?;
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the distinct ship names in the collection, sorted.
+    pub fn get_ship_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.ship_weapons.iter().map(|sw| sw.ship_name.to_string()).collect();
+
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// Consume the collection, divide into HashMap by `ship_name`. Keeps the original order.
+    pub fn key_by_ship_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for sw in self.ship_weapons {
+            map
+                .entry(sw.ship_name.to_string())
+                .or_default()
+                .ship_weapons.push(sw);
+        }
+
+        map
+    }
+}
+
+///
+/// NewShipWeapon
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = ship_weapons)]
+pub struct NewShipWeapon {
+    pub ship_name: String,
+    pub weapon_name: String,
+    pub hardpoint: String,
+    pub extra_arguments: String
+}
+
+/// Array of `NewShipWeapon` with helper methods.
+#[derive(Debug)]
+pub struct NewShipWeaponCollection {
+    pub ship_weapons: Vec<NewShipWeapon>
+}
+
+impl NewShipWeaponCollection {
+    /// Build from every parsed `StartShipWeaponConfig(...)` lua call in `chunk`.
+    pub fn from_chunk(ship_name: &str, chunk: &Chunk, source: &str) -> Result<Self, ParseError> {
+        let mut ship_weapons = Vec::new();
+
+        for call in chunk.calls_named("StartShipWeaponConfig") {
+            ship_weapons.push(NewShipWeapon {
+                ship_name: ship_name.to_string(),
+                weapon_name: call.string_arg(1)?,
+                hardpoint: call.string_arg(2)?,
+                extra_arguments: arguments_from(call, 3, source)
+            });
+        }
+
+        Ok(Self {
+            ship_weapons
+        })
+    }
+}
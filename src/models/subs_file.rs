@@ -0,0 +1,237 @@
+pub mod subsystem;
+pub mod subsystem_weapon;
+pub mod subsystem_extra_statement;
+
+use std::io::prelude::*;
+use std::fmt;
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use subsystem::{ Subsystem, SubsystemCollection };
+use subsystem_weapon::{ SubsystemWeaponCollection, NewSubsystemWeapon, NewSubsystemWeaponCollection };
+use subsystem_extra_statement::{ SubsystemExtraStatementCollection, NewSubsystemExtraStatement, NewSubsystemExtraStatementCollection };
+use super::wepn_file::{ ImportOptions, SQLITE_MAX_VARIABLES, WEAPON_FILE_HEADER };
+use super::data_file::{ DataFile, NewDataFile, NewDataFileCollection, anchor, fill_in_properties, lay_out };
+use super::lua;
+use super::parse_error::ParseError;
+use super::wepn_file::import_error::ImportError;
+
+/// Subsystem files carry the same watermark as weapon files.
+pub const SUBS_FILE_HEADER: &str = WEAPON_FILE_HEADER;
+
+/// The lua calls Scaffold models, besides `StartSubSystemConfig()` and the `SUBSYSTEM_PROPERTIES`. Anything else is
+/// kept as a `SubsystemExtraStatement`.
+pub const SUBS_FILE_CALLS: [&str; 1] = [
+    "StartSubSystemWeaponConfig"
+];
+
+///
+/// Subsystem File (*.subs)
+///
+/// Modules, turrets, sensors and the like. Laid out like a `ShipFile`, with its own health and build cost, and the
+/// weapons it mounts.
+///
+#[derive(Debug)]
+pub struct SubsFile {
+    pub subsystem: Subsystem,
+    pub subsystem_weapons: SubsystemWeaponCollection,
+    pub subsystem_extra_statements: SubsystemExtraStatementCollection
+}
+
+impl fmt::Display for SubsFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Each modelled call is paired with its anchor and the section it belongs to; see `lay_out`.
+        let modelled: Vec<(String, &str, String)> = self.subsystem_weapons.subsystem_weapons
+            .iter()
+            .enumerate()
+            .map(|(index, weapon)| (anchor("StartSubSystemWeaponConfig", index), "weapons", weapon.to_string()))
+            .collect();
+
+        let extras: Vec<(Option<&str>, String)> = self.subsystem_extra_statements.subsystem_extra_statements
+            .iter()
+            .map(|extra| (extra.anchor.as_deref(), extra.to_string()))
+            .collect();
+
+        // The properties are written back among the extra statements, in the lines they were read from.
+        let extras = fill_in_properties(&self.subsystem, &extras);
+
+        write!(f, "{}{}", SUBS_FILE_HEADER, lay_out(&modelled, &extras, None))
+    }
+}
+
+impl SubsFile {
+    // Get the Subsystem File for a specific subsystem name. Possible that it can't be found.
+    pub fn get_for_subsystem(connection: &mut SqliteConnection, subsystem_name: &str) -> Option<Self> {
+        let subsystem = Subsystem::get_for_subsystem(connection, subsystem_name)?;
+
+        Some(Self {
+            subsystem,
+            subsystem_weapons: SubsystemWeaponCollection::get_for_subsystem(connection, subsystem_name),
+            subsystem_extra_statements: SubsystemExtraStatementCollection::get_for_subsystem(connection, subsystem_name)
+        })
+    }
+}
+
+impl DataFile for SubsFile {
+    const EXTENSION: &'static str = "subs";
+
+    fn name(&self) -> &str {
+        &self.subsystem.name
+    }
+}
+
+#[derive(Default)]
+pub struct SubsFileCollection {
+    pub subs_files: HashMap<String, SubsFile>
+}
+
+impl SubsFileCollection {
+    /// Create SubsFileCollection from a SubsystemCollection
+    pub fn from_subsystem_collection(connection: &mut SqliteConnection, mut subsystems: SubsystemCollection) -> Self {
+        let subsystem_names = subsystems.get_names();
+        let mut subs_files = HashMap::new();
+
+        let mut all_subsystem_weapons = SubsystemWeaponCollection::get_for_subsystems(connection, &subsystem_names).key_by_subsystem_name();
+        let mut all_subsystem_extra_statements = SubsystemExtraStatementCollection::get_for_subsystems(connection, &subsystem_names).key_by_subsystem_name();
+
+        for (name, subsystem) in subsystems.subsystems.drain() {
+            subs_files.insert(name.to_string(), SubsFile {
+                subsystem,
+                subsystem_weapons: all_subsystem_weapons.remove(&name).unwrap_or_default(),
+                subsystem_extra_statements: all_subsystem_extra_statements.remove(&name).unwrap_or_default()
+            });
+        }
+
+        Self {
+            subs_files
+        }
+    }
+
+    /// Get all Subsystem Files from the Database.
+    pub fn get_all_subs_files(connection: &mut SqliteConnection) -> Self {
+        let subsystems = SubsystemCollection::get_all_subsystems(connection);
+
+        Self::from_subsystem_collection(connection, subsystems)
+    }
+
+    /// Get subsystem files from a list of subsystem_name Strings. Loaded `SQLITE_MAX_VARIABLES` names at a time.
+    pub fn get_subs_files_from_names(connection: &mut SqliteConnection, subsystem_names: Vec<String>) -> Self {
+        let mut subs_files = HashMap::new();
+
+        for chunk in subsystem_names.chunks(SQLITE_MAX_VARIABLES) {
+            let subsystems = SubsystemCollection::get_subsystems_from_names(connection, chunk.to_vec());
+
+            subs_files.extend(Self::from_subsystem_collection(connection, subsystems).subs_files);
+        }
+
+        Self {
+            subs_files
+        }
+    }
+
+    /// Render every subsystem file, keyed by subsystem name.
+    pub fn rendered(&self) -> HashMap<String, String> {
+        self.subs_files
+            .iter()
+            .map(|(name, subs)| (name.to_string(), subs.to_string()))
+            .collect()
+    }
+}
+
+///
+/// NewSubsFile
+///
+#[derive(Debug)]
+pub struct NewSubsFile {
+    pub subsystem: Subsystem,
+    pub subsystem_weapons: NewSubsystemWeaponCollection,
+    pub subsystem_extra_statements: NewSubsystemExtraStatementCollection
+}
+
+impl NewDataFile for NewSubsFile {
+    const EXTENSION: &'static str = "subs";
+
+    /// Create new `NewSubsFile` from the body of a *.subs file.
+    fn from_string(subsystem_name: &str, contents: &str) -> Result<Self, ParseError> {
+        let chunk = lua::parse(contents)?;
+
+        Ok(Self {
+            subsystem: Subsystem::from_chunk(subsystem_name, &chunk)?,
+            subsystem_weapons: NewSubsystemWeaponCollection::from_chunk(subsystem_name, &chunk, contents)?,
+            subsystem_extra_statements: NewSubsystemExtraStatementCollection::from_chunk(subsystem_name, &chunk, contents)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct NewSubsFileCollection {
+    pub new_subs_files: Vec<NewSubsFile>
+}
+
+impl NewDataFileCollection for NewSubsFileCollection {
+    type File = NewSubsFile;
+
+    /// Create from a Vector of NewSubsFile
+    fn from_vec(new_subs_files: Vec<NewSubsFile>) -> Self {
+        Self {
+            new_subs_files
+        }
+    }
+
+    /// Get list of subsystem names in the collection.
+    fn get_names(&self) -> Vec<String> {
+        self.new_subs_files.iter().map(|sf| sf.subsystem.name.to_string()).collect()
+    }
+
+    fn get_all_names(connection: &mut SqliteConnection) -> Vec<String> {
+        SubsystemCollection::get_all_subsystems(connection).get_names()
+    }
+
+    fn render(connection: &mut SqliteConnection, names: &[String]) -> HashMap<String, String> {
+        SubsFileCollection::get_subs_files_from_names(connection, names.to_vec()).rendered()
+    }
+
+    fn delete_named(connection: &mut SqliteConnection, names: &[String]) -> Result<(), ImportError> {
+        use crate::schema::{ subsystems, subsystem_weapons, subsystem_extra_statements };
+
+        delete_rows!(connection, names,
+            subsystem_extra_statements.subsystem_name,
+            subsystem_weapons.subsystem_name,
+            subsystems.name
+        );
+
+        Ok(())
+    }
+
+    fn delete_all(connection: &mut SqliteConnection) -> Result<(), ImportError> {
+        use crate::schema::{ subsystems, subsystem_weapons, subsystem_extra_statements };
+
+        delete_rows!(connection, subsystem_extra_statements, subsystem_weapons, subsystems);
+
+        Ok(())
+    }
+
+    fn insert_rows(&self, connection: &mut SqliteConnection, options: &ImportOptions) -> Result<(), ImportError> {
+        use crate::schema::{ subsystems, subsystem_weapons, subsystem_extra_statements };
+
+        let mut subsystems_to_insert: Vec<&Subsystem> = Vec::new();
+        let mut subsystem_weapons_to_insert: Vec<&NewSubsystemWeapon> = Vec::new();
+        let mut subsystem_extra_statements_to_insert: Vec<&NewSubsystemExtraStatement> = Vec::new();
+
+        for subs_file in self.new_subs_files.iter() {
+            subsystems_to_insert.push(&subs_file.subsystem);
+
+            subs_file.subsystem_weapons.subsystem_weapons.iter().for_each(|x| subsystem_weapons_to_insert.push(x));
+            subs_file.subsystem_extra_statements.subsystem_extra_statements.iter().for_each(|x| subsystem_extra_statements_to_insert.push(x));
+        }
+
+        insert_rows!(connection, subsystems::table, "subsystems", 4, subsystems_to_insert, options, |x| x.name.to_string());
+        insert_rows!(connection, subsystem_weapons::table, "subsystem_weapons", 4, subsystem_weapons_to_insert, options, |x| x.subsystem_name.to_string());
+        insert_rows!(connection, subsystem_extra_statements::table, "subsystem_extra_statements", 4, subsystem_extra_statements_to_insert, options, |x| x.subsystem_name.to_string());
+
+        SubsystemWeaponCollection::match_weapon_names(connection).map_err(|e| ImportError::new("subsystem_weapons", None, e))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use diesel::prelude::*;
+use crate::schema::{subsystems};
+use crate::models::lua::{ Argument, Chunk, Statement, StatementKind, Value };
+use crate::models::data_file::PropertyModel;
+use crate::models::parse_error::ParseError;
+use std::collections::HashMap;
+
+/// The `NewSubSystemType` properties Scaffold models, in the order it writes them. Any other property is kept as a `SubsystemExtraStatement`.
+pub const SUBSYSTEM_PROPERTIES: [&str; 3] = [
+    "maxhealth",
+    "costToBuild",
+    "timeToBuild"
+];
+
+///
+/// Subsystem
+///
+#[derive(Queryable, Selectable, Identifiable, Default, Insertable, Debug)]
+#[diesel(table_name = subsystems)]
+#[diesel(primary_key(name))]
+pub struct Subsystem {
+    pub name: String,
+    pub max_health: Option<f32>,
+    pub cost_to_build: Option<f32>,
+    pub time_to_build: Option<f32>
+}
+
+impl Subsystem {
+    /// Create a `Subsystem` Model from a `name` and the parsed statements in `chunk`. Only the first assignment to
+    /// each property is used.
+    pub fn from_chunk(name: &str, chunk: &Chunk) -> Result<Self, ParseError> {
+        if !chunk.statements.iter().any(Self::is_start) {
+            return Err(ParseError::new("Missing `NewSubSystemType = StartSubSystemConfig()`"));
+        }
+
+        let mut subsystem = Self {
+            name: name.to_string(),
+            ..Default::default()
+        };
+
+        for statement in chunk.statements.iter() {
+            let Some((property, argument)) = Self::property(statement) else {
+                continue;
+            };
+
+            let value = argument.value.as_number().map(|n| n as f32);
+
+            match property {
+                "maxhealth" if subsystem.max_health.is_none() => subsystem.max_health = value,
+                "costToBuild" if subsystem.cost_to_build.is_none() => subsystem.cost_to_build = value,
+                "timeToBuild" if subsystem.time_to_build.is_none() => subsystem.time_to_build = value,
+                _ => {}
+            }
+        }
+
+        Ok(subsystem)
+    }
+
+    /// Get the Subsystem for a specific subsystem name. Possible that it can't be found.
+    pub fn get_for_subsystem(connection: &mut SqliteConnection, subsystem_name: &str) -> Option<Self> {
+        use crate::schema::subsystems::dsl::*;
+
+        let res = subsystems
+                    .filter(name.eq(subsystem_name))
+                    .first(connection)
+                    .optional();
+
+        res.unwrap_or_default()
+    }
+}
+
+impl PropertyModel for Subsystem {
+    const START: &'static str = "NewSubSystemType = StartSubSystemConfig()";
+
+    /// Is `statement` the `NewSubSystemType = StartSubSystemConfig()` line?
+    fn is_start(statement: &Statement) -> bool {
+        match statement.as_assignment() {
+            Some(assignment) => {
+                assignment.targets == ["NewSubSystemType"]
+                    && matches!(assignment.values.first().map(|a| &a.value), Some(Value::Call(call)) if call.name == "StartSubSystemConfig")
+            },
+            None => false
+        }
+    }
+
+    /// If `statement` sets one of the `SUBSYSTEM_PROPERTIES` to a plain number, return the property and its value.
+    /// Anything else is left to `SubsystemExtraStatement`.
+    fn property(statement: &Statement) -> Option<(&'static str, &Argument)> {
+        let StatementKind::Assignment(assignment) = &statement.kind else {
+            return None;
+        };
+
+        if assignment.targets.len() != 1 || assignment.values.len() != 1 {
+            return None;
+        }
+
+        let name = assignment.targets[0].strip_prefix("NewSubSystemType.")?;
+        let property = SUBSYSTEM_PROPERTIES.iter().find(|p| **p == name)?;
+
+        assignment.values[0].value.as_number().map(|_| (*property, &assignment.values[0]))
+    }
+
+    fn values(&self) -> Vec<(&'static str, String)> {
+        let values = [
+            ("maxhealth", self.max_health),
+            ("costToBuild", self.cost_to_build),
+            ("timeToBuild", self.time_to_build)
+        ];
+
+        values.into_iter().filter_map(|(property, value)| value.map(|v| (property, v.to_string()))).collect()
+    }
+
+    fn statement(property: &str, value: &str) -> String {
+        format!("NewSubSystemType.{} = {}", property, value)
+    }
+}
+
+pub struct SubsystemCollection {
+    pub subsystems: HashMap<String, Subsystem>
+}
+
+impl SubsystemCollection {
+    /// Get all Subsystems from the Database.
+    pub fn get_all_subsystems(connection: &mut SqliteConnection) -> Self {
+        use crate::schema::subsystems::dsl::*;
+
+        let subsystems_vec = subsystems
+                            .load::<Subsystem>(connection)
+                            .expect("Could not load subsystems table!");
+
+        Self {
+            subsystems: subsystems_vec.into_iter().map(|s| (s.name.to_string(), s)).collect()
+        }
+    }
+
+    /// Get subsystems from a list of subsystem_name Strings
+    pub fn get_subsystems_from_names(connection: &mut SqliteConnection, subsystem_names: Vec<String>) -> Self {
+        use crate::schema::subsystems::dsl::*;
+
+        let subsystems_vec = subsystems
+                            .filter(name.eq_any(subsystem_names))
+                            .load::<Subsystem>(connection)
+                            .expect("Could not load subsystems table!");
+
+        Self {
+            subsystems: subsystems_vec.into_iter().map(|s| (s.name.to_string(), s)).collect()
+        }
+    }
+
+    /// Get list of Names
+    pub fn get_names(&self) -> Vec<String> {
+        self.subsystems.keys().map(|n| n.to_string()).collect()
+    }
+}
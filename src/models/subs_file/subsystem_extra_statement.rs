@@ -0,0 +1,141 @@
+use diesel::prelude::*;
+use crate::schema::{subsystem_extra_statements};
+use super::subsystem::Subsystem;
+use super::SUBS_FILE_CALLS;
+use crate::models::lua::{ Call, Chunk };
+use crate::models::data_file::{ anchored_extras, without_watermark };
+use std::fmt;
+use std::collections::HashMap;
+
+///
+/// Subsystem Extra Statement
+///
+/// Any statement in a *.subs file that isn't one of the `SUBS_FILE_CALLS` (`NewSubSystemType` properties, comments,
+/// ...). Stored verbatim with the `anchor` of the modelled call it follows (see `data_file::anchor`), so it can be
+/// written back where it was found; `position` orders the statements. The `StartSubSystemConfig()` line and the lines
+/// `Subsystem` is read from are kept here too, and given the `Subsystem`'s values when written back (see
+/// `data_file::fill_in_properties`).
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Subsystem, foreign_key = subsystem_name))]
+#[diesel(table_name = subsystem_extra_statements)]
+pub struct SubsystemExtraStatement {
+    pub id: i32,
+    pub subsystem_name: String,
+    pub position: i32,
+    pub statement: String,
+    pub anchor: Option<String>
+}
+
+impl fmt::Display for SubsystemExtraStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.statement)
+    }
+}
+
+/// Array of `SubsystemExtraStatement`, ordered by `position`.
+#[derive(Debug)]
+pub struct SubsystemExtraStatementCollection {
+    pub subsystem_extra_statements: Vec<SubsystemExtraStatement>
+}
+
+impl Default for SubsystemExtraStatementCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SubsystemExtraStatementCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            subsystem_extra_statements: Vec::new()
+        }
+    }
+
+    /// Get extra statements for a specific subsystem name.
+    pub fn get_for_subsystem(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::subsystem_extra_statements::dsl::*;
+
+        let vec = subsystem_extra_statements
+                    .filter(subsystem_name.eq(name))
+                    .order(position.asc())
+                    .load::<SubsystemExtraStatement>(connection)
+                    .expect("Error loading subsystem extra statements!");
+
+        Self {
+            subsystem_extra_statements: vec
+        }
+    }
+
+    /// Get extra statements for a list of subsystem names.
+    pub fn get_for_subsystems(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::subsystem_extra_statements::dsl::*;
+
+        let vec = subsystem_extra_statements
+                    .filter(subsystem_name.eq_any(names))
+                    .order(position.asc())
+                    .load::<SubsystemExtraStatement>(connection)
+                    .expect("Error loading subsystem extra statements!");
+
+        Self {
+            subsystem_extra_statements: vec
+        }
+    }
+
+    /// Consume the collection, divide into HashMap by `subsystem_name`. Keeps the `position` order.
+    pub fn key_by_subsystem_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for ss in self.subsystem_extra_statements {
+            map
+                .entry(ss.subsystem_name.to_string())
+                .or_default()
+                .subsystem_extra_statements.push(ss);
+        }
+
+        map
+    }
+}
+
+///
+/// NewSubsystemExtraStatement
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = subsystem_extra_statements)]
+pub struct NewSubsystemExtraStatement {
+    pub subsystem_name: String,
+    pub position: i32,
+    pub statement: String,
+    pub anchor: Option<String>
+}
+
+/// Array of `NewSubsystemExtraStatement` with helper methods.
+#[derive(Debug)]
+pub struct NewSubsystemExtraStatementCollection {
+    pub subsystem_extra_statements: Vec<NewSubsystemExtraStatement>
+}
+
+impl NewSubsystemExtraStatementCollection {
+    /// Collect every statement in `chunk` but the calls in `SUBS_FILE_CALLS`, each anchored to the call before it.
+    /// The Scaffold watermark is skipped so it doesn't pile up on every round-trip.
+    pub fn from_chunk(subsystem_name: &str, chunk: &Chunk, source: &str) -> Self {
+        let modelled = |call: &Call| SUBS_FILE_CALLS.contains(&call.name.as_str());
+
+        let subsystem_extra_statements = anchored_extras(without_watermark(chunk), source, modelled)
+            .into_iter()
+            .enumerate()
+            .map(|(position, (anchor, statement))| NewSubsystemExtraStatement {
+                subsystem_name: subsystem_name.to_string(),
+                position: position as i32,
+                statement,
+                anchor
+            })
+            .collect();
+
+        Self {
+            subsystem_extra_statements
+        }
+    }
+}
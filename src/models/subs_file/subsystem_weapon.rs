@@ -0,0 +1,211 @@
+use diesel::prelude::*;
+use crate::schema::{subsystem_weapons};
+use super::subsystem::Subsystem;
+use crate::models::wepn_file::weapon::{ Weapon, WeaponCollection };
+use crate::models::ship_file::ship_weapon::arguments_from;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
+use std::fmt;
+use std::collections::{ HashMap, HashSet };
+
+///
+/// Subsystem Weapon
+///
+/// A `StartSubSystemWeaponConfig(...)` binding: which weapon the subsystem (usually a turret) mounts, and on which
+/// hardpoint. Any arguments after the hardpoint are kept verbatim in `extra_arguments`. `weapon_name` is a foreign
+/// key to `weapons.name`, spelled the way the weapon is on import.
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Subsystem, foreign_key = subsystem_name))]
+#[diesel(belongs_to(Weapon, foreign_key = weapon_name))]
+#[diesel(table_name = subsystem_weapons)]
+pub struct SubsystemWeapon {
+    pub id: i32,
+    pub subsystem_name: String,
+    pub weapon_name: String,
+    pub hardpoint: String,
+    pub extra_arguments: String
+}
+
+impl fmt::Display for SubsystemWeapon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StartSubSystemWeaponConfig(NewSubSystemType, \"{}\", \"{}\"", self.weapon_name, self.hardpoint)?;
+
+        if !self.extra_arguments.is_empty() {
+            write!(f, ", {}", self.extra_arguments)?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// Array of `SubsystemWeapon`, in the order they were imported.
+#[derive(Debug)]
+pub struct SubsystemWeaponCollection {
+    pub subsystem_weapons: Vec<SubsystemWeapon>
+}
+
+impl Default for SubsystemWeaponCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SubsystemWeaponCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            subsystem_weapons: Vec::new()
+        }
+    }
+
+    /// Get weapon bindings for a specific subsystem name.
+    pub fn get_for_subsystem(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::subsystem_weapons::dsl::*;
+
+        let vec = subsystem_weapons
+                    .filter(subsystem_name.eq(name))
+                    .order(id.asc())
+                    .load::<SubsystemWeapon>(connection)
+                    .expect("Error loading subsystem weapons!");
+
+        Self {
+            subsystem_weapons: vec
+        }
+    }
+
+    /// Get weapon bindings for a list of subsystem names.
+    pub fn get_for_subsystems(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::subsystem_weapons::dsl::*;
+
+        let vec = subsystem_weapons
+                    .filter(subsystem_name.eq_any(names))
+                    .order(id.asc())
+                    .load::<SubsystemWeapon>(connection)
+                    .expect("Error loading subsystem weapons!");
+
+        Self {
+            subsystem_weapons: vec
+        }
+    }
+
+    /// Get the weapon bindings that use `weapon`, compared without regard to case.
+    pub fn get_for_weapon(connection: &mut SqliteConnection, weapon: &str) -> Self {
+        use crate::schema::subsystem_weapons::dsl::*;
+
+        let vec = subsystem_weapons
+                    .order(id.asc())
+                    .load::<SubsystemWeapon>(connection)
+                    .expect("Error loading subsystem weapons!");
+
+        Self {
+            subsystem_weapons: vec.into_iter().filter(|sw| sw.weapon_name.eq_ignore_ascii_case(weapon)).collect()
+        }
+    }
+
+    /// Get the weapon bindings whose weapon isn't in the `weapons` table, compared without regard to case.
+    pub fn get_dangling(connection: &mut SqliteConnection) -> Self {
+        use crate::schema::subsystem_weapons::dsl::*;
+
+        let names: HashSet<String> = WeaponCollection::get_all_weapons(connection)
+            .get_names()
+            .iter()
+            .map(|n| n.to_lowercase())
+            .collect();
+
+        let vec = subsystem_weapons
+                    .order((subsystem_name.asc(), id.asc()))
+                    .load::<SubsystemWeapon>(connection)
+                    .expect("Error loading subsystem weapons!");
+
+        Self {
+            subsystem_weapons: vec.into_iter().filter(|sw| !names.contains(&sw.weapon_name.to_lowercase())).collect()
+        }
+    }
+
+    /// Spell each weapon name the way the weapon it refers to is spelled, where the two only differ in case. See
+    /// `ShipWeaponCollection::match_weapon_names`.
+    pub fn match_weapon_names(connection: &mut SqliteConnection) -> QueryResult<()> {
+        use crate::schema::subsystem_weapons::dsl::*;
+
+        let names: HashMap<String, String> = WeaponCollection::get_all_weapons(connection)
+            .get_names()
+            .into_iter()
+            .map(|n| (n.to_lowercase(), n))
+            .collect();
+
+        for sw in subsystem_weapons.load::<SubsystemWeapon>(connection)? {
+            match names.get(&sw.weapon_name.to_lowercase()) {
+                Some(name) if *name != sw.weapon_name => {
+                    diesel::update(subsystem_weapons.find(sw.id)).set(weapon_name.eq(name)).execute(connection)?;
+                },
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the distinct subsystem names in the collection, sorted.
+    pub fn get_subsystem_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.subsystem_weapons.iter().map(|sw| sw.subsystem_name.to_string()).collect();
+
+        names.sort();
+        names.dedup();
+
+        names
+    }
+
+    /// Consume the collection, divide into HashMap by `subsystem_name`. Keeps the original order.
+    pub fn key_by_subsystem_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for sw in self.subsystem_weapons {
+            map
+                .entry(sw.subsystem_name.to_string())
+                .or_default()
+                .subsystem_weapons.push(sw);
+        }
+
+        map
+    }
+}
+
+///
+/// NewSubsystemWeapon
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = subsystem_weapons)]
+pub struct NewSubsystemWeapon {
+    pub subsystem_name: String,
+    pub weapon_name: String,
+    pub hardpoint: String,
+    pub extra_arguments: String
+}
+
+/// Array of `NewSubsystemWeapon` with helper methods.
+#[derive(Debug)]
+pub struct NewSubsystemWeaponCollection {
+    pub subsystem_weapons: Vec<NewSubsystemWeapon>
+}
+
+impl NewSubsystemWeaponCollection {
+    /// Build from every parsed `StartSubSystemWeaponConfig(...)` lua call in `chunk`.
+    pub fn from_chunk(subsystem_name: &str, chunk: &Chunk, source: &str) -> Result<Self, ParseError> {
+        let mut subsystem_weapons = Vec::new();
+
+        for call in chunk.calls_named("StartSubSystemWeaponConfig") {
+            subsystem_weapons.push(NewSubsystemWeapon {
+                subsystem_name: subsystem_name.to_string(),
+                weapon_name: call.string_arg(1)?,
+                hardpoint: call.string_arg(2)?,
+                extra_arguments: arguments_from(call, 3, source)
+            });
+        }
+
+        Ok(Self {
+            subsystem_weapons
+        })
+    }
+}
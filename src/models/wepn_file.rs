@@ -1,3 +1,4 @@
+pub mod import_error;
 pub mod weapon;
 pub mod weapon_result;
 pub mod weapon_penetration;
@@ -5,12 +6,20 @@ pub mod weapon_accuracy;
 pub mod weapon_angles;
 pub mod weapon_misc;
 pub mod weapon_turret_sound;
-
-use std::path::Path;
+pub mod weapon_extra_statement;
+pub mod weapon_filter;
+pub mod weapon_source;
+pub mod weapon_diff;
+pub mod spawn_graph;
+pub mod weapon_lint;
+
+use std::path::{ Path, PathBuf };
+use std::io;
 use std::io::prelude::*;
 use std::fs;
 use std::fmt;
 use std::collections::HashMap;
+use std::time::{ SystemTime, UNIX_EPOCH };
 
 use diesel::prelude::*;
 
@@ -20,7 +29,13 @@ use weapon_accuracy::{ WeaponAccuracyCollection, NewWeaponAccuracy, NewWeaponAcc
 use weapon_angles::{ WeaponAngles, NewWeaponAngles, WeaponAnglesCollection };
 use weapon_misc::{ WeaponMisc, NewWeaponMisc, WeaponMiscCollection };
 use weapon_turret_sound::{ WeaponTurretSound, NewWeaponTurretSound, WeaponTurretSoundCollection };
+use weapon_extra_statement::{ WeaponExtraStatementCollection, NewWeaponExtraStatement, NewWeaponExtraStatementCollection };
+use weapon_source::{ WeaponSourceCollection, NewWeaponSourceCollection };
 use weapon::{ Weapon, WeaponCollection };
+use super::lua;
+use super::parse_error::ParseError;
+use import_error::ImportError;
+use super::data_file::{ DataFile, NewDataFile, NewDataFileCollection, anchor, lay_out };
 
 /// Add Watermark to the top of the file.
 pub const WEAPON_FILE_HEADER: &str = r#"-- Created with Scaffold
@@ -28,6 +43,111 @@ pub const WEAPON_FILE_HEADER: &str = r#"-- Created with Scaffold
 -- Pull Requests and Issues welcome!
 "#;
 
+/// The lua calls Scaffold models. Anything else is kept as a `WeaponExtraStatement`.
+pub const WEAPON_FILE_CALLS: [&str; 7] = [
+    "StartWeaponConfig",
+    "AddWeaponResult",
+    "setPenetration",
+    "setAccuracy",
+    "setAngles",
+    "setMiscValues",
+    "addAnimTurretSound"
+];
+
+/// Options for `DataFile::write_changes_to_kind_dir`.
+#[derive(Debug, Default)]
+pub struct WriteOptions {
+    /// Treat a file as unchanged if it only differs by the `WEAPON_FILE_HEADER` watermark.
+    pub ignore_header: bool,
+
+    /// Copy files here before they're overwritten.
+    pub backup_dir: Option<PathBuf>
+}
+
+/// What `DataFile::write_changes_to_kind_dir` did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteOutcome {
+    Created,
+    Updated,
+    Unchanged
+}
+
+/// `contents` without the `WEAPON_FILE_HEADER` watermark, whichever line endings it was written with.
+fn without_header(contents: &str) -> &str {
+    let mut rest = contents;
+
+    for line in WEAPON_FILE_HEADER.lines() {
+        match rest.strip_prefix(line) {
+            Some(r) => rest = r.strip_prefix("\r\n").or_else(|| r.strip_prefix('\n')).unwrap_or(r),
+            None => break
+        }
+    }
+
+    rest
+}
+
+/// Does `contents` match `rendered`? With `ignore_header`, the watermark is left out of the comparison.
+pub fn contents_match(rendered: &str, contents: &str, ignore_header: bool) -> bool {
+    if ignore_header {
+        without_header(rendered) == without_header(contents)
+    } else {
+        rendered == contents
+    }
+}
+
+/// Write `contents` to `<path>.tmp` first and then rename it over `path`, so a failed write never leaves a truncated
+/// file behind.
+pub fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let res = fs::File::create(&tmp_path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&tmp_path, path));
+
+    if res.is_err() {
+        fs::remove_file(&tmp_path).ok();
+    }
+
+    res
+}
+
+/// Copy a data file (e.g. *.wepn) to `<backup_dir>/<name>/<name>.<unix time in ms>.<extension>.bak`. The `.bak`
+/// extension keeps backups from being picked up as data files if `backup_dir` is inside "data". An existing backup is
+/// never overwritten; if there's already one from the same millisecond, `_1`, `_2` and so on is added to the time.
+pub fn backup_file(path: &Path, backup_dir: &Path) -> io::Result<PathBuf> {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+
+    let dir = backup_dir.join(name.as_ref());
+
+    fs::create_dir_all(&dir)?;
+
+    let mut previous = fs::File::open(path)?;
+    let mut attempt = 0;
+
+    loop {
+        let stamp = if attempt == 0 { timestamp.to_string() } else { format!("{}_{}", timestamp, attempt) };
+        let backup = dir.join(format!("{}.{}.{}.bak", name, stamp, extension));
+
+        match fs::OpenOptions::new().write(true).create_new(true).open(&backup) {
+            Ok(mut file) => {
+                io::copy(&mut previous, &mut file)?;
+
+                return Ok(backup);
+            },
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(e)
+        }
+    }
+}
+
 ///
 /// Weapon File (*.wepn)
 ///
@@ -39,33 +159,40 @@ pub struct WeaponFile {
     pub weapon_accuracy: WeaponAccuracyCollection,
     pub weapon_angles: Option<WeaponAngles>,
     pub weapon_misc: Option<WeaponMisc>,
-    pub weapon_turret_sound: Option<WeaponTurretSound>
+    pub weapon_turret_sound: Option<WeaponTurretSound>,
+    pub weapon_extra_statements: WeaponExtraStatementCollection
 }
 
 impl fmt::Display for WeaponFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut wepn_file: Vec<String> = Vec::new();
+        // Each modelled call is paired with its anchor and the section it belongs to; see `lay_out`.
+        let mut modelled: Vec<(String, &str, String)> = vec![(anchor("StartWeaponConfig", 0), "weapon", self.weapon.to_string())];
+
+        for (index, result) in self.weapon_results.weapon_results.iter().enumerate() {
+            modelled.push((anchor("AddWeaponResult", index), "results", result.to_string()));
+        }
 
-        wepn_file.push(self.weapon.to_string());
-        wepn_file.push(self.weapon_results.to_string());
-        wepn_file.push(self.weapon_penetration.to_string());
-        wepn_file.push(self.weapon_accuracy.to_string());
+        modelled.push((anchor("setPenetration", 0), "penetration", self.weapon_penetration.to_string()));
+        modelled.push((anchor("setAccuracy", 0), "accuracy", self.weapon_accuracy.to_string()));
 
         if let Some(angles) = &self.weapon_angles {
-            wepn_file.push(angles.to_string());
+            modelled.push((anchor("setAngles", 0), "angles", angles.to_string()));
         }
 
         if let Some(misc) = &self.weapon_misc {
-            wepn_file.push(misc.to_string());
+            modelled.push((anchor("setMiscValues", 0), "misc", misc.to_string()));
         }
 
         if let Some(sound) = &self.weapon_turret_sound {
-            wepn_file.push(sound.to_string());
+            modelled.push((anchor("addAnimTurretSound", 0), "sound", sound.to_string()));
         }
 
-        let joined = wepn_file.join("\r\n\r\n");
+        let extras: Vec<(Option<&str>, String)> = self.weapon_extra_statements.weapon_extra_statements
+            .iter()
+            .map(|extra| (extra.anchor.as_deref(), extra.to_string()))
+            .collect();
 
-        write!(f, "{}{}", WEAPON_FILE_HEADER, joined)
+        write!(f, "{}{}", WEAPON_FILE_HEADER, lay_out(&modelled, &extras, Some("extra")))
     }
 }
 
@@ -81,6 +208,7 @@ impl WeaponFile {
             let weapon_angles = WeaponAngles::get_for_weapon(connection, weapon_name);
             let weapon_misc = WeaponMisc::get_for_weapon(connection, weapon_name);
             let weapon_turret_sound = WeaponTurretSound::get_for_weapon(connection, weapon_name);
+            let weapon_extra_statements = WeaponExtraStatementCollection::get_for_weapon(connection, weapon_name);
 
             // Copy default values from `Weapon` model into collections for ease of exporting.
             weapon_accuracy.use_default_accuracy(&weapon);
@@ -94,56 +222,34 @@ impl WeaponFile {
                 weapon_accuracy,
                 weapon_angles,
                 weapon_misc,
-                weapon_turret_sound
+                weapon_turret_sound,
+                weapon_extra_statements
             })
         } else {
             None
         }
     }
 
-    /// Write WeaponFile to disk.
-    pub fn write_to_disk(&self, path: &Path) -> Result<(), &str> {
-        if !path.is_dir() {
-            if let Ok(mut file) = fs::File::create(path) {
-                let res = file.write_all(self.to_string().as_bytes());
+    /// Every keyed value in the weapon file, i.e. all but the results and extra statements, keyed by a path such as
+    /// `fire_range` or `penetration.Fighter`, in the order they're written out.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = self.weapon.fields();
 
-                if res.is_err() {
-                    Err("Could not write to file!")
-                } else {
-                    Ok(())
-                }
-            } else {
-                Err("Could not open file!")
-            }
-        } else {
-            Err("Cannot write *.wepn file to a directory!")
-        }
-    }
+        fields.extend(self.weapon_penetration.fields());
+        fields.extend(self.weapon_accuracy.fields());
+        fields.extend(self.weapon_angles.iter().flat_map(|wa| wa.fields()));
+        fields.extend(self.weapon_misc.iter().flat_map(|wm| wm.fields()));
+        fields.extend(self.weapon_turret_sound.iter().flat_map(|wt| wt.fields()));
 
-    /// Write WeaponFile out to a directory.
-    pub fn write_to_dir(&self, dir: &Path) -> Result<(), &str> {
-        if dir.is_dir() {
-            let wepn_file = dir.join(format!("{}.wepn", self.weapon.name));
-
-            self.write_to_disk(wepn_file.as_path())
-        } else {
-            Err("Could write weapon file to directory!")
-        }
+        fields
     }
+}
 
-    /// Write out the weapon file to the "weapon" directory. This creates the containment directory for the weapon as well.
-    pub fn write_to_weapon_dir(&self, weapon_dir: &Path) -> Result<(), &str> {
-        if weapon_dir.is_dir() {
-            let containment_dir = weapon_dir.join(self.weapon.name.as_str());
+impl DataFile for WeaponFile {
+    const EXTENSION: &'static str = "wepn";
 
-            if !containment_dir.exists() {
-                fs::create_dir(&containment_dir).expect("Could not create weapon containment directory!");
-            }
-
-            self.write_to_dir(containment_dir.as_path())
-        } else {
-            Err("Path is not a directory!")
-        }
+    fn name(&self) -> &str {
+        &self.weapon.name
     }
 }
 
@@ -163,6 +269,7 @@ impl WeaponFileCollection {
         let mut all_weapon_angles = WeaponAnglesCollection::get_for_weapons(connection, &weapon_names);
         let mut all_weapon_misc = WeaponMiscCollection::get_for_weapons(connection, &weapon_names);
         let mut all_weapon_turret_sounds = WeaponTurretSoundCollection::get_for_weapons(connection, &weapon_names);
+        let mut all_weapon_extra_statements = WeaponExtraStatementCollection::get_for_weapons(connection, &weapon_names).key_by_weapon_name();
 
         for (name, w) in weapons.weapons.drain() {
             let mut weapon_penetration = all_weapon_penetrations.remove(&name).unwrap_or(WeaponPenetrationCollection::new());
@@ -171,6 +278,7 @@ impl WeaponFileCollection {
             let weapon_angles = all_weapon_angles.weapon_angles.remove(&name);
             let weapon_misc = all_weapon_misc.weapon_misc.remove(&name);
             let weapon_turret_sound = all_weapon_turret_sounds.weapon_turret_sounds.remove(&name);
+            let weapon_extra_statements = all_weapon_extra_statements.remove(&name).unwrap_or_default();
 
             // Copy default values from `Weapon` model into collections for ease of exporting.
             weapon_accuracy.use_default_accuracy(&w);
@@ -184,7 +292,8 @@ impl WeaponFileCollection {
                 weapon_accuracy,
                 weapon_angles,
                 weapon_misc,
-                weapon_turret_sound
+                weapon_turret_sound,
+                weapon_extra_statements
             });
         }
 
@@ -200,11 +309,33 @@ impl WeaponFileCollection {
         Self::from_weapon_collection(connection, weapons)
     }
 
-    /// Get weapon files from a list of weapon_name Strings
+    /// Get weapon files from a list of weapon_name Strings. Loaded `SQLITE_MAX_VARIABLES` names at a time.
     pub fn get_weapon_files_from_names(connection: &mut SqliteConnection, weapon_names: Vec<String>) -> Self {
-        let weapons = WeaponCollection::get_weapons_from_names(connection, weapon_names);
+        let mut weapon_files = HashMap::new();
 
-        Self::from_weapon_collection(connection, weapons)
+        for chunk in weapon_names.chunks(SQLITE_MAX_VARIABLES) {
+            let weapons = WeaponCollection::get_weapons_from_names(connection, chunk.to_vec());
+
+            weapon_files.extend(Self::from_weapon_collection(connection, weapons).weapon_files);
+        }
+
+        Self {
+            weapon_files
+        }
+    }
+
+    /// Drop the weapon files that render exactly as they do in `reference` (rendered files keyed by weapon name).
+    /// Weapons missing from `reference` are kept.
+    pub fn retain_differing(&mut self, reference: &HashMap<String, String>) {
+        self.weapon_files.retain(|name, wepn| reference.get(name) != Some(&wepn.to_string()));
+    }
+
+    /// Render every weapon file, keyed by weapon name.
+    pub fn rendered(&self) -> HashMap<String, String> {
+        self.weapon_files
+            .iter()
+            .map(|(name, wepn)| (name.to_string(), wepn.to_string()))
+            .collect()
     }
 }
 
@@ -219,28 +350,25 @@ pub struct NewWeaponFile {
     pub weapon_accuracy: NewWeaponAccuracyCollection,
     pub weapon_angles: Option<NewWeaponAngles>,
     pub weapon_misc: Option<NewWeaponMisc>,
-    pub weapon_turret_sound: Option<NewWeaponTurretSound>
+    pub weapon_turret_sound: Option<NewWeaponTurretSound>,
+    pub weapon_extra_statements: NewWeaponExtraStatementCollection
 }
 
-impl NewWeaponFile {
-    /// Create new `NewWeaponFile` from `Path`
-    pub fn from_path(wepn_file: &Path) -> Self {
-        let weapon_name = wepn_file.file_stem()
-                                .unwrap()
-                                .to_str()
-                                .unwrap()
-                                .to_string();
-
-        let contents = fs::read_to_string(&wepn_file)
-                                .expect("Should have been able to read the file");
-
-        let weapon = Weapon::from_string(&weapon_name, &contents);
-        let weapon_results = NewWeaponResultCollection::from_string(&weapon_name, &contents);
-        let weapon_penetration = NewWeaponPenetrationCollection::from_string(&weapon_name, &contents);
-        let weapon_accuracy = NewWeaponAccuracyCollection::from_string(&weapon_name, &contents);
-        let weapon_angles = NewWeaponAngles::from_string(&weapon_name, &contents);
-        let weapon_misc = NewWeaponMisc::from_string(&weapon_name, &contents);
-        let weapon_turret_sound = NewWeaponTurretSound::from_string(&weapon_name, &contents);
+impl NewDataFile for NewWeaponFile {
+    const EXTENSION: &'static str = "wepn";
+
+    /// Create new `NewWeaponFile` from the body of a *.wepn file.
+    fn from_string(weapon_name: &str, contents: &str) -> Result<Self, ParseError> {
+        let chunk = lua::parse(contents)?;
+
+        let weapon = Weapon::from_chunk(weapon_name, &chunk)?;
+        let weapon_results = NewWeaponResultCollection::from_chunk(weapon_name, &chunk)?;
+        let weapon_penetration = NewWeaponPenetrationCollection::from_chunk(weapon_name, &chunk)?;
+        let weapon_accuracy = NewWeaponAccuracyCollection::from_chunk(weapon_name, &chunk)?;
+        let weapon_angles = NewWeaponAngles::from_chunk(weapon_name, &chunk)?;
+        let weapon_misc = NewWeaponMisc::from_chunk(weapon_name, &chunk)?;
+        let weapon_turret_sound = NewWeaponTurretSound::from_chunk(weapon_name, &chunk)?;
+        let weapon_extra_statements = NewWeaponExtraStatementCollection::from_chunk(weapon_name, &chunk, contents);
 
         let mut new_weapon_file = Self {
             weapon,
@@ -249,15 +377,18 @@ impl NewWeaponFile {
             weapon_accuracy,
             weapon_angles,
             weapon_misc,
-            weapon_turret_sound
+            weapon_turret_sound,
+            weapon_extra_statements
         };
 
         new_weapon_file.set_weapon_penetration();
         new_weapon_file.set_weapon_accuracy();
 
-        new_weapon_file
+        Ok(new_weapon_file)
     }
+}
 
+impl NewWeaponFile {
     /// Copies the Weapon Penetration from `weapon_penetration` to `weapon` for saving.
     pub fn set_weapon_penetration(&mut self) {
         self.weapon.field_penetration = self.weapon_penetration.field_penetration;
@@ -270,28 +401,178 @@ impl NewWeaponFile {
     }
 }
 
+/// SQLite's default limit on bound parameters per statement, before version 3.32. Used to size insert batches.
+pub const SQLITE_MAX_VARIABLES: usize = 999;
+
+/// Options for `NewDataFileCollection::insert`.
+#[derive(Debug, Default)]
+pub struct ImportOptions {
+    /// Delete every weapon in the database before inserting, rather than merging by weapon name.
+    pub replace: bool,
+
+    /// Rows per insert statement. Defaults to, and is capped at, as many as fit in `SQLITE_MAX_VARIABLES`.
+    pub chunk_size: Option<usize>,
+
+    /// Don't print progress while inserting.
+    pub quiet: bool,
+
+    /// Where the weapons came from, e.g. the 'data/' directory. When set, each weapon is recorded in
+    /// `weapon_sources` under this source's layer.
+    pub source: Option<String>
+}
+
+impl ImportOptions {
+    /// Rows per statement for a table with `columns` columns: `chunk_size`, but never more than fit in
+    /// `SQLITE_MAX_VARIABLES`, and at least one.
+    pub fn rows_per_insert(&self, columns: usize) -> usize {
+        self.chunk_size.unwrap_or(SQLITE_MAX_VARIABLES).clamp(1, SQLITE_MAX_VARIABLES / columns)
+    }
+}
+
+/// What an import did to the database, by weapon name.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// The layer the weapons were recorded under, if the import had a `source`.
+    pub layer: Option<i32>,
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub removed: Vec<String>
+}
+
+impl ImportReport {
+    /// Did the import touch anything at all?
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.unchanged.is_empty() && self.removed.is_empty()
+    }
+
+    /// Compare the rendered weapon files from before and after an import.
+    pub fn compare(before: &HashMap<String, String>, after: &HashMap<String, String>, mut removed: Vec<String>) -> Self {
+        let mut report = Self::default();
+
+        for (name, rendered) in after.iter() {
+            match before.get(name) {
+                None => report.added.push(name.to_string()),
+                Some(previous) if previous != rendered => report.changed.push(name.to_string()),
+                Some(_) => report.unchanged.push(name.to_string())
+            }
+        }
+
+        report.added.sort();
+        report.changed.sort();
+        report.unchanged.sort();
+        removed.sort();
+        report.removed = removed;
+
+        report
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in self.added.iter() {
+            writeln!(f, "added... {}", name)?;
+        }
+
+        for name in self.changed.iter() {
+            writeln!(f, "changed... {}", name)?;
+        }
+
+        for name in self.removed.iter() {
+            writeln!(f, "removed... {}", name)?;
+        }
+
+        write!(f, "{} added, {} changed, {} unchanged, {} removed.",
+            self.added.len(),
+            self.changed.len(),
+            self.unchanged.len(),
+            self.removed.len()
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct NewWeaponFileCollection {
     pub new_weapon_files: Vec<NewWeaponFile>
 }
 
-impl NewWeaponFileCollection {
+impl NewDataFileCollection for NewWeaponFileCollection {
+    type File = NewWeaponFile;
+
     /// Create from a Vector of NewWeaponFile
-    pub fn from_vec(new_weapon_files: Vec<NewWeaponFile>) -> Self {
+    fn from_vec(new_weapon_files: Vec<NewWeaponFile>) -> Self {
         Self {
             new_weapon_files
         }
     }
 
-    /// Consumes the collection, inserts into database
-    pub fn insert(self, connection: &mut SqliteConnection) {
-        use crate::schema::weapons::dsl::*;
-        use crate::schema::weapon_results::dsl::*;
-        use crate::schema::weapon_penetrations::dsl::*;
-        use crate::schema::weapon_accuracy::dsl::*;
-        use crate::schema::weapon_angles::dsl::*;
-        use crate::schema::weapon_misc::dsl::*;
-        use crate::schema::weapon_turret_sound::dsl::*;
+    /// Get list of weapon names in the collection.
+    fn get_names(&self) -> Vec<String> {
+        self.new_weapon_files.iter().map(|wf| wf.weapon.name.to_string()).collect()
+    }
+
+    fn get_all_names(connection: &mut SqliteConnection) -> Vec<String> {
+        WeaponCollection::get_all_weapons(connection).get_names()
+    }
+
+    fn render(connection: &mut SqliteConnection, names: &[String]) -> HashMap<String, String> {
+        WeaponFileCollection::get_weapon_files_from_names(connection, names.to_vec()).rendered()
+    }
+
+    fn delete_named(connection: &mut SqliteConnection, names: &[String]) -> Result<(), ImportError> {
+        use crate::schema::{ weapons, weapon_results, weapon_penetrations, weapon_accuracy, weapon_angles, weapon_misc, weapon_turret_sound, weapon_extra_statements };
+
+        delete_rows!(connection, names,
+            weapon_extra_statements.weapon_name,
+            weapon_turret_sound.weapon_name,
+            weapon_misc.weapon_name,
+            weapon_accuracy.weapon_name,
+            weapon_penetrations.weapon_name,
+            weapon_results.weapon_name,
+            weapon_angles.weapon_name,
+            weapons.name
+        );
+
+        Ok(())
+    }
+
+    /// Delete every weapon, and every recorded source, from the database.
+    fn delete_all(connection: &mut SqliteConnection) -> Result<(), ImportError> {
+        use crate::schema::{ weapons, weapon_results, weapon_penetrations, weapon_accuracy, weapon_angles, weapon_misc, weapon_turret_sound, weapon_extra_statements, weapon_sources };
+
+        delete_rows!(connection,
+            weapon_sources,
+            weapon_extra_statements,
+            weapon_turret_sound,
+            weapon_misc,
+            weapon_accuracy,
+            weapon_penetrations,
+            weapon_results,
+            weapon_angles,
+            weapons
+        );
+
+        Ok(())
+    }
+
+    /// With `options.source`, record the imported weapons in `weapon_sources`.
+    fn after_insert(connection: &mut SqliteConnection, options: &ImportOptions, after: &HashMap<String, String>, report: &mut ImportReport) -> Result<(), ImportError> {
+        if let Some(source) = &options.source {
+            let layer = WeaponSourceCollection::layer_for_source(connection, source)
+                .map_err(|e| ImportError::new("weapon_sources", None, e))?;
+
+            NewWeaponSourceCollection::from_rendered(layer, source, after)
+                .insert(connection)
+                .map_err(|e| ImportError::new("weapon_sources", None, e))?;
+
+            report.layer = Some(layer);
+        }
+
+        Ok(())
+    }
+
+    fn insert_rows(&self, connection: &mut SqliteConnection, options: &ImportOptions) -> Result<(), ImportError> {
+        use crate::schema::{ weapons, weapon_results, weapon_penetrations, weapon_accuracy, weapon_angles, weapon_misc, weapon_turret_sound, weapon_extra_statements };
 
         let mut weapons_to_insert: Vec<&Weapon> = Vec::new();
         let mut weapon_results_to_insert: Vec<&NewWeaponResult> = Vec::new();
@@ -300,6 +581,7 @@ impl NewWeaponFileCollection {
         let mut weapon_angles_to_insert: Vec<&NewWeaponAngles> = Vec::new();
         let mut weapon_misc_to_insert: Vec<&NewWeaponMisc> = Vec::new();
         let mut weapon_turret_sounds_to_insert: Vec<&NewWeaponTurretSound> = Vec::new();
+        let mut weapon_extra_statements_to_insert: Vec<&NewWeaponExtraStatement> = Vec::new();
 
         for weapon_file in self.new_weapon_files.iter() {
             weapons_to_insert.push(&weapon_file.weapon);
@@ -319,71 +601,113 @@ impl NewWeaponFileCollection {
             weapon_file.weapon_results.weapon_results.iter().for_each(|x| weapon_results_to_insert.push(x));
             weapon_file.weapon_penetration.weapon_penetrations.iter().for_each(|x| weapon_penetrations_to_insert.push(x));
             weapon_file.weapon_accuracy.weapon_accuracies.iter().for_each(|x| weapon_accuracies_to_insert.push(x));
+            weapon_file.weapon_extra_statements.weapon_extra_statements.iter().for_each(|x| weapon_extra_statements_to_insert.push(x));
         }
 
-        // Delete all weapons first.
-        diesel::delete(weapon_turret_sound)
-            .execute(connection)
-            .expect("Error clearing weapon_turret_sound!");
-
-        diesel::delete(weapon_misc)
-            .execute(connection)
-            .expect("Error clearing weapon_misc!");
-
-        diesel::delete(weapon_accuracy)
-            .execute(connection)
-            .expect("Error clearing weapon_accuracy!");
-
-        diesel::delete(weapon_penetrations)
-            .execute(connection)
-            .expect("Error clearing weapon_penetrations!");
-
-        diesel::delete(weapon_results)
-            .execute(connection)
-            .expect("Error clearing weapon_results!");
-
-        diesel::delete(weapon_angles)
-            .execute(connection)
-            .expect("Error clearing weapon_angles!");
-
-        diesel::delete(weapons)
-            .execute(connection)
-            .expect("Error clearing weapons!");
-
-        // Insert our  weapons
-        diesel::insert_into(weapons)
-            .values(weapons_to_insert)
-            .execute(connection)
-            .expect("Could not insert weapons!");
-
-        diesel::insert_into(weapon_results)
-            .values(weapon_results_to_insert)
-            .execute(connection)
-            .expect("Could not insert weapon_results!");
-
-        diesel::insert_into(weapon_penetrations)
-            .values(weapon_penetrations_to_insert)
-            .execute(connection)
-            .expect("Could not insert weapon_penetrations!");
-
-        diesel::insert_into(weapon_accuracy)
-            .values(weapon_accuracies_to_insert)
-            .execute(connection)
-            .expect("Could not insert weapon_accuracies!");
-
-        diesel::insert_into(weapon_angles)
-            .values(weapon_angles_to_insert)
-            .execute(connection)
-            .expect("Could not insert weapon_angles!");
-
-        diesel::insert_into(weapon_misc)
-            .values(weapon_misc_to_insert)
-            .execute(connection)
-            .expect("Could not insert weapon_misc!");
-
-        diesel::insert_into(weapon_turret_sound)
-            .values(weapon_turret_sounds_to_insert)
-            .execute(connection)
-            .expect("Could not insert weapon_turret_sounds!");
+        // Insert our weapons
+        insert_rows!(connection, weapons::table, "weapons", 29, weapons_to_insert, options, |x| x.name.to_string());
+        insert_rows!(connection, weapon_results::table, "weapon_results", 7, weapon_results_to_insert, options, |x| x.weapon_name.to_string());
+        insert_rows!(connection, weapon_penetrations::table, "weapon_penetrations", 3, weapon_penetrations_to_insert, options, |x| x.weapon_name.to_string());
+        insert_rows!(connection, weapon_accuracy::table, "weapon_accuracy", 4, weapon_accuracies_to_insert, options, |x| x.weapon_name.to_string());
+        insert_rows!(connection, weapon_angles::table, "weapon_angles", 6, weapon_angles_to_insert, options, |x| x.weapon_name.to_string());
+        insert_rows!(connection, weapon_misc::table, "weapon_misc", 3, weapon_misc_to_insert, options, |x| x.weapon_name.to_string());
+        insert_rows!(connection, weapon_turret_sound::table, "weapon_turret_sound", 2, weapon_turret_sounds_to_insert, options, |x| x.weapon_name.to_string());
+        insert_rows!(connection, weapon_extra_statements::table, "weapon_extra_statements", 4, weapon_extra_statements_to_insert, options, |x| x.weapon_name.to_string());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing;
+
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+
+        names.sort();
+
+        names
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_whole_file() {
+        let dir = testing::temp_dir("atomic_write_replaces_the_whole_file");
+        let path = dir.join("hgn_flak.wepn");
+
+        write_atomically(&path, "a much longer first version").unwrap();
+        write_atomically(&path, "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(files_in(&dir), vec!["hgn_flak.wepn".to_string()]);
+    }
+
+    #[test]
+    fn failed_atomic_write_leaves_nothing_behind() {
+        let dir = testing::temp_dir("failed_atomic_write_leaves_nothing_behind");
+
+        // Renaming a file over a directory that isn't empty fails after the temporary file is written.
+        let path = dir.join("hgn_flak");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("hgn_flak.wepn"), "old").unwrap();
+
+        assert!(write_atomically(&path, "new").is_err());
+        assert_eq!(files_in(&dir), vec!["hgn_flak".to_string()]);
+        assert_eq!(fs::read_to_string(path.join("hgn_flak.wepn")).unwrap(), "old");
+
+        assert!(write_atomically(&dir.join("missing").join("hgn_flak.wepn"), "new").is_err());
+        assert_eq!(files_in(&dir), vec!["hgn_flak".to_string()]);
+    }
+
+    #[test]
+    fn backups_never_overwrite_each_other() {
+        let dir = testing::temp_dir("backups_never_overwrite_each_other");
+        let path = dir.join("hgn_flak.wepn");
+        let backup_dir = dir.join("backups");
+
+        fs::write(&path, "first").unwrap();
+        let first = backup_file(&path, &backup_dir).unwrap();
+
+        fs::write(&path, "second").unwrap();
+        let second = backup_file(&path, &backup_dir).unwrap();
+
+        fs::write(&path, "third").unwrap();
+        let third = backup_file(&path, &backup_dir).unwrap();
+
+        assert_eq!(first.parent().unwrap(), backup_dir.join("hgn_flak"));
+        assert!(first.file_name().unwrap().to_string_lossy().starts_with("hgn_flak."));
+        assert!(first.file_name().unwrap().to_string_lossy().ends_with(".wepn.bak"));
+        assert_eq!(files_in(&backup_dir.join("hgn_flak")).len(), 3);
+
+        assert_eq!(fs::read_to_string(first).unwrap(), "first");
+        assert_eq!(fs::read_to_string(second).unwrap(), "second");
+        assert_eq!(fs::read_to_string(third).unwrap(), "third");
+    }
+
+    #[test]
+    fn backup_of_a_missing_file_fails_without_creating_one() {
+        let dir = testing::temp_dir("backup_of_a_missing_file_fails_without_creating_one");
+        let backup_dir = dir.join("backups");
+
+        assert!(backup_file(&dir.join("hgn_flak.wepn"), &backup_dir).is_err());
+        assert!(files_in(&backup_dir.join("hgn_flak")).is_empty());
+    }
+
+    #[test]
+    fn rows_per_insert_fit_in_sqlite_max_variables() {
+        let options = |chunk_size| ImportOptions {
+            chunk_size,
+            ..Default::default()
+        };
+
+        assert_eq!(options(None).rows_per_insert(1), SQLITE_MAX_VARIABLES);
+        assert_eq!(options(None).rows_per_insert(7), SQLITE_MAX_VARIABLES / 7);
+        assert_eq!(options(Some(50)).rows_per_insert(7), 50);
+        assert_eq!(options(Some(5000)).rows_per_insert(7), SQLITE_MAX_VARIABLES / 7);
+        assert_eq!(options(Some(0)).rows_per_insert(7), 1);
     }
 }
@@ -0,0 +1,56 @@
+use std::fmt;
+
+///
+/// Import Error
+///
+/// Raised when imported weapons or ships can't be written to the database. The whole import is rolled back, so the
+/// database is left as it was. `weapon_name` is the weapon (or ship) whose rows were rejected, when it can be found.
+///
+#[derive(Debug)]
+pub struct ImportError {
+    pub table: Option<String>,
+    pub weapon_name: Option<String>,
+    pub source: diesel::result::Error
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.table {
+            Some(table) => write!(f, "Could not write to `{}`", table)?,
+            None => write!(f, "Database error")?
+        }
+
+        if let Some(weapon_name) = &self.weapon_name {
+            write!(f, " for `{}`", weapon_name)?;
+        }
+
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl std::error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<diesel::result::Error> for ImportError {
+    fn from(source: diesel::result::Error) -> Self {
+        Self {
+            table: None,
+            weapon_name: None,
+            source
+        }
+    }
+}
+
+impl ImportError {
+    /// Error writing to `table`.
+    pub fn new(table: &str, weapon_name: Option<String>, source: diesel::result::Error) -> Self {
+        Self {
+            table: Some(table.to_string()),
+            weapon_name,
+            source
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use diesel::prelude::*;
+use super::weapon::WeaponCollection;
+use super::weapon_result::WeaponResultCollection;
+use crate::json;
+use std::collections::{ BTreeMap, BTreeSet, HashMap, HashSet, VecDeque };
+
+/// A quoted Graphviz ID.
+fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+///
+/// Spawn Graph
+///
+/// Which weapons spawn which, through the last argument of `AddWeaponResult(...)`. Every weapon in the database is a
+/// node, and each edge goes from a weapon to a weapon it spawns. The game matches weapon names without regard to
+/// case, so spawned names are resolved to the weapon they refer to; names that don't refer to any weapon are kept in
+/// `dangling` instead.
+///
+#[derive(Debug, Default)]
+pub struct SpawnGraph {
+    /// Each weapon, and the weapons it spawns in the order its results list them, without repeats.
+    pub spawns: BTreeMap<String, Vec<String>>,
+    /// Each weapon that spawns something that isn't in the database, and the names it spawns.
+    pub dangling: BTreeMap<String, Vec<String>>
+}
+
+impl SpawnGraph {
+    /// Build the graph from every weapon and weapon result in the Database.
+    pub fn from_database(connection: &mut SqliteConnection) -> Self {
+        let names = WeaponCollection::get_all_weapons(connection).get_names();
+        let results = WeaponResultCollection::get_spawning(connection);
+
+        let canonical: HashMap<String, &String> = names.iter().map(|n| (n.to_lowercase(), n)).collect();
+
+        let mut graph = Self {
+            spawns: names.iter().map(|n| (n.to_string(), Vec::new())).collect(),
+            dangling: BTreeMap::new()
+        };
+
+        for wr in results.weapon_results.iter() {
+            let Some(spawn) = &wr.spawn_weapon_name else {
+                continue;
+            };
+
+            let (edges, target) = match canonical.get(&spawn.to_lowercase()) {
+                Some(name) => (graph.spawns.entry(wr.weapon_name.to_string()).or_default(), name.to_string()),
+                None => (graph.dangling.entry(wr.weapon_name.to_string()).or_default(), spawn.to_string())
+            };
+
+            if !edges.contains(&target) {
+                edges.push(target);
+            }
+        }
+
+        graph
+    }
+
+    /// The weapons `weapon` spawns.
+    pub fn spawns_of(&self, weapon: &str) -> &[String] {
+        self.spawns.get(weapon).map(|s| s.as_slice()).unwrap_or_default()
+    }
+
+    /// Only `root` and the weapons it spawns, directly or not. `root` is matched without regard to case; `None` if
+    /// there's no such weapon.
+    pub fn rooted_at(&self, root: &str) -> Option<Self> {
+        let root = self.spawns.keys().find(|n| n.eq_ignore_ascii_case(root))?;
+
+        let mut graph = Self::default();
+        let mut queue: VecDeque<&String> = VecDeque::from([root]);
+
+        while let Some(weapon) = queue.pop_front() {
+            if graph.spawns.contains_key(weapon) {
+                continue;
+            }
+
+            graph.spawns.insert(weapon.to_string(), self.spawns_of(weapon).to_vec());
+
+            if let Some(dangling) = self.dangling.get(weapon) {
+                graph.dangling.insert(weapon.to_string(), dangling.to_vec());
+            }
+
+            queue.extend(self.spawns_of(weapon));
+        }
+
+        Some(graph)
+    }
+
+    /// Only the weapons that spawn something, or are spawned by something.
+    pub fn linked(&self) -> Self {
+        let spawned: HashSet<&String> = self.spawns.values().flatten().collect();
+
+        Self {
+            spawns: self.spawns
+                .iter()
+                .filter(|(name, spawns)| !spawns.is_empty() || spawned.contains(name) || self.dangling.contains_key(*name))
+                .map(|(name, spawns)| (name.to_string(), spawns.to_vec()))
+                .collect(),
+            dangling: self.dangling.clone()
+        }
+    }
+
+    /// Graphviz DOT. Edges that are part of a loop are red; spawned weapons that don't exist are dashed.
+    pub fn to_dot(&self) -> String {
+        let in_cycle: BTreeSet<(String, String)> = self.cycles()
+            .iter()
+            .flat_map(|c| c.windows(2).map(|w| (w[0].to_string(), w[1].to_string())).collect::<Vec<_>>())
+            .collect();
+
+        let mut lines = vec![String::from("digraph spawns {")];
+
+        for (weapon, spawns) in self.spawns.iter() {
+            lines.push(format!("    {};", dot_id(weapon)));
+
+            for spawn in spawns {
+                let style = if in_cycle.contains(&(weapon.to_string(), spawn.to_string())) { " [color=red]" } else { "" };
+
+                lines.push(format!("    {} -> {}{};", dot_id(weapon), dot_id(spawn), style));
+            }
+        }
+
+        for (weapon, spawns) in self.dangling.iter() {
+            for spawn in spawns {
+                lines.push(format!("    {} [style=dashed];", dot_id(spawn)));
+                lines.push(format!("    {} -> {} [style=dashed];", dot_id(weapon), dot_id(spawn)));
+            }
+        }
+
+        lines.push(String::from("}"));
+
+        lines.join("\n")
+    }
+
+    /// JSON adjacency lists: `weapons` maps each weapon to the weapons it spawns, `missing` maps weapons to spawned
+    /// names that aren't weapons, and `cycles` lists every loop.
+    pub fn to_json(&self) -> String {
+        let adjacency = |map: &BTreeMap<String, Vec<String>>| {
+            json::object(map
+                .iter()
+                .map(|(name, spawns)| (name.as_str(), json::array(spawns.iter().map(|s| json::string(s)))))
+                .collect())
+        };
+
+        json::object(vec![
+            ("weapons", adjacency(&self.spawns)),
+            ("missing", adjacency(&self.dangling)),
+            ("cycles", json::array(self.cycles().iter().map(|c| json::array(c.iter().map(|n| json::string(n))))))
+        ])
+    }
+
+    /// Every loop of weapons that end up spawning themselves, e.g. `["a", "b", "a"]`. A weapon that spawns itself
+    /// directly is `["a", "a"]`. Each loop is found from its first weapon by name, taking the shortest way back, so
+    /// it's only reported once.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles = Vec::new();
+
+        for start in self.spawns.keys() {
+            // Breadth-first search back to `start`, only through weapons that sort after it.
+            let mut previous: HashMap<&String, &String> = HashMap::new();
+            let mut seen: HashSet<&String> = HashSet::new();
+            let mut queue: VecDeque<&String> = VecDeque::from([start]);
+            let mut closed_by = None;
+
+            while let Some(weapon) = queue.pop_front() {
+                for spawn in self.spawns_of(weapon) {
+                    if spawn == start {
+                        closed_by = Some(weapon);
+                        break;
+                    }
+
+                    if spawn > start && seen.insert(spawn) {
+                        previous.insert(spawn, weapon);
+                        queue.push_back(spawn);
+                    }
+                }
+
+                if closed_by.is_some() {
+                    break;
+                }
+            }
+
+            if let Some(mut weapon) = closed_by {
+                let mut cycle = vec![start.to_string()];
+
+                while weapon != start {
+                    cycle.push(weapon.to_string());
+                    weapon = previous[weapon];
+                }
+
+                cycle.push(start.to_string());
+                cycle.reverse();
+                cycles.push(cycle);
+            }
+        }
+
+        cycles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing;
+
+    /// `AddWeaponResult(...)` spawning each of `spawns` on hit.
+    fn spawning(spawns: &[&str]) -> String {
+        spawns
+            .iter()
+            .map(|s| format!("AddWeaponResult(NewWeaponType,\"Hit\",\"SpawnWeaponFire\",\"Target\",0,0,\"{}\");", s))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn graph(weapons: &[(&str, &[&str])]) -> SpawnGraph {
+        let connection = &mut testing::database();
+        let bodies: Vec<(&str, String)> = weapons.iter().map(|(name, spawns)| (*name, spawning(spawns))).collect();
+        let files: Vec<(&str, &str)> = bodies.iter().map(|(name, body)| (*name, body.as_str())).collect();
+
+        testing::import_weapons(connection, &files);
+
+        SpawnGraph::from_database(connection)
+    }
+
+    #[test]
+    fn weapon_spawning_itself_is_a_cycle() {
+        let graph = graph(&[("a", &["a"]), ("b", &[])]);
+
+        assert_eq!(graph.cycles(), vec![vec!["a".to_string(), "a".to_string()]]);
+    }
+
+    #[test]
+    fn indirect_cycle_is_reported_once() {
+        let graph = graph(&[("a", &["b"]), ("b", &["a", "c"]), ("c", &[])]);
+
+        assert_eq!(graph.cycles(), vec![vec!["a".to_string(), "b".to_string(), "a".to_string()]]);
+    }
+
+    #[test]
+    fn chain_without_a_loop_has_no_cycles() {
+        let graph = graph(&[("a", &["b"]), ("b", &["c"]), ("c", &[])]);
+
+        assert!(graph.cycles().is_empty());
+        assert_eq!(graph.spawns_of("a"), ["b".to_string()]);
+    }
+
+    #[test]
+    fn spawned_names_are_matched_without_regard_to_case() {
+        let graph = graph(&[("hgn_flak", &["HGN_FlakBurst", "hgn_flakburst"]), ("hgn_flakburst", &["Hgn_Flak"])]);
+
+        assert_eq!(graph.spawns_of("hgn_flak"), ["hgn_flakburst".to_string()]);
+        assert!(graph.dangling.is_empty());
+        assert_eq!(graph.cycles(), vec![vec!["hgn_flak".to_string(), "hgn_flakburst".to_string(), "hgn_flak".to_string()]]);
+    }
+
+    #[test]
+    fn spawns_of_missing_weapons_are_dangling() {
+        let graph = graph(&[("a", &["b", "nope"]), ("b", &[])]);
+
+        assert_eq!(graph.spawns_of("a"), ["b".to_string()]);
+        assert_eq!(graph.dangling.get("a"), Some(&vec!["nope".to_string()]));
+        assert!(graph.cycles().is_empty());
+        assert!(graph.to_dot().contains("\"a\" -> \"nope\" [style=dashed];"));
+    }
+}
@@ -1,14 +1,61 @@
 use std::fmt;
 use diesel::prelude::*;
 use crate::schema::{weapons};
-use regex::Regex;
-use std::collections::HashMap;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
+use crate::models::miss_file::missile::MissileCollection;
+use std::collections::{ HashMap, HashSet };
 
-///
-/// Regex's
-///
-pub fn start_weapon_config_regex() -> Regex {
-    Regex::new(r"StartWeaponConfig\(NewWeaponType,\s*(.*)\)").unwrap()
+string_enum! {
+    ///
+    /// Weapon Type
+    ///
+    /// How the weapon is mounted; argument 1 of `StartWeaponConfig(...)`.
+    ///
+    WeaponType {
+        Gimble => "Gimble",
+        Fixed => "Fixed",
+        AnimatedTurret => "AnimatedTurret"
+    }
+}
+
+string_enum! {
+    ///
+    /// Weapon Fire Type
+    ///
+    /// What the weapon fires; argument 2 of `StartWeaponConfig(...)`. `weapon_fire_name` refers to a missile for
+    /// `Missile`, and to a bullet or effect otherwise.
+    ///
+    WeaponFireType {
+        Bullet => "Bullet",
+        Missile => "Missile",
+        InstantHit => "InstantHit",
+        SphereBurst => "SphereBurst",
+        Mine => "Mine"
+    }
+}
+
+string_enum! {
+    ///
+    /// Activation
+    ///
+    /// When the weapon fires; argument 4 of `StartWeaponConfig(...)`.
+    ///
+    Activation {
+        Normal => "Normal",
+        SpecialAttack => "SpecialAttack"
+    }
+}
+
+string_enum! {
+    ///
+    /// Shield Penetration
+    ///
+    /// Argument 22 of `StartWeaponConfig(...)`.
+    ///
+    ShieldPenetration {
+        Normal => "Normal"
+    }
 }
 
 ///
@@ -19,10 +66,10 @@ pub fn start_weapon_config_regex() -> Regex {
 #[diesel(primary_key(name))]
 pub struct Weapon {
     pub name: String,
-    pub weapon_type: String,
-    pub weapon_fire_type: String,
+    pub weapon_type: WeaponType,
+    pub weapon_fire_type: WeaponFireType,
     pub weapon_fire_name: String,
-    pub activation: String,
+    pub activation: Activation,
     pub fire_speed: f32,
     pub fire_range: f32,
     pub fire_radius: f32,
@@ -40,7 +87,7 @@ pub struct Weapon {
     pub max_azimuth_speed: f32,
     pub max_declination_speed: f32,
     pub speed_multiplier: f32,
-    pub shield_penetration: String,
+    pub shield_penetration: ShieldPenetration,
     pub track_targets_outside_range: i32,
     pub wait_for_code_red: f32,
     pub instant_hit_threshold: i32,
@@ -86,51 +133,96 @@ impl fmt::Display for Weapon {
 }
 
 impl Weapon {
-    /// Create a `Weapon` Model from a `name` and String `body`.
-    pub fn from_string(name: &String, body: &String) -> Self {
-        let rx: Regex = start_weapon_config_regex();
+    /// Every field except `name`, paired with its value, for comparing weapons field by field.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let fields = [
+            ("weapon_type", self.weapon_type.to_string()),
+            ("weapon_fire_type", self.weapon_fire_type.to_string()),
+            ("weapon_fire_name", self.weapon_fire_name.to_string()),
+            ("activation", self.activation.to_string()),
+            ("fire_speed", self.fire_speed.to_string()),
+            ("fire_range", self.fire_range.to_string()),
+            ("fire_radius", self.fire_radius.to_string()),
+            ("fire_lifetime", self.fire_lifetime.to_string()),
+            ("fire_anticipation_time", self.fire_anticipation_time.to_string()),
+            ("fire_axis", self.fire_axis.to_string()),
+            ("max_effects_spawned", self.max_effects_spawned.to_string()),
+            ("lead_target", self.lead_target.to_string()),
+            ("check_line_of_fire", self.check_line_of_fire.to_string()),
+            ("fire_time", self.fire_time.to_string()),
+            ("burst_fire_time", self.burst_fire_time.to_string()),
+            ("burst_wait_time", self.burst_wait_time.to_string()),
+            ("shoot_at_secondaries", self.shoot_at_secondaries.to_string()),
+            ("shoot_at_surroundings", self.shoot_at_surroundings.to_string()),
+            ("max_azimuth_speed", self.max_azimuth_speed.to_string()),
+            ("max_declination_speed", self.max_declination_speed.to_string()),
+            ("speed_multiplier", self.speed_multiplier.to_string()),
+            ("shield_penetration", self.shield_penetration.to_string()),
+            ("track_targets_outside_range", self.track_targets_outside_range.to_string()),
+            ("wait_for_code_red", self.wait_for_code_red.to_string()),
+            ("instant_hit_threshold", self.instant_hit_threshold.to_string()),
+            ("field_penetration", self.field_penetration.to_string()),
+            ("default_penetration", self.default_penetration.to_string()),
+            ("default_accuracy", self.default_accuracy.to_string())
+        ];
 
-        let args = match rx.captures(&body).unwrap().get(1) {
-            Some(x) => x.as_str(),
-            None => ""
-        };
+        fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+    }
 
-        let split: Vec<&str> = args.split(",").collect::<Vec<&str>>().iter().map(|x| x.trim()).collect();
+    /// The enum fields set to values Scaffold doesn't know, as `(field, value)`.
+    pub fn unknown_values(&self) -> Vec<(&'static str, &str)> {
+        let fields = [
+            ("weapon_type", self.weapon_type.is_other(), self.weapon_type.as_str()),
+            ("weapon_fire_type", self.weapon_fire_type.is_other(), self.weapon_fire_type.as_str()),
+            ("activation", self.activation.is_other(), self.activation.as_str()),
+            ("shield_penetration", self.shield_penetration.is_other(), self.shield_penetration.as_str())
+        ];
 
-        Self {
+        fields.into_iter().filter(|(_, other, _)| *other).map(|(field, _, value)| (field, value)).collect()
+    }
+
+    /// Create a `Weapon` Model from a `name` and the parsed `StartWeaponConfig(...)` call in `chunk`.
+    pub fn from_chunk(name: &str, chunk: &Chunk) -> Result<Self, ParseError> {
+        let call = chunk
+            .find_call("StartWeaponConfig")
+            .ok_or_else(|| ParseError::new("Missing `StartWeaponConfig(...)` call"))?;
+
+        // Argument 0 is always `NewWeaponType`.
+        Ok(Self {
             name: name.to_string(),
-            weapon_type: split[0].to_string().replace("\"", ""),
-            weapon_fire_type: split[1].to_string().replace("\"", ""),
-            weapon_fire_name: split[2].to_string().replace("\"", ""),
-            activation: split[3].to_string().replace("\"", ""),
-            fire_speed: split[4].parse().unwrap(),
-            fire_range: split[5].parse().unwrap(),
-            fire_radius: split[6].parse().unwrap(),
-            fire_lifetime: split[7].parse().unwrap(),
-            fire_anticipation_time: split[8].parse().unwrap(),
-            fire_axis: split[9].parse().unwrap(),
-            max_effects_spawned: split[10].parse().unwrap(),
-            lead_target: split[11].parse().unwrap(),
-            check_line_of_fire: split[12].parse().unwrap(),
-            fire_time: split[13].parse().unwrap(),
-            burst_fire_time: split[14].parse().unwrap(),
-            burst_wait_time: split[15].parse().unwrap(),
-            shoot_at_secondaries: split[16].parse().unwrap(),
-            shoot_at_surroundings: split[17].parse().unwrap(),
-            max_azimuth_speed: split[18].parse().unwrap(),
-            max_declination_speed: split[19].parse().unwrap(),
-            speed_multiplier: split[20].parse().unwrap(),
-            shield_penetration: split[21].to_string().replace("\"", ""),
-            track_targets_outside_range: split[22].parse().unwrap(),
-            wait_for_code_red: split[23].parse().unwrap(),
-            instant_hit_threshold: split[24].parse().unwrap(),
+            weapon_type: call.string_arg(1)?.as_str().into(),
+            weapon_fire_type: call.string_arg(2)?.as_str().into(),
+            weapon_fire_name: call.string_arg(3)?,
+            activation: call.string_arg(4)?.as_str().into(),
+            fire_speed: call.number_arg(5)?,
+            fire_range: call.number_arg(6)?,
+            fire_radius: call.number_arg(7)?,
+            fire_lifetime: call.number_arg(8)?,
+            fire_anticipation_time: call.number_arg(9)?,
+            fire_axis: call.integer_arg(10)?,
+            max_effects_spawned: call.integer_arg(11)?,
+            lead_target: call.integer_arg(12)?,
+            check_line_of_fire: call.integer_arg(13)?,
+            fire_time: call.number_arg(14)?,
+            burst_fire_time: call.number_arg(15)?,
+            burst_wait_time: call.number_arg(16)?,
+            shoot_at_secondaries: call.integer_arg(17)?,
+            shoot_at_surroundings: call.integer_arg(18)?,
+            max_azimuth_speed: call.number_arg(19)?,
+            max_declination_speed: call.number_arg(20)?,
+            speed_multiplier: call.number_arg(21)?,
+            shield_penetration: call.string_arg(22)?.as_str().into(),
+            track_targets_outside_range: call.integer_arg(23)?,
+            wait_for_code_red: call.number_arg(24)?,
+            instant_hit_threshold: call.integer_arg(25)?,
             field_penetration: 0,
             default_penetration: 0.0,
             default_accuracy: 1.0
-        }
+        })
     }
 
     /// Get the Weapon File for a specific weapon name. Possible that it can't be found.
+    #[allow(clippy::manual_unwrap_or_default)]
     pub fn get_for_weapon(connection: &mut SqliteConnection, weapon_name: &String) -> Option<Self> {
         use crate::schema::weapons::dsl::*;
 
@@ -195,4 +287,24 @@ impl WeaponCollection {
     pub fn get_names(&self) -> Vec<String> {
         self.weapons.keys().map(|n| n.to_string()).collect()
     }
+
+    /// Get the weapons that fire a `Missile` whose `weapon_fire_name` isn't in the `missiles` table, sorted by name.
+    /// Names are compared without regard to case, as the game does.
+    pub fn get_missing_missiles(connection: &mut SqliteConnection) -> Vec<Weapon> {
+        let names: HashSet<String> = MissileCollection::get_all_missiles(connection)
+            .get_names()
+            .iter()
+            .map(|n| n.to_lowercase())
+            .collect();
+
+        let mut missing: Vec<Weapon> = Self::get_all_weapons(connection)
+            .weapons
+            .into_values()
+            .filter(|w| w.weapon_fire_type == WeaponFireType::Missile && !names.contains(&w.weapon_fire_name.to_lowercase()))
+            .collect();
+
+        missing.sort_by(|a, b| a.name.cmp(&b.name));
+
+        missing
+    }
 }
@@ -1,23 +1,10 @@
-use regex::Regex;
 use diesel::prelude::*;
 use crate::schema::{weapon_accuracy};
 use super::weapon::Weapon;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
 use std::fmt;
-use std::collections::HashMap;
-
-///
-/// Regex's
-///
-
-/// Find the `setAccuracy(...)` function call in a string, parse into groups.
-pub fn set_accuracy_regex() -> Regex {
-    Regex::new(r"setAccuracy\(NewWeaponType\s*,\s*([0-9\.]*)\s*,\s*([\sA-za-z0-9=\.\{\},]*)\s*\)").unwrap()
-}
-
-/// Find the armor family arguments in `setAccuracy(...)`
-pub fn armor_family_regex() -> Regex {
-    Regex::new(r"\{\s*(\w*)\s*=\s*(\d*\.*\d*)\s*,\s*(?:damage\s*=\s*(\d*\.*\d*))*\s*,*\}").unwrap()
-}
+use std::collections::{ HashMap, HashSet };
 
 ///
 /// Weapon Accuracy
@@ -51,7 +38,20 @@ pub struct WeaponAccuracyCollection {
 }
 
 impl WeaponAccuracyCollection {
+    /// Each armour family's accuracy and damage, keyed as `accuracy.<family>` and `accuracy.<family>.damage`.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+
+        for wa in self.weapon_accuracies.iter() {
+            fields.push((format!("accuracy.{}", wa.armor_family), wa.accuracy.to_string()));
+            fields.push((format!("accuracy.{}.damage", wa.armor_family), wa.damage.to_string()));
+        }
+
+        fields
+    }
+
     /// Return a new empty instance
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             default_accuracy: 1.0,
@@ -74,6 +74,7 @@ impl WeaponAccuracyCollection {
 
         let vec = weapon_accuracy
                     .filter(weapon_name.eq(name))
+                    .order(id.asc())
                     .load::<WeaponAccuracy>(connection)
                     .expect("Error loading weapon accuracy!");
 
@@ -90,6 +91,7 @@ impl WeaponAccuracyCollection {
 
         let vec = weapon_accuracy
                     .filter(weapon_name.eq_any(names))
+                    .order(id.asc())
                     .load::<WeaponAccuracy>(connection)
                     .expect("Error loading weapon accuracies!");
 
@@ -99,17 +101,32 @@ impl WeaponAccuracyCollection {
         }
     }
 
+    /// Get the weapon accuracy rows whose armour family isn't in `families` (lowercased names), ordered by weapon name.
+    pub fn get_with_unknown_armour_family(connection: &mut SqliteConnection, families: &HashSet<String>) -> Self {
+        use crate::schema::weapon_accuracy::dsl::*;
+
+        let vec = weapon_accuracy
+                    .order((weapon_name.asc(), id.asc()))
+                    .load::<WeaponAccuracy>(connection)
+                    .expect("Error loading weapon accuracies!");
+
+        Self {
+            default_accuracy: 0.0,
+            weapon_accuracies: vec.into_iter().filter(|wa| !families.contains(&wa.armor_family.to_lowercase())).collect()
+        }
+    }
+
     /// Copy the `default_accuracy` value from weapon so it can be exported.
     pub fn use_default_accuracy(&mut self, weapon: &Weapon) {
         self.default_accuracy = weapon.default_accuracy;
     }
 
-    /// Consume the collection, divide into HashMap by `weapon_name`
+    /// Consume the collection, divide into HashMap by `weapon_name`. Keeps the original order.
     pub fn key_by_weapon_name(self) -> HashMap<String, Self> {
-        let mut weapon_accuracies: Vec<WeaponAccuracy> = self.weapon_accuracies;
+        let weapon_accuracies: Vec<WeaponAccuracy> = self.weapon_accuracies;
         let mut map = HashMap::new();
 
-        while let Some(wa) = weapon_accuracies.pop() {
+        for wa in weapon_accuracies {
             if !map.contains_key(&wa.weapon_name) {
                 map.insert(wa.weapon_name.to_string(), Self {
                     default_accuracy: self.default_accuracy,
@@ -154,6 +171,7 @@ impl fmt::Display for WeaponAccuracyCollection {
 ///
 
 /// Class Ready to be Inserted via Diesel
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Default, Insertable, Debug)]
 #[diesel(table_name = weapon_accuracy)]
 pub struct NewWeaponAccuracy {
@@ -171,43 +189,53 @@ pub struct NewWeaponAccuracyCollection {
 }
 
 impl NewWeaponAccuracyCollection {
-    /// Parse the `setAccuracy(...)` lua call into an instance.
-    /// This whole process needs to be optimized at some point.
-    pub fn from_string(weapon_name: &String, body: &String) -> Self {
-        let rx = set_accuracy_regex();
-        let rx_family = armor_family_regex();
-
-        let caps = rx.captures(body);
-
+    /// Build from the parsed `setAccuracy(...)` lua call in `chunk`.
+    /// Every argument after the default is a table of `{ArmorFamily=accuracy, damage=multiplier}`.
+    pub fn from_chunk(weapon_name: &str, chunk: &Chunk) -> Result<Self, ParseError> {
         let mut default_accuracy: f32 = 0.0;
         let mut weapon_accuracies: Vec<NewWeaponAccuracy> = Vec::new();
 
-        if caps.is_some() {
-            let caps_unwrapped = caps.unwrap();
+        if let Some(call) = chunk.find_call("setAccuracy") {
+            default_accuracy = call.number_arg(1)?;
+
+            for index in 2..call.args.len() {
+                let fields = call.table_arg(index)?;
+                let mut damage: f32 = 1.0;
+                let mut families: Vec<(&str, f32)> = Vec::new();
 
-            default_accuracy = caps_unwrapped.get(1).unwrap().as_str().parse().unwrap_or(0.0);
+                for field in fields {
+                    let name = field
+                        .name()
+                        .ok_or_else(|| ParseError::argument(call, index, "Expected `ArmorFamily=accuracy` pairs"))?;
 
-            let family_str = caps_unwrapped.get(2).unwrap().as_str();
+                    let value = field
+                        .value
+                        .as_number()
+                        .ok_or_else(|| ParseError::argument(call, index, &format!("Expected a number for `{}`", name)))?;
 
-            for family in rx_family.captures_iter(family_str) {
-                weapon_accuracies.push(NewWeaponAccuracy {
-                    weapon_name: weapon_name.to_string(),
-                    armor_family: family.get(1).unwrap().as_str().to_string(),
-                    accuracy: family.get(2).unwrap().as_str().parse().unwrap_or(0.0),
-                    damage: if let Some(d) = family.get(3) {
-                        d.as_str().parse().unwrap_or(1.0)
+                    if name == "damage" {
+                        damage = value as f32;
                     } else {
-                        1.0
+                        families.push((name, value as f32));
                     }
-                });
+                }
+
+                for (armor_family, accuracy) in families {
+                    weapon_accuracies.push(NewWeaponAccuracy {
+                        weapon_name: weapon_name.to_string(),
+                        armor_family: armor_family.to_string(),
+                        accuracy,
+                        damage
+                    });
+                }
             }
         } else {
-            println!("No weapon accuracies found for: {}!", weapon_name);
+            eprintln!("No weapon accuracies found for: {}!", weapon_name);
         }
 
-        Self {
+        Ok(Self {
             default_accuracy,
             weapon_accuracies
-        }
+        })
     }
 }
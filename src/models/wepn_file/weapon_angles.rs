@@ -1,19 +1,11 @@
-use regex::Regex;
 use diesel::prelude::*;
 use crate::schema::{weapon_angles};
 use super::weapon::Weapon;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
 use std::fmt;
 use std::collections::HashMap;
 
-///
-/// Regex's
-///
-
-/// Find the `setAngles(...)` function call in a string, parse into groups.
-pub fn set_angles_regex() -> Regex {
-    Regex::new(r"setAngles\(NewWeaponType\s*,\s*([0-9\.\-]*)\s*,\s*([0-9\.\-]*)\s*,\s*([0-9\.\-]*)\s*,\s*([0-9\.\-]*)\s*,\s*([0-9\.\-]*)\s*\)").unwrap()
-}
-
 ///
 /// Weapon Angle
 ///
@@ -43,7 +35,19 @@ impl fmt::Display for WeaponAngles {
 }
 
 impl WeaponAngles {
+    /// Every angle, paired with its value and prefixed with `angles`.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        vec![
+            ("angles.firing_cone".to_string(), self.firing_cone.to_string()),
+            ("angles.min_azimuth".to_string(), self.min_azimuth.to_string()),
+            ("angles.max_azimuth".to_string(), self.max_azimuth.to_string()),
+            ("angles.min_declination".to_string(), self.min_declination.to_string()),
+            ("angles.max_declination".to_string(), self.max_declination.to_string())
+        ]
+    }
+
     /// Get weapon angles for a specific weapon name. Possible that it can't be found.
+    #[allow(clippy::manual_unwrap_or_default)]
     pub fn get_for_weapon(connection: &mut SqliteConnection, name: &String) -> Option<Self> {
         use crate::schema::weapon_angles::dsl::*;
 
@@ -91,6 +95,7 @@ impl WeaponAnglesCollection {
 ///
 
 /// Class Ready to be Inserted via Diesel
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Default, Insertable, Debug)]
 #[diesel(table_name = weapon_angles)]
 pub struct NewWeaponAngles {
@@ -103,23 +108,19 @@ pub struct NewWeaponAngles {
 }
 
 impl NewWeaponAngles {
-    /// Parse the `setAngles(...)` lua call into an instance.
-    /// This whole process needs to be optimized at some point.
-    pub fn from_string(weapon_name: &String, body: &String) -> Option<Self> {
-        let rx = set_angles_regex();
-        let some_caps = rx.captures(body);
-
-        if let Some(caps) = some_caps {
-            Some(NewWeaponAngles {
+    /// Build from the parsed `setAngles(...)` lua call in `chunk`.
+    pub fn from_chunk(weapon_name: &str, chunk: &Chunk) -> Result<Option<Self>, ParseError> {
+        if let Some(call) = chunk.find_call("setAngles") {
+            Ok(Some(NewWeaponAngles {
                 weapon_name: weapon_name.to_string(),
-                firing_cone: caps.get(1).unwrap().as_str().parse().unwrap_or(0.0),
-                min_azimuth: caps.get(2).unwrap().as_str().parse().unwrap_or(0.0),
-                max_azimuth: caps.get(3).unwrap().as_str().parse().unwrap_or(0.0),
-                min_declination: caps.get(4).unwrap().as_str().parse().unwrap_or(0.0),
-                max_declination: caps.get(5).unwrap().as_str().parse().unwrap_or(0.0)
-            })
+                firing_cone: call.number_arg(1)?,
+                min_azimuth: call.number_arg(2)?,
+                max_azimuth: call.number_arg(3)?,
+                min_declination: call.number_arg(4)?,
+                max_declination: call.number_arg(5)?
+            }))
         } else {
-            None
+            Ok(None)
         }
     }
 }
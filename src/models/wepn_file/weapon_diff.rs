@@ -0,0 +1,355 @@
+use std::fmt;
+use std::collections::{ BTreeSet, HashMap };
+use super::{ WeaponFile, WeaponFileCollection };
+use crate::json;
+use crate::unified_diff::common_items;
+
+///
+/// Field Change
+///
+/// One value that differs between two versions of a weapon. `from` or `to` is `None` when the field only exists on
+/// one side, e.g. a penetration for an armour family that was added.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub weapon_name: String,
+    pub field: String,
+    pub from: Option<String>,
+    pub to: Option<String>
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}: {} → {}",
+            self.weapon_name,
+            self.field,
+            Self::display_value(self.from.as_deref()),
+            Self::display_value(self.to.as_deref())
+        )
+    }
+}
+
+impl FieldChange {
+    fn new(weapon_name: &str, field: String, from: Option<&String>, to: Option<&String>) -> Self {
+        Self {
+            weapon_name: weapon_name.to_string(),
+            field,
+            from: from.cloned(),
+            to: to.cloned()
+        }
+    }
+
+    /// Keep each change on one line: line breaks are escaped, and empty values are shown as `""`.
+    fn display_value(value: Option<&str>) -> String {
+        match value {
+            None => String::from("(none)"),
+            Some("") => String::from("\"\""),
+            Some(v) => v.replace("\r\n", "\\n").replace('\n', "\\n")
+        }
+    }
+
+    /// JSON object for this change.
+    pub fn to_json(&self) -> String {
+        json::object(vec![
+            ("weapon", json::string(&self.weapon_name)),
+            ("field", json::string(&self.field)),
+            ("from", json::optional_string(self.from.as_deref())),
+            ("to", json::optional_string(self.to.as_deref()))
+        ])
+    }
+}
+
+///
+/// Weapon File Diff
+///
+/// Field-by-field differences between two sets of weapon files, e.g. two databases or a database and a 'data/'
+/// directory. Weapons are matched by name.
+///
+#[derive(Debug, Default)]
+pub struct WeaponFileDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: usize,
+    pub changes: Vec<FieldChange>
+}
+
+impl fmt::Display for WeaponFileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in self.added.iter() {
+            writeln!(f, "added... {}", name)?;
+        }
+
+        for name in self.removed.iter() {
+            writeln!(f, "removed... {}", name)?;
+        }
+
+        for change in self.changes.iter() {
+            writeln!(f, "{}", change)?;
+        }
+
+        write!(f, "{} added, {} changed, {} unchanged, {} removed.",
+            self.added.len(),
+            self.changed.len(),
+            self.unchanged,
+            self.removed.len()
+        )
+    }
+}
+
+impl WeaponFileDiff {
+    /// Compare every weapon in `before` with the one of the same name in `after`.
+    pub fn compare(before: &WeaponFileCollection, after: &WeaponFileCollection) -> Self {
+        let mut diff = Self::default();
+
+        let names: BTreeSet<&String> = before.weapon_files.keys()
+            .chain(after.weapon_files.keys())
+            .collect();
+
+        for name in names {
+            match (before.weapon_files.get(name), after.weapon_files.get(name)) {
+                (Some(old), Some(new)) => {
+                    let changes = Self::compare_weapon(name, old, new);
+
+                    if changes.is_empty() {
+                        diff.unchanged += 1;
+                    } else {
+                        diff.changed.push(name.to_string());
+                        diff.changes.extend(changes);
+                    }
+                },
+                (Some(_), None) => diff.removed.push(name.to_string()),
+                (None, Some(_)) => diff.added.push(name.to_string()),
+                (None, None) => {}
+            }
+        }
+
+        diff
+    }
+
+    /// Compare two versions of a weapon, field by field. Keyed fields come first, in the order they're written out
+    /// with fields only found in `new` at the end, then the results and extra statements.
+    fn compare_weapon(name: &str, old: &WeaponFile, new: &WeaponFile) -> Vec<FieldChange> {
+        let old_fields = old.fields();
+        let new_fields = new.fields();
+        let old_values: HashMap<&String, &String> = old_fields.iter().map(|(k, v)| (k, v)).collect();
+        let new_values: HashMap<&String, &String> = new_fields.iter().map(|(k, v)| (k, v)).collect();
+
+        let mut changes = Vec::new();
+
+        for (field, value) in old_fields.iter() {
+            let to = new_values.get(field);
+
+            if to != Some(&value) {
+                changes.push(FieldChange::new(name, field.to_string(), Some(value), to.copied()));
+            }
+        }
+
+        for (field, value) in new_fields.iter() {
+            if !old_values.contains_key(field) {
+                changes.push(FieldChange::new(name, field.to_string(), None, Some(value)));
+            }
+        }
+
+        changes.extend(Self::compare_entries(name, "results",
+            &old.weapon_results.fields(),
+            &new.weapon_results.fields()
+        ));
+
+        let as_entry = |statement: String| vec![(String::new(), statement)];
+
+        changes.extend(Self::compare_entries(name, "extra",
+            &old.weapon_extra_statements.fields().into_iter().map(as_entry).collect::<Vec<_>>(),
+            &new.weapon_extra_statements.fields().into_iter().map(as_entry).collect::<Vec<_>>()
+        ));
+
+        changes
+    }
+
+    /// Compare two lists of entries that have no key of their own, such as results. Entries are matched by content,
+    /// so inserting one only reports that one; unmatched entries between two matches are compared in order. A
+    /// removed entry is numbered by its place in `old`, any other by its place in `new`, e.g. `results[2].effect`
+    /// (or `extra[2]` for an entry whose only field has no name).
+    fn compare_entries(name: &str, list: &str, old: &[Vec<(String, String)>], new: &[Vec<(String, String)>]) -> Vec<FieldChange> {
+        let field = |index: usize, key: &str| match key {
+            "" => format!("{}[{}]", list, index),
+            _ => format!("{}[{}].{}", list, index, key)
+        };
+
+        let mut changes = Vec::new();
+        let (mut i, mut j) = (0, 0);
+
+        for (next_old, next_new) in common_items(old, new).into_iter().chain([(old.len(), new.len())]) {
+            let paired = (next_old - i).min(next_new - j);
+
+            for k in 0..paired {
+                for ((key, from), (_, to)) in old[i + k].iter().zip(new[j + k].iter()) {
+                    if from != to {
+                        changes.push(FieldChange::new(name, field(j + k, key), Some(from), Some(to)));
+                    }
+                }
+            }
+
+            for (index, entry) in old.iter().enumerate().take(next_old).skip(i + paired) {
+                for (key, from) in entry.iter() {
+                    changes.push(FieldChange::new(name, field(index, key), Some(from), None));
+                }
+            }
+
+            for (index, entry) in new.iter().enumerate().take(next_new).skip(j + paired) {
+                for (key, to) in entry.iter() {
+                    changes.push(FieldChange::new(name, field(index, key), None, Some(to)));
+                }
+            }
+
+            (i, j) = (next_old + 1, next_new + 1);
+        }
+
+        changes
+    }
+
+    /// JSON object with the `added`, `removed` and `changed` weapon names, the `unchanged` count and every field
+    /// in `changes`.
+    pub fn to_json(&self) -> String {
+        json::object(vec![
+            ("added", json::array(self.added.iter().map(|n| json::string(n)))),
+            ("removed", json::array(self.removed.iter().map(|n| json::string(n)))),
+            ("changed", json::array(self.changed.iter().map(|n| json::string(n)))),
+            ("unchanged", self.unchanged.to_string()),
+            ("changes", json::array(self.changes.iter().map(|c| c.to_json())))
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::data_file::{ NewDataFile, NewDataFileCollection };
+    use crate::models::testing;
+    use crate::models::wepn_file::{ NewWeaponFile, NewWeaponFileCollection };
+
+    const HIT: &str = "AddWeaponResult(NewWeaponType,\"Hit\",\"DamageHealth\",\"Target\",10,10,\"\");";
+    const MISS: &str = "AddWeaponResult(NewWeaponType,\"Miss\",\"DamageHealth\",\"Target\",5,5,\"\");";
+    const KILL: &str = "AddWeaponResult(NewWeaponType,\"Hit\",\"Disable\",\"Target\",1,1,\"\");";
+
+    /// Import `(name, file contents)` weapon files into an empty database, and read them back the way `diff` does.
+    fn weapon_files(weapons: &[(&str, &str)]) -> WeaponFileCollection {
+        let connection = &mut testing::database();
+        let files = weapons.iter()
+            .map(|(name, contents)| NewWeaponFile::from_string(name, contents).unwrap())
+            .collect();
+
+        NewWeaponFileCollection::from_vec(files).insert(connection, &testing::quiet()).unwrap();
+
+        WeaponFileCollection::get_all_weapon_files(connection)
+    }
+
+    /// `testing::WEAPON` followed by `body`.
+    fn weapon(body: &str) -> String {
+        format!("{}\n{}", testing::WEAPON, body)
+    }
+
+    fn fields(diff: &WeaponFileDiff) -> Vec<String> {
+        diff.changes.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn lists_added_removed_and_unchanged_weapons() {
+        let before = weapon_files(&[("old", &weapon("")), ("same", &weapon(""))]);
+        let after = weapon_files(&[("new", &weapon("")), ("same", &weapon(""))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(diff.added, vec!["new"]);
+        assert_eq!(diff.removed, vec!["old"]);
+        assert_eq!(diff.changed, Vec::<String>::new());
+        assert_eq!(diff.unchanged, 1);
+        assert_eq!(diff.to_string(), "added... new\nremoved... old\n1 added, 0 changed, 1 unchanged, 1 removed.");
+    }
+
+    #[test]
+    fn reports_each_changed_field() {
+        let before = weapon_files(&[("tst", &weapon(""))]);
+        let after = weapon_files(&[("tst", &testing::WEAPON.replace(",2200,", ",2600,"))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(diff.changed, vec!["tst"]);
+        assert_eq!(fields(&diff), vec!["tst.fire_range: 2200 → 2600"]);
+    }
+
+    #[test]
+    fn keys_penetration_and_accuracy_by_family() {
+        let before = weapon_files(&[("tst", &weapon("setPenetration(NewWeaponType,5,1,{Fighter=1},{Frigate=0.5});\nsetAccuracy(NewWeaponType,1,{Fighter=0.5});"))]);
+        let after = weapon_files(&[("tst", &weapon("setPenetration(NewWeaponType,5,1,{Fighter=1},{Capital=0.25});\nsetAccuracy(NewWeaponType,1,{Fighter=0.75});"))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(fields(&diff), vec![
+            "tst.penetration.Frigate: 0.5 → (none)",
+            "tst.accuracy.Fighter: 0.5 → 0.75",
+            "tst.penetration.Capital: (none) → 0.25"
+        ]);
+    }
+
+    #[test]
+    fn inserting_a_result_only_reports_that_result() {
+        let before = weapon_files(&[("tst", &weapon(&[HIT, MISS].join("\n")))]);
+        let after = weapon_files(&[("tst", &weapon(&[KILL, HIT, MISS].join("\n")))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(fields(&diff), vec![
+            "tst.results[0].condition: (none) → Hit",
+            "tst.results[0].effect: (none) → Disable",
+            "tst.results[0].target: (none) → Target",
+            "tst.results[0].minimum_effect: (none) → 1",
+            "tst.results[0].maximum_effect: (none) → 1",
+            "tst.results[0].spawn_weapon_name: (none) → \"\""
+        ]);
+    }
+
+    #[test]
+    fn removing_a_result_numbers_it_as_it_was() {
+        let before = weapon_files(&[("tst", &weapon(&[KILL, HIT, MISS].join("\n")))]);
+        let after = weapon_files(&[("tst", &weapon(&[KILL, MISS].join("\n")))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(diff.changes.len(), 6);
+        assert!(diff.changes.iter().all(|c| c.field.starts_with("results[1].") && c.to.is_none()));
+    }
+
+    #[test]
+    fn compares_unmatched_results_in_order() {
+        let before = weapon_files(&[("tst", &weapon(&[HIT, MISS].join("\n")))]);
+        let after = weapon_files(&[("tst", &weapon(&[HIT, &MISS.replace(",5,5,", ",5,8,")].join("\n")))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(fields(&diff), vec!["tst.results[1].maximum_effect: 5 → 8"]);
+    }
+
+    #[test]
+    fn matches_extra_statements_by_content() {
+        let before = weapon_files(&[("tst", &weapon("-- one\n-- two"))]);
+        let after = weapon_files(&[("tst", &weapon("-- zero\n-- one\n-- two"))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(fields(&diff), vec!["tst.extra[0]: (none) → -- zero"]);
+    }
+
+    #[test]
+    fn json_lists_weapons_and_changes() {
+        let before = weapon_files(&[("old", &weapon("")), ("tst", &weapon(""))]);
+        let after = weapon_files(&[("tst", &testing::WEAPON.replace(",2200,", ",2600,"))]);
+
+        let diff = WeaponFileDiff::compare(&before, &after);
+
+        assert_eq!(diff.to_json(), concat!(
+            r#"{"added":[],"removed":["old"],"changed":["tst"],"unchanged":0,"#,
+            r#""changes":[{"weapon":"tst","field":"fire_range","from":"2200","to":"2600"}]}"#
+        ));
+    }
+}
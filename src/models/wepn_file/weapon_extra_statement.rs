@@ -0,0 +1,160 @@
+use diesel::prelude::*;
+use crate::schema::{weapon_extra_statements};
+use super::weapon::Weapon;
+use crate::models::lua::{ Call, Chunk };
+use super::WEAPON_FILE_CALLS;
+use crate::models::data_file::{ anchored_extras, without_watermark };
+use std::fmt;
+use std::collections::HashMap;
+
+///
+/// Weapon Extra Statement
+///
+/// Any statement in a *.wepn file that Scaffold doesn't model (comments, `dofilepath`, variables, calls added by
+/// newer patches, ...). Stored verbatim with the `anchor` of the modelled call it follows (see `data_file::anchor`),
+/// so it can be written back where it was found; `position` orders the statements.
+///
+#[derive(Queryable, Selectable, Identifiable, Associations, Debug)]
+#[diesel(belongs_to(Weapon, foreign_key = weapon_name))]
+#[diesel(table_name = weapon_extra_statements)]
+pub struct WeaponExtraStatement {
+    pub id: i32,
+    pub weapon_name: String,
+    pub position: i32,
+    pub statement: String,
+    pub anchor: Option<String>
+}
+
+impl fmt::Display for WeaponExtraStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.statement)
+    }
+}
+
+/// Array of `WeaponExtraStatement`, ordered by `position`.
+#[derive(Debug)]
+pub struct WeaponExtraStatementCollection {
+    pub weapon_extra_statements: Vec<WeaponExtraStatement>
+}
+
+impl Default for WeaponExtraStatementCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeaponExtraStatementCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            weapon_extra_statements: Vec::new()
+        }
+    }
+
+    /// Each statement, in order. Like results, the diff matches them by content (see `WeaponFileDiff`), so adding
+    /// a comment doesn't shift every statement after it.
+    pub fn fields(&self) -> Vec<String> {
+        self.weapon_extra_statements
+            .iter()
+            .map(|ws| ws.statement.to_string())
+            .collect()
+    }
+
+    /// Get extra statements for a specific weapon name.
+    pub fn get_for_weapon(connection: &mut SqliteConnection, name: &str) -> Self {
+        use crate::schema::weapon_extra_statements::dsl::*;
+
+        let vec = weapon_extra_statements
+                    .filter(weapon_name.eq(name))
+                    .order(position.asc())
+                    .load::<WeaponExtraStatement>(connection)
+                    .expect("Error loading weapon extra statements!");
+
+        Self {
+            weapon_extra_statements: vec
+        }
+    }
+
+    /// Get extra statements for a list of weapon names.
+    pub fn get_for_weapons(connection: &mut SqliteConnection, names: &[String]) -> Self {
+        use crate::schema::weapon_extra_statements::dsl::*;
+
+        let vec = weapon_extra_statements
+                    .filter(weapon_name.eq_any(names))
+                    .order(position.asc())
+                    .load::<WeaponExtraStatement>(connection)
+                    .expect("Error loading weapon extra statements!");
+
+        Self {
+            weapon_extra_statements: vec
+        }
+    }
+
+    /// Consume the collection, divide into HashMap by `weapon_name`. Keeps the `position` order.
+    pub fn key_by_weapon_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for ws in self.weapon_extra_statements {
+            map
+                .entry(ws.weapon_name.to_string())
+                .or_default()
+                .weapon_extra_statements.push(ws);
+        }
+
+        map
+    }
+}
+
+///
+/// NewWeaponExtraStatement
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Debug)]
+#[diesel(table_name = weapon_extra_statements)]
+pub struct NewWeaponExtraStatement {
+    pub weapon_name: String,
+    pub position: i32,
+    pub statement: String,
+    pub anchor: Option<String>
+}
+
+/// Array of `NewWeaponExtraStatement` with helper methods.
+#[derive(Debug)]
+pub struct NewWeaponExtraStatementCollection {
+    pub weapon_extra_statements: Vec<NewWeaponExtraStatement>
+}
+
+impl NewWeaponExtraStatementCollection {
+    /// Collect every statement in `chunk` that isn't one of the calls in `WEAPON_FILE_CALLS`, anchored to the call
+    /// before it. Only the first of each call is modelled (except `AddWeaponResult`), so any repeats are kept here
+    /// too, as are comments inside the modelled calls.
+    pub fn from_chunk(weapon_name: &str, chunk: &Chunk, source: &str) -> Self {
+        let mut seen: Vec<String> = Vec::new();
+
+        let modelled = |call: &Call| {
+            let known = WEAPON_FILE_CALLS.contains(&call.name.as_str());
+
+            if known && (call.name == "AddWeaponResult" || !seen.contains(&call.name)) {
+                seen.push(call.name.to_string());
+                true
+            } else {
+                false
+            }
+        };
+
+        let weapon_extra_statements = anchored_extras(without_watermark(chunk), source, modelled)
+            .into_iter()
+            .enumerate()
+            .map(|(position, (anchor, statement))| NewWeaponExtraStatement {
+                weapon_name: weapon_name.to_string(),
+                position: position as i32,
+                statement,
+                anchor
+            })
+            .collect();
+
+        Self {
+            weapon_extra_statements
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use diesel::prelude::*;
+use diesel::dsl::sql;
+use diesel::sql_types::Bool;
+use regex::Regex;
+
+///
+/// Weapon Filter
+///
+/// Narrows down which weapons to work with. A weapon has to pass every filter that's set: be one of `only` (if any
+/// are given), have a name matching `pattern`, and satisfy the SQL `predicate` on the `weapons` table.
+///
+#[derive(Debug, Default)]
+pub struct WeaponFilter {
+    pub only: Vec<String>,
+    pub pattern: Option<Regex>,
+    pub predicate: Option<String>
+}
+
+impl WeaponFilter {
+    /// Create a filter. `pattern` is a glob (`hgn_*`, `kus_?cannon`), or a regular expression if wrapped in
+    /// slashes (`/^(hgn|vgr)_.*gun$/`).
+    pub fn new(only: Vec<String>, pattern: Option<&str>, predicate: Option<String>) -> Result<Self, regex::Error> {
+        let pattern = match pattern {
+            Some(p) => Some(Self::compile_pattern(p)?),
+            None => None
+        };
+
+        Ok(Self {
+            only,
+            pattern,
+            predicate
+        })
+    }
+
+    /// Does this filter let every weapon through?
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.pattern.is_none() && self.predicate.is_none()
+    }
+
+    /// Names of the weapons in the database that pass the filter, sorted. Fails if `predicate` isn't valid SQL.
+    pub fn get_names(&self, connection: &mut SqliteConnection) -> QueryResult<Vec<String>> {
+        use crate::schema::weapons::dsl::*;
+
+        let mut query = weapons.select(name).into_boxed();
+
+        if let Some(predicate) = &self.predicate {
+            query = query.filter(sql::<Bool>(predicate));
+        }
+
+        let mut names: Vec<String> = query
+            .load::<String>(connection)?
+            .into_iter()
+            .filter(|n| self.matches(n))
+            .collect();
+
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Names passed to `only` that aren't in the database.
+    pub fn get_missing(&self, connection: &mut SqliteConnection) -> QueryResult<Vec<String>> {
+        use crate::schema::weapons::dsl::*;
+
+        let found: Vec<String> = weapons
+            .select(name)
+            .filter(name.eq_any(&self.only))
+            .load::<String>(connection)?;
+
+        Ok(self.only.iter().filter(|n| !found.contains(n)).map(|n| n.to_string()).collect())
+    }
+
+    /// Check a weapon name against `only` and `pattern`.
+    fn matches(&self, weapon_name: &str) -> bool {
+        let listed = self.only.is_empty() || self.only.iter().any(|n| n == weapon_name);
+        let matched = match &self.pattern {
+            Some(re) => re.is_match(weapon_name),
+            None => true
+        };
+
+        listed && matched
+    }
+
+    /// Turn a glob, or a `/regex/`, into a `Regex`. Globs match the whole name; `*` matches any run of characters
+    /// and `?` matches one.
+    fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+        if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            return Regex::new(&pattern[1..pattern.len() - 1]);
+        }
+
+        let mut re = String::from("^");
+
+        for c in pattern.chars() {
+            match c {
+                '*' => re.push_str(".*"),
+                '?' => re.push('.'),
+                _ => re.push_str(&regex::escape(&c.to_string()))
+            }
+        }
+
+        re.push('$');
+
+        Regex::new(&re)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing;
+
+    fn filter(pattern: &str) -> WeaponFilter {
+        WeaponFilter::new(Vec::new(), Some(pattern), None).unwrap()
+    }
+
+    #[test]
+    fn globs_match_the_whole_name() {
+        let hgn = filter("hgn_*");
+
+        assert!(hgn.matches("hgn_flak"));
+        assert!(hgn.matches("hgn_"));
+        assert!(!hgn.matches("kus_hgn_flak"));
+
+        let cannon = filter("kus_?cannon");
+
+        assert!(cannon.matches("kus_xcannon"));
+        assert!(!cannon.matches("kus_cannon"));
+        assert!(!cannon.matches("kus_ioncannon"));
+
+        assert!(!filter("flak").matches("hgn_flak"));
+    }
+
+    #[test]
+    fn globs_escape_regex_characters() {
+        let dotted = filter("hgn.flak");
+
+        assert!(dotted.matches("hgn.flak"));
+        assert!(!dotted.matches("hgnxflak"));
+
+        assert!(filter("(hgn)+[1]").matches("(hgn)+[1]"));
+        assert!(filter("a^b$").matches("a^b$"));
+    }
+
+    #[test]
+    fn slashes_make_a_regex() {
+        let gun = filter("/^(hgn|vgr)_.*gun$/");
+
+        assert!(gun.matches("hgn_assaultfrigategun"));
+        assert!(gun.matches("vgr_gun"));
+        assert!(!gun.matches("kus_gun"));
+
+        // Unanchored, like any regex.
+        assert!(filter("/flak/").matches("hgn_flakburst"));
+
+        // A lone slash is a glob.
+        assert!(filter("/").matches("/"));
+
+        assert!(WeaponFilter::new(Vec::new(), Some("/(hgn/"), None).is_err());
+    }
+
+    #[test]
+    fn only_and_pattern_both_have_to_match() {
+        let filter = WeaponFilter::new(vec!["hgn_flak".to_string(), "kus_gun".to_string()], Some("hgn_*"), None).unwrap();
+
+        assert!(filter.matches("hgn_flak"));
+        assert!(!filter.matches("kus_gun"));
+        assert!(!filter.matches("hgn_gun"));
+        assert!(WeaponFilter::default().is_empty());
+    }
+
+    #[test]
+    fn predicate_filters_the_weapons_table() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("hgn_flak", ""), ("hgn_gun", ""), ("kus_gun", "")]);
+
+        let filter = WeaponFilter::new(Vec::new(), Some("*_gun"), Some("name LIKE 'hgn%'".to_string())).unwrap();
+
+        assert_eq!(filter.get_names(connection).unwrap(), vec!["hgn_gun".to_string()]);
+
+        let only = WeaponFilter::new(vec!["hgn_flak".to_string(), "nope".to_string()], None, None).unwrap();
+
+        assert_eq!(only.get_missing(connection).unwrap(), vec!["nope".to_string()]);
+    }
+
+    #[test]
+    fn bad_predicate_is_an_error() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("hgn_flak", "")]);
+
+        let filter = WeaponFilter::new(Vec::new(), None, Some("no_such_column = 1".to_string())).unwrap();
+
+        assert!(filter.get_names(connection).is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::collections::HashMap;
+use super::{ WeaponFile, WeaponFileCollection };
+use crate::models::parse_error::ParseError;
+use crate::json;
+
+///
+/// Lint Severity
+///
+/// Errors make `validate` fail; warnings are only reported.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning")
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Severity::Error),
+            "warning" => Ok(Severity::Warning),
+            _ => Err(format!("Unknown severity `{}`; expected `error`, `warning` or `off`", s))
+        }
+    }
+}
+
+///
+/// Lint Rule
+///
+/// A named check over one weapon file. `check` returns a message for each problem it finds.
+///
+pub struct LintRule {
+    pub id: &'static str,
+    pub severity: Severity,
+    pub description: &'static str,
+    pub check: fn(&WeaponFile) -> Vec<String>
+}
+
+/// Every rule, in the order findings are reported.
+pub const LINT_RULES: [LintRule; 6] = [
+    LintRule {
+        id: "azimuth-order",
+        severity: Severity::Error,
+        description: "`setAngles` minimum azimuth is greater than the maximum",
+        check: check_azimuth_order
+    },
+    LintRule {
+        id: "effect-order",
+        severity: Severity::Error,
+        description: "`AddWeaponResult` minimum effect is greater than the maximum",
+        check: check_effect_order
+    },
+    LintRule {
+        id: "negative-fire-range",
+        severity: Severity::Error,
+        description: "fire range is negative",
+        check: check_negative_fire_range
+    },
+    LintRule {
+        id: "burst-wait-zero",
+        severity: Severity::Warning,
+        description: "burst wait time is zero, but burst fire time isn't",
+        check: check_burst_wait_zero
+    },
+    LintRule {
+        id: "accuracy-range",
+        severity: Severity::Error,
+        description: "an accuracy is outside 0 to 1",
+        check: check_accuracy_range
+    },
+    LintRule {
+        id: "armour-family-case",
+        severity: Severity::Warning,
+        description: "an armour family is listed again in `setPenetration` or `setAccuracy`, in a different case",
+        check: check_armour_family_case
+    }
+];
+
+fn check_azimuth_order(wepn: &WeaponFile) -> Vec<String> {
+    match &wepn.weapon_angles {
+        Some(angles) if angles.min_azimuth > angles.max_azimuth => {
+            vec![format!("min_azimuth ({}) is greater than max_azimuth ({})", angles.min_azimuth, angles.max_azimuth)]
+        },
+        _ => Vec::new()
+    }
+}
+
+fn check_effect_order(wepn: &WeaponFile) -> Vec<String> {
+    wepn.weapon_results.weapon_results
+        .iter()
+        .enumerate()
+        .filter(|(_, wr)| wr.minimum_effect > wr.maximum_effect)
+        .map(|(index, wr)| {
+            format!("results[{}]: minimum_effect ({}) is greater than maximum_effect ({})", index, wr.minimum_effect, wr.maximum_effect)
+        })
+        .collect()
+}
+
+fn check_negative_fire_range(wepn: &WeaponFile) -> Vec<String> {
+    if wepn.weapon.fire_range < 0.0 {
+        vec![format!("fire_range is {}", wepn.weapon.fire_range)]
+    } else {
+        Vec::new()
+    }
+}
+
+fn check_burst_wait_zero(wepn: &WeaponFile) -> Vec<String> {
+    if wepn.weapon.burst_wait_time == 0.0 && wepn.weapon.burst_fire_time != 0.0 {
+        vec![format!("burst_wait_time is 0, but burst_fire_time is {}", wepn.weapon.burst_fire_time)]
+    } else {
+        Vec::new()
+    }
+}
+
+fn check_accuracy_range(wepn: &WeaponFile) -> Vec<String> {
+    let mut messages = Vec::new();
+
+    if !(0.0..=1.0).contains(&wepn.weapon.default_accuracy) {
+        messages.push(format!("default_accuracy is {}", wepn.weapon.default_accuracy));
+    }
+
+    for wa in wepn.weapon_accuracy.weapon_accuracies.iter() {
+        if !(0.0..=1.0).contains(&wa.accuracy) {
+            messages.push(format!("accuracy for `{}` is {}", wa.armor_family, wa.accuracy));
+        }
+    }
+
+    messages
+}
+
+/// The database can't hold the same armour family twice for one weapon, but it can hold two that only differ in case,
+/// which the game treats as the same family.
+fn check_armour_family_case(wepn: &WeaponFile) -> Vec<String> {
+    let penetrations = wepn.weapon_penetration.weapon_penetrations.iter().map(|wp| wp.armor_family.as_str());
+    let accuracies = wepn.weapon_accuracy.weapon_accuracies.iter().map(|wa| wa.armor_family.as_str());
+
+    let mut messages = Vec::new();
+
+    for (call, families) in [("setPenetration", penetrations.collect::<Vec<&str>>()), ("setAccuracy", accuracies.collect())] {
+        let mut seen: Vec<&str> = Vec::new();
+
+        for family in families {
+            match seen.iter().find(|s| s.eq_ignore_ascii_case(family)) {
+                Some(first) => messages.push(format!("`{}` is also listed as `{}` in {}", family, first, call)),
+                None => seen.push(family)
+            }
+        }
+    }
+
+    messages
+}
+
+///
+/// Lint Config
+///
+/// Severity overrides, read from a file with one `rule-id = error|warning|off` per line. `#` starts a comment.
+/// Rules that aren't mentioned keep their default severity.
+///
+#[derive(Debug, Default)]
+pub struct LintConfig {
+    pub severities: HashMap<String, Option<Severity>>
+}
+
+impl LintConfig {
+    /// Read a lint config file.
+    pub fn from_path(path: &Path) -> Result<Self, ParseError> {
+        let contents = fs::read_to_string(path)
+                        .map_err(|e| ParseError::new(&format!("Could not read file: {}", e)).with_path(path))?;
+
+        Self::from_string(&contents).map_err(|e| e.with_path(path))
+    }
+
+    /// Parse the body of a lint config file. Unknown rules and severities are errors, so typos don't go unnoticed.
+    pub fn from_string(contents: &str) -> Result<Self, ParseError> {
+        let mut severities = HashMap::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |message: String| ParseError {
+                line: index + 1,
+                column: 1,
+                ..ParseError::new(&message)
+            };
+
+            let (id, severity) = line
+                .split_once('=')
+                .map(|(id, severity)| (id.trim(), severity.trim()))
+                .ok_or_else(|| error(format!("Expected `rule-id = error|warning|off`, found `{}`", line)))?;
+
+            if !LINT_RULES.iter().any(|rule| rule.id == id) {
+                return Err(error(format!("Unknown lint rule `{}`", id)));
+            }
+
+            let severity = match severity {
+                "off" => None,
+                severity => Some(severity.parse::<Severity>().map_err(error)?)
+            };
+
+            severities.insert(id.to_string(), severity);
+        }
+
+        Ok(Self {
+            severities
+        })
+    }
+
+    /// Severity of `rule`, or `None` if it's turned off.
+    pub fn severity(&self, rule: &LintRule) -> Option<Severity> {
+        self.severities.get(rule.id).copied().unwrap_or(Some(rule.severity))
+    }
+}
+
+///
+/// Lint Finding
+///
+/// One problem a rule found in a weapon.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub weapon_name: String,
+    pub message: String
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.wepn: {}[{}]: {}", self.weapon_name, self.severity, self.rule, self.message)
+    }
+}
+
+impl LintFinding {
+    /// The finding as a JSON object.
+    pub fn to_json(&self) -> String {
+        json::object(vec![
+            ("rule", json::string(self.rule)),
+            ("severity", json::string(&self.severity.to_string())),
+            ("weapon", json::string(&self.weapon_name)),
+            ("message", json::string(&self.message))
+        ])
+    }
+}
+
+/// Run every rule that isn't turned off over every weapon file. Findings are sorted by weapon name, then in the
+/// order of `LINT_RULES`.
+pub fn lint(weapon_files: &WeaponFileCollection, config: &LintConfig) -> Vec<LintFinding> {
+    let mut names: Vec<&String> = weapon_files.weapon_files.keys().collect();
+    let mut findings = Vec::new();
+
+    names.sort();
+
+    for name in names {
+        let wepn = &weapon_files.weapon_files[name];
+
+        for rule in LINT_RULES.iter() {
+            let Some(severity) = config.severity(rule) else {
+                continue;
+            };
+
+            for message in (rule.check)(wepn) {
+                findings.push(LintFinding {
+                    rule: rule.id,
+                    severity,
+                    weapon_name: name.to_string(),
+                    message
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::prelude::*;
+    use diesel_migrations::MigrationHarness;
+    use crate::models::data_file::{ NewDataFile, NewDataFileCollection };
+    use crate::models::wepn_file::{ ImportOptions, NewWeaponFile, NewWeaponFileCollection };
+
+    const WEAPON: &str = "StartWeaponConfig(NewWeaponType,\"Gimble\",\"Bullet\",\"Flak\",\"Normal\",1800,2200,0,0,0,0,1,1,1,0.5,0.5,2,0,0,60,60,1,\"Normal\",0,0,0);";
+
+    /// Import `body` as weapon `tst` into an empty database, and read it back the way `validate` does.
+    fn weapon_file(body: &str) -> WeaponFile {
+        let connection = &mut SqliteConnection::establish(":memory:").unwrap();
+
+        connection.run_pending_migrations(crate::MIGRATIONS).unwrap();
+
+        let options = ImportOptions {
+            quiet: true,
+            ..Default::default()
+        };
+
+        let file = NewWeaponFile::from_string("tst", &format!("{}\n{}", WEAPON, body)).unwrap();
+
+        NewWeaponFileCollection::from_vec(vec![file]).insert(connection, &options).unwrap();
+
+        WeaponFile::get_for_weapon(connection, &"tst".to_string()).unwrap()
+    }
+
+    fn messages(rule_id: &str, wepn: &WeaponFile) -> Vec<String> {
+        let rule = LINT_RULES.iter().find(|rule| rule.id == rule_id).unwrap();
+
+        (rule.check)(wepn)
+    }
+
+    #[test]
+    fn clean_weapon_has_no_findings() {
+        let wepn = weapon_file("setPenetration(NewWeaponType,5,1,{Fighter=1});\nsetAccuracy(NewWeaponType,1,{Fighter=0.5});\nsetAngles(NewWeaponType,10,-20,20,-5,5);");
+        let collection = WeaponFileCollection {
+            weapon_files: HashMap::from([("tst".to_string(), wepn)])
+        };
+
+        assert_eq!(lint(&collection, &LintConfig::default()), Vec::new());
+    }
+
+    #[test]
+    fn rules_find_their_problems() {
+        let wepn = weapon_file(concat!(
+            "AddWeaponResult(NewWeaponType,\"Hit\",\"DamageHealth\",\"Target\",10,5,\"\");\n",
+            "setAngles(NewWeaponType,10,20,-20,-5,5);\n",
+            "setAccuracy(NewWeaponType,1.5,{Fighter=0.5},{fighter=-1});"
+        ));
+
+        assert_eq!(messages("azimuth-order", &wepn), vec!["min_azimuth (20) is greater than max_azimuth (-20)"]);
+        assert_eq!(messages("effect-order", &wepn), vec!["results[0]: minimum_effect (10) is greater than maximum_effect (5)"]);
+        assert_eq!(messages("accuracy-range", &wepn), vec!["default_accuracy is 1.5", "accuracy for `fighter` is -1"]);
+        assert_eq!(messages("armour-family-case", &wepn), vec!["`fighter` is also listed as `Fighter` in setAccuracy"]);
+        assert_eq!(messages("negative-fire-range", &wepn), Vec::<String>::new());
+        assert_eq!(messages("burst-wait-zero", &wepn), Vec::<String>::new());
+    }
+
+    #[test]
+    fn config_overrides_severities() {
+        let config = LintConfig::from_string("# comment\nazimuth-order = warning\n\nburst-wait-zero = off # too noisy\n  accuracy-range=error").unwrap();
+        let rule = |id: &str| LINT_RULES.iter().find(|rule| rule.id == id).unwrap();
+
+        assert_eq!(config.severity(rule("azimuth-order")), Some(Severity::Warning));
+        assert_eq!(config.severity(rule("burst-wait-zero")), None);
+        assert_eq!(config.severity(rule("accuracy-range")), Some(Severity::Error));
+        assert_eq!(config.severity(rule("armour-family-case")), Some(Severity::Warning));
+    }
+
+    #[test]
+    fn config_rejects_unknown_rules_and_severities() {
+        let error = LintConfig::from_string("azimuth-order = error\nno-such-rule = off").unwrap_err();
+
+        assert_eq!((error.line, error.message.as_str()), (2, "Unknown lint rule `no-such-rule`"));
+
+        let error = LintConfig::from_string("azimuth-order = fatal").unwrap_err();
+
+        assert_eq!(error.message, "Unknown severity `fatal`; expected `error`, `warning` or `off`");
+
+        let error = LintConfig::from_string("azimuth-order").unwrap_err();
+
+        assert_eq!(error.message, "Expected `rule-id = error|warning|off`, found `azimuth-order`");
+    }
+}
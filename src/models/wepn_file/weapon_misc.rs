@@ -1,19 +1,11 @@
-use regex::Regex;
 use diesel::prelude::*;
 use crate::schema::{weapon_misc};
 use super::weapon::Weapon;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
 use std::fmt;
 use std::collections::HashMap;
 
-///
-/// Regex's
-///
-
-/// Find the `setMiscValues(...)` function call in a string, parse into groups.
-pub fn set_misc_values_regex() -> Regex {
-    Regex::new(r"setMiscValues\(NewWeaponType\s*,\s*([0-9\.\-]*)\s*,\s*([0-9\.\-]*)\s*\)").unwrap()
-}
-
 ///
 /// Weapon Misc
 ///
@@ -37,7 +29,16 @@ impl fmt::Display for WeaponMisc {
 }
 
 impl WeaponMisc {
+    /// Every value, prefixed with `misc`.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        vec![
+            ("misc.recoil_distance".to_string(), self.recoil_distance.to_string()),
+            ("misc.slave_fire_delay".to_string(), self.slave_fire_delay.to_string())
+        ]
+    }
+
     /// Get weapon misc for a specific weapon name. Possible that it can't be found.
+    #[allow(clippy::manual_unwrap_or_default)]
     pub fn get_for_weapon(connection: &mut SqliteConnection, name: &String) -> Option<Self> {
         use crate::schema::weapon_misc::dsl::*;
 
@@ -85,6 +86,7 @@ impl WeaponMiscCollection {
 ///
 
 /// Class Ready to be Inserted via Diesel
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Default, Insertable, Debug)]
 #[diesel(table_name = weapon_misc)]
 pub struct NewWeaponMisc {
@@ -94,19 +96,16 @@ pub struct NewWeaponMisc {
 }
 
 impl NewWeaponMisc {
-    /// Parse the `setMiscValues(...)` lua call into an instance.
-    /// This whole process needs to be optimized at some point.
-    pub fn from_string(weapon_name: &String, body: &String) -> Option<Self> {
-        let rx = set_misc_values_regex();
-
-        if let Some(caps) = rx.captures(body) {
-            Some(Self {
+    /// Build from the parsed `setMiscValues(...)` lua call in `chunk`.
+    pub fn from_chunk(weapon_name: &str, chunk: &Chunk) -> Result<Option<Self>, ParseError> {
+        if let Some(call) = chunk.find_call("setMiscValues") {
+            Ok(Some(Self {
                 weapon_name: weapon_name.to_string(),
-                recoil_distance: caps.get(1).unwrap().as_str().parse().unwrap_or(0.0),
-                slave_fire_delay: caps.get(2).unwrap().as_str().parse().unwrap_or(0.0)
-            })
+                recoil_distance: call.number_arg(1)?,
+                slave_fire_delay: call.number_arg(2)?
+            }))
         } else {
-            None
+            Ok(None)
         }
     }
 }
@@ -1,23 +1,10 @@
-use regex::Regex;
 use diesel::prelude::*;
 use crate::schema::{weapon_penetrations};
 use super::weapon::Weapon;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
 use std::fmt;
-use std::collections::HashMap;
-
-///
-/// Regex's
-///
-
-/// Find the `setPenetration(...)` function call in a string, parse into groups.
-pub fn set_penetration_regex() -> Regex {
-    Regex::new(r"setPenetration\(NewWeaponType\s*,\s*([0-9]*)\s*,\s*([0-9\.]*)\s*,\s*([\sA-za-z0-9=\.\{\},]*)\s*\)").unwrap()
-}
-
-/// Find the armor family arguments in `setPenetration(...)`
-pub fn armor_family_regex() -> Regex {
-    Regex::new(r"\{\s*(\w*)\s*=\s*(\d*\.*\d*)\s*,*\s*\}").unwrap()
-}
+use std::collections::{ HashMap, HashSet };
 
 ///
 /// Weapon Penetration
@@ -49,7 +36,16 @@ pub struct WeaponPenetrationCollection {
 }
 
 impl WeaponPenetrationCollection {
+    /// Each armour family's penetration, keyed as `penetration.<family>`.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        self.weapon_penetrations
+            .iter()
+            .map(|wp| (format!("penetration.{}", wp.armor_family), wp.penetration.to_string()))
+            .collect()
+    }
+
     /// Return a new empty instance
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             field_penetration: 0,
@@ -74,6 +70,7 @@ impl WeaponPenetrationCollection {
 
         let vec = weapon_penetrations
                     .filter(weapon_name.eq(name))
+                    .order(id.asc())
                     .load::<WeaponPenetration>(connection)
                     .expect("Error loading weapon penetration!");
 
@@ -91,6 +88,7 @@ impl WeaponPenetrationCollection {
 
         let vec = weapon_penetrations
                     .filter(weapon_name.eq_any(names))
+                    .order(id.asc())
                     .load::<WeaponPenetration>(connection)
                     .expect("Error loading weapon penetration!");
 
@@ -101,6 +99,23 @@ impl WeaponPenetrationCollection {
         }
     }
 
+    /// Get the weapon penetration rows whose armour family isn't in `families` (lowercased names), ordered by weapon
+    /// name.
+    pub fn get_with_unknown_armour_family(connection: &mut SqliteConnection, families: &HashSet<String>) -> Self {
+        use crate::schema::weapon_penetrations::dsl::*;
+
+        let vec = weapon_penetrations
+                    .order((weapon_name.asc(), id.asc()))
+                    .load::<WeaponPenetration>(connection)
+                    .expect("Error loading weapon penetration!");
+
+        Self {
+            field_penetration: 0,
+            default_penetration: 0.0,
+            weapon_penetrations: vec.into_iter().filter(|wp| !families.contains(&wp.armor_family.to_lowercase())).collect()
+        }
+    }
+
     /// Copy the `field_penetration` value into collection so it can be exported.
     pub fn use_field_penetration(&mut self, weapon: &Weapon) {
         self.field_penetration = weapon.field_penetration;
@@ -111,12 +126,12 @@ impl WeaponPenetrationCollection {
         self.default_penetration = weapon.default_penetration;
     }
 
-    /// Consume the collection, divide into HashMap by `weapon_name`
+    /// Consume the collection, divide into HashMap by `weapon_name`. Keeps the original order.
     pub fn key_by_weapon_name(self) -> HashMap<String, Self> {
-        let mut weapon_penetrations: Vec<WeaponPenetration> = self.weapon_penetrations;
+        let weapon_penetrations: Vec<WeaponPenetration> = self.weapon_penetrations;
         let mut map = HashMap::new();
 
-        while let Some(wp) = weapon_penetrations.pop() {
+        for wp in weapon_penetrations {
             if !map.contains_key(&wp.weapon_name) {
                 map.insert(wp.weapon_name.to_string(), Self {
                     field_penetration: self.field_penetration,
@@ -163,6 +178,7 @@ impl fmt::Display for WeaponPenetrationCollection {
 ///
 
 /// Class Ready to be inserted via Diesel.
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Default, Insertable, Debug)]
 #[diesel(table_name = weapon_penetrations)]
 pub struct NewWeaponPenetration {
@@ -180,41 +196,43 @@ pub struct NewWeaponPenetrationCollection {
 }
 
 impl NewWeaponPenetrationCollection {
-    /// Parse the `setPenetration(...)` lua call into an instance.
-    /// This whole process needs to be optimized at some point.
-    pub fn from_string(weapon_name: &String, body: &String) -> Self {
-        let rx = set_penetration_regex();
-        let rx_family = armor_family_regex();
-
-        let caps = rx.captures(body);
-
+    /// Build from the parsed `setPenetration(...)` lua call in `chunk`.
+    /// Every argument after the defaults is a table of `{ArmorFamily=penetration}` pairs.
+    pub fn from_chunk(weapon_name: &str, chunk: &Chunk) -> Result<Self, ParseError> {
         let mut field_penetration: i32 = 0;
         let mut default_penetration: f32 = 0.0;
         let mut weapon_penetrations: Vec<NewWeaponPenetration> = Vec::new();
 
-        if caps.is_some() {
-            let caps_unwrapped = caps.unwrap();
-
-            field_penetration = caps_unwrapped.get(1).unwrap().as_str().parse().unwrap_or(0);
-            default_penetration = caps_unwrapped.get(2).unwrap().as_str().parse().unwrap_or(0.0);
-
-            let family_str = caps_unwrapped.get(3).unwrap().as_str();
-
-            for family in rx_family.captures_iter(family_str) {
-                weapon_penetrations.push(NewWeaponPenetration {
-                    weapon_name: weapon_name.to_string(),
-                    armor_family: family.get(1).unwrap().as_str().to_string(),
-                    penetration: family.get(2).unwrap().as_str().parse().unwrap_or(0.0)
-                });
+        if let Some(call) = chunk.find_call("setPenetration") {
+            field_penetration = call.integer_arg(1)?;
+            default_penetration = call.number_arg(2)?;
+
+            for index in 3..call.args.len() {
+                for family in call.table_arg(index)? {
+                    let armor_family = family
+                        .name()
+                        .ok_or_else(|| ParseError::argument(call, index, "Expected `ArmorFamily=penetration` pairs"))?;
+
+                    let penetration = family
+                        .value
+                        .as_number()
+                        .ok_or_else(|| ParseError::argument(call, index, &format!("Expected a number for `{}`", armor_family)))?;
+
+                    weapon_penetrations.push(NewWeaponPenetration {
+                        weapon_name: weapon_name.to_string(),
+                        armor_family: armor_family.to_string(),
+                        penetration: penetration as f32
+                    });
+                }
             }
         } else {
-            println!("No weapon penetrations found for: {}!", weapon_name);
+            eprintln!("No weapon penetrations found for: {}!", weapon_name);
         }
 
-        Self {
+        Ok(Self {
             field_penetration,
             default_penetration,
             weapon_penetrations
-        }
+        })
     }
 }
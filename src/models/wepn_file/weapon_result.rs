@@ -1,15 +1,48 @@
 use std::fmt;
 use diesel::prelude::*;
 use crate::schema::{weapon_results};
-use regex::Regex;
 use super::weapon::Weapon;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
 use std::collections::HashMap;
 
-///
-/// Regex's
-///
-pub fn add_weapon_result_regex() -> Regex {
-    Regex::new(r"AddWeaponResult\(NewWeaponType,\s*(.*)\)").unwrap()
+string_enum! {
+    ///
+    /// Weapon Result Condition
+    ///
+    /// When the result applies; argument 1 of `AddWeaponResult(...)`.
+    ///
+    Condition {
+        Hit => "Hit",
+        Miss => "Miss"
+    }
+}
+
+string_enum! {
+    ///
+    /// Weapon Result Effect
+    ///
+    /// What the result does; argument 2 of `AddWeaponResult(...)`. Only `SpawnWeaponFire` uses the spawned weapon.
+    ///
+    Effect {
+        DamageHealth => "DamageHealth",
+        Push => "Push",
+        Disable => "Disable",
+        SpawnWeaponFire => "SpawnWeaponFire",
+        ModifySpeed => "ModifySpeed"
+    }
+}
+
+string_enum! {
+    ///
+    /// Weapon Result Target
+    ///
+    /// Who the result applies to; argument 3 of `AddWeaponResult(...)`.
+    ///
+    Target {
+        Target => "Target",
+        Owner => "Owner"
+    }
 }
 
 ///
@@ -21,9 +54,9 @@ pub fn add_weapon_result_regex() -> Regex {
 pub struct WeaponResult {
     pub id: i32,
     pub weapon_name: String,
-    pub condition: String,
-    pub effect: String,
-    pub target: String,
+    pub condition: Condition,
+    pub effect: Effect,
+    pub target: Target,
     pub minimum_effect: f32,
     pub maximum_effect: f32,
     pub spawn_weapon_name: Option<String>
@@ -63,7 +96,24 @@ impl fmt::Display for WeaponResultCollection {
 }
 
 impl WeaponResultCollection {
+    /// Each result's fields, paired with their values, in order. Results have no key of their own, so the diff
+    /// matches them by content (see `WeaponFileDiff`).
+    pub fn fields(&self) -> Vec<Vec<(String, String)>> {
+        self.weapon_results
+            .iter()
+            .map(|wr| vec![
+                (String::from("condition"), wr.condition.to_string()),
+                (String::from("effect"), wr.effect.to_string()),
+                (String::from("target"), wr.target.to_string()),
+                (String::from("minimum_effect"), wr.minimum_effect.to_string()),
+                (String::from("maximum_effect"), wr.maximum_effect.to_string()),
+                (String::from("spawn_weapon_name"), wr.spawn_weapon_name.clone().unwrap_or_default())
+            ])
+            .collect()
+    }
+
     /// Return a new empty instance
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
             weapon_results: Vec::new()
@@ -76,6 +126,7 @@ impl WeaponResultCollection {
 
         let vec = weapon_results
                     .filter(weapon_name.eq(name))
+                    .order(id.asc())
                     .load::<WeaponResult>(connection)
                     .expect("Error loading weapon results!");
 
@@ -90,6 +141,22 @@ impl WeaponResultCollection {
 
         let vec = weapon_results
                     .filter(weapon_name.eq_any(names))
+                    .order(id.asc())
+                    .load::<WeaponResult>(connection)
+                    .expect("Error loading weapon results!");
+
+        Self {
+            weapon_results: vec
+        }
+    }
+
+    /// Get every weapon result that spawns another weapon, ordered by weapon name.
+    pub fn get_spawning(connection: &mut SqliteConnection) -> Self {
+        use crate::schema::weapon_results::dsl::*;
+
+        let vec = weapon_results
+                    .filter(spawn_weapon_name.is_not_null())
+                    .order((weapon_name.asc(), id.asc()))
                     .load::<WeaponResult>(connection)
                     .expect("Error loading weapon results!");
 
@@ -98,12 +165,12 @@ impl WeaponResultCollection {
         }
     }
 
-    /// Consume the collection, divide into HashMap by `weapon_name`
+    /// Consume the collection, divide into HashMap by `weapon_name`. Keeps the original order.
     pub fn key_by_weapon_name(self) -> HashMap<String, Self> {
-        let mut weapon_results: Vec<WeaponResult> = self.weapon_results;
+        let weapon_results: Vec<WeaponResult> = self.weapon_results;
         let mut map = HashMap::new();
 
-        while let Some(wr) = weapon_results.pop() {
+        for wr in weapon_results {
             if !map.contains_key(&wr.weapon_name) {
                 map.insert(wr.weapon_name.to_string(), Self {
                     weapon_results: vec![wr]
@@ -125,13 +192,14 @@ impl WeaponResultCollection {
 ///
 
 /// Class Ready to be inserted via Diesel.
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Default, Insertable, Debug)]
 #[diesel(table_name = weapon_results)]
 pub struct NewWeaponResult {
     pub weapon_name: String,
-    pub condition: String,
-    pub effect: String,
-    pub target: String,
+    pub condition: Condition,
+    pub effect: Effect,
+    pub target: Target,
     pub minimum_effect: f32,
     pub maximum_effect: f32,
     pub spawn_weapon_name: Option<String>
@@ -144,33 +212,52 @@ pub struct NewWeaponResultCollection {
 }
 
 impl NewWeaponResultCollection {
-    /// Parse the `AddWeaponResult(...)` lua calls into an instance.
-    /// This whole process needs to be optimized at some point.
-    pub fn from_string(weapon_name: &String, body: &String) -> Self {
-        let rx = add_weapon_result_regex();
+    /// Build from the parsed `AddWeaponResult(...)` lua calls in `chunk`.
+    pub fn from_chunk(weapon_name: &str, chunk: &Chunk) -> Result<Self, ParseError> {
         let mut weapon_results: Vec<NewWeaponResult> = Vec::new();
 
-        for caps in rx.captures_iter(&body) {
-            let split: Vec<&str> = caps[1]
-                                        .split(",")
-                                        .collect::<Vec<&str>>()
-                                        .iter()
-                                        .map(|x| x.trim())
-                                        .collect();
+        for call in chunk.calls_named("AddWeaponResult") {
+            // The spawned weapon is optional; it's only meaningful for `SpawnWeaponFire`. Empty names are stored as
+            // `None`, and written back out as `""`.
+            let spawn_weapon_name = if call.value(6).is_some() {
+                Some(call.string_arg(6)?).filter(|name| !name.is_empty())
+            } else {
+                None
+            };
 
             weapon_results.push(NewWeaponResult {
                 weapon_name: weapon_name.to_string(),
-                condition: split[0].to_string().replace("\"", ""),
-                effect: split[1].to_string().replace("\"", ""),
-                target: split[2].to_string().replace("\"", ""),
-                minimum_effect: split[3].parse().unwrap_or_default(),
-                maximum_effect: split[4].parse().unwrap_or_default(),
-                spawn_weapon_name: Some(split[5].to_string().replace("\"", ""))
-            })
+                condition: call.string_arg(1)?.as_str().into(),
+                effect: call.string_arg(2)?.as_str().into(),
+                target: call.string_arg(3)?.as_str().into(),
+                minimum_effect: call.number_arg(4)?,
+                maximum_effect: call.number_arg(5)?,
+                spawn_weapon_name
+            });
         }
 
-        Self {
+        Ok(Self {
             weapon_results
+        })
+    }
+
+    /// The condition, effect and target of each result that Scaffold doesn't know, as `(field, value)` with the
+    /// field prefixed by `results[<index>]`.
+    pub fn unknown_values(&self) -> Vec<(String, &str)> {
+        let mut unknown = Vec::new();
+
+        for (index, wr) in self.weapon_results.iter().enumerate() {
+            let values = [
+                ("condition", wr.condition.is_other(), wr.condition.as_str()),
+                ("effect", wr.effect.is_other(), wr.effect.as_str()),
+                ("target", wr.target.is_other(), wr.target.as_str())
+            ];
+
+            for (k, _, v) in values.into_iter().filter(|(_, other, _)| *other) {
+                unknown.push((format!("results[{}].{}", index, k), v));
+            }
         }
+
+        unknown
     }
 }
@@ -0,0 +1,259 @@
+use diesel::prelude::*;
+use crate::schema::{weapon_sources};
+use super::SQLITE_MAX_VARIABLES;
+use std::collections::{ BTreeMap, HashMap };
+
+///
+/// Weapon Source
+///
+/// Where a weapon came from. Each import layer that provides a weapon records a row, along with the weapon file as
+/// it was rendered after that layer was imported. Layers are numbered in the order they were first imported, so the
+/// lowest layer is the base game.
+///
+#[derive(Queryable, Selectable, Identifiable, Debug)]
+#[diesel(table_name = weapon_sources)]
+pub struct WeaponSource {
+    pub id: i32,
+    pub weapon_name: String,
+    pub layer: i32,
+    pub source: String,
+    pub rendered: String
+}
+
+/// Array of `WeaponSource`, ordered by `layer`.
+#[derive(Debug)]
+pub struct WeaponSourceCollection {
+    pub weapon_sources: Vec<WeaponSource>
+}
+
+impl Default for WeaponSourceCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeaponSourceCollection {
+    /// Return a new empty instance
+    pub fn new() -> Self {
+        Self {
+            weapon_sources: Vec::new()
+        }
+    }
+
+    /// Get the rendered weapon files from the base (lowest) layer, keyed by weapon name.
+    pub fn get_base_layer(connection: &mut SqliteConnection) -> HashMap<String, String> {
+        use crate::schema::weapon_sources::dsl::*;
+
+        let base: Option<i32> = weapon_sources
+                    .select(diesel::dsl::min(layer))
+                    .first(connection)
+                    .expect("Error loading weapon sources!");
+
+        let Some(base) = base else {
+            return HashMap::new();
+        };
+
+        weapon_sources
+            .filter(layer.eq(base))
+            .select((weapon_name, rendered))
+            .load::<(String, String)>(connection)
+            .expect("Error loading weapon sources!")
+            .into_iter()
+            .collect()
+    }
+
+    /// Get each layer's source and how many weapons it provided, ordered by layer.
+    pub fn get_layers(connection: &mut SqliteConnection) -> Vec<(i32, String, usize)> {
+        use crate::schema::weapon_sources::dsl::*;
+
+        let rows = weapon_sources
+                    .select((layer, source))
+                    .load::<(i32, String)>(connection)
+                    .expect("Error loading weapon sources!");
+
+        let mut layers: BTreeMap<i32, (String, usize)> = BTreeMap::new();
+
+        for (l, s) in rows {
+            layers.entry(l).or_insert((s, 0)).1 += 1;
+        }
+
+        layers.into_iter().map(|(l, (s, count))| (l, s, count)).collect()
+    }
+
+    /// Layer number for `source`: the one it was given when first imported, or the next one up if it's new.
+    pub fn layer_for_source(connection: &mut SqliteConnection, src: &str) -> QueryResult<i32> {
+        use crate::schema::weapon_sources::dsl::*;
+
+        let existing: Option<i32> = weapon_sources
+                    .filter(source.eq(src))
+                    .select(layer)
+                    .first(connection)
+                    .optional()?;
+
+        if let Some(existing) = existing {
+            return Ok(existing);
+        }
+
+        let top: Option<i32> = weapon_sources
+                    .select(diesel::dsl::max(layer))
+                    .first(connection)?;
+
+        Ok(top.map_or(0, |t| t + 1))
+    }
+
+    /// Consume the collection, divide into HashMap by `weapon_name`. Keeps the `layer` order.
+    pub fn key_by_weapon_name(self) -> HashMap<String, Self> {
+        let mut map: HashMap<String, Self> = HashMap::new();
+
+        for ws in self.weapon_sources {
+            map
+                .entry(ws.weapon_name.to_string())
+                .or_default()
+                .weapon_sources.push(ws);
+        }
+
+        map
+    }
+}
+
+///
+/// NewWeaponSource
+///
+/// Class Ready to be Inserted via Diesel
+#[derive(Default, Insertable, Clone, Debug)]
+#[diesel(table_name = weapon_sources)]
+pub struct NewWeaponSource {
+    pub weapon_name: String,
+    pub layer: i32,
+    pub source: String,
+    pub rendered: String
+}
+
+/// Array of `NewWeaponSource` with helper methods.
+#[derive(Debug)]
+pub struct NewWeaponSourceCollection {
+    pub weapon_sources: Vec<NewWeaponSource>
+}
+
+impl NewWeaponSourceCollection {
+    /// Record each rendered weapon file as coming from `source`, at `layer`.
+    pub fn from_rendered(layer: i32, source: &str, rendered: &HashMap<String, String>) -> Self {
+        let mut weapon_sources: Vec<NewWeaponSource> = rendered
+            .iter()
+            .map(|(name, wepn)| NewWeaponSource {
+                weapon_name: name.to_string(),
+                layer,
+                source: source.to_string(),
+                rendered: wepn.to_string()
+            })
+            .collect();
+
+        weapon_sources.sort_by(|a, b| a.weapon_name.cmp(&b.weapon_name));
+
+        Self {
+            weapon_sources
+        }
+    }
+
+    /// Consumes the collection, replacing any rows already recorded for the same weapons at the same layer.
+    pub fn insert(self, connection: &mut SqliteConnection) -> QueryResult<()> {
+        use crate::schema::weapon_sources::dsl::*;
+
+        let Some(first) = self.weapon_sources.first() else {
+            return Ok(());
+        };
+
+        let this_layer = first.layer;
+        let names: Vec<&String> = self.weapon_sources.iter().map(|ws| &ws.weapon_name).collect();
+
+        for chunk in names.chunks(SQLITE_MAX_VARIABLES - 1) {
+            diesel::delete(weapon_sources.filter(layer.eq(this_layer)).filter(weapon_name.eq_any(chunk)))
+                .execute(connection)?;
+        }
+
+        for chunk in self.weapon_sources.chunks(SQLITE_MAX_VARIABLES / 4) {
+            diesel::insert_into(weapon_sources)
+                .values(chunk.to_vec())
+                .execute(connection)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::testing;
+    use crate::models::data_file::NewDataFileCollection;
+    use crate::models::wepn_file::{ ImportOptions, ImportReport, NewWeaponFileCollection, WeaponFileCollection };
+
+    /// Import `(name, body)` weapon files as the layer for `source`.
+    fn import_layer(connection: &mut SqliteConnection, source: &str, weapons: &[(&str, &str)]) -> ImportReport {
+        let files = weapons.iter().map(|(name, body)| testing::weapon_file(name, body)).collect();
+        let options = ImportOptions {
+            source: Some(source.to_string()),
+            ..testing::quiet()
+        };
+
+        NewWeaponFileCollection::from_vec(files).insert(connection, &options).unwrap()
+    }
+
+    fn names(weapon_files: &WeaponFileCollection) -> Vec<&str> {
+        let mut names: Vec<&str> = weapon_files.weapon_files.keys().map(|n| n.as_str()).collect();
+
+        names.sort();
+
+        names
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let connection = &mut testing::database();
+
+        import_layer(connection, "base", &[("tst", "setPenetration(NewWeaponType,5,1,{Fighter=1});")]);
+        import_layer(connection, "mod", &[("tst", "setPenetration(NewWeaponType,5,1,{Fighter=2});")]);
+
+        let weapon_files = WeaponFileCollection::get_all_weapon_files(connection);
+
+        assert!(weapon_files.weapon_files["tst"].to_string().contains("Fighter=2"));
+        assert!(WeaponSourceCollection::get_base_layer(connection)["tst"].contains("Fighter=1"));
+    }
+
+    #[test]
+    fn layers_keep_their_number_when_imported_again() {
+        let connection = &mut testing::database();
+
+        assert_eq!(import_layer(connection, "base", &[("a", "")]).layer, Some(0));
+        assert_eq!(import_layer(connection, "mod", &[("a", ""), ("b", "")]).layer, Some(1));
+        assert_eq!(import_layer(connection, "base", &[("a", "")]).layer, Some(0));
+
+        assert_eq!(WeaponSourceCollection::get_layers(connection), vec![
+            (0, String::from("base"), 1),
+            (1, String::from("mod"), 2)
+        ]);
+    }
+
+    #[test]
+    fn overrides_only_keeps_weapons_that_differ_from_the_base_layer() {
+        let connection = &mut testing::database();
+
+        import_layer(connection, "base", &[("same", ""), ("changed", ""), ("untouched", "")]);
+        import_layer(connection, "mod", &[("same", ""), ("changed", "setAccuracy(NewWeaponType,1,{Fighter=0.5});"), ("new", "")]);
+
+        let mut weapon_files = WeaponFileCollection::get_all_weapon_files(connection);
+
+        weapon_files.retain_differing(&WeaponSourceCollection::get_base_layer(connection));
+
+        assert_eq!(names(&weapon_files), vec!["changed", "new"]);
+    }
+
+    #[test]
+    fn no_base_layer_without_sources() {
+        let connection = &mut testing::database();
+
+        testing::import_weapons(connection, &[("tst", "")]);
+
+        assert!(WeaponSourceCollection::get_base_layer(connection).is_empty());
+    }
+}
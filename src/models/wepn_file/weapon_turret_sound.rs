@@ -1,19 +1,11 @@
-use regex::Regex;
 use diesel::prelude::*;
 use crate::schema::{weapon_turret_sound};
 use super::weapon::Weapon;
+use crate::models::lua::Chunk;
+use crate::models::parse_error::ParseError;
 use std::fmt;
 use std::collections::HashMap;
 
-///
-/// Regex's
-///
-
-/// Find the `addAnimTurretSound(...)` function call in a string, parse into groups.
-pub fn add_anim_turret_regex() -> Regex {
-    Regex::new(r#"addAnimTurretSound\(NewWeaponType\s*,\s*"(.*)"\s*\)"#).unwrap()
-}
-
 ///
 /// Weapon Turret Sound
 ///
@@ -33,7 +25,13 @@ impl fmt::Display for WeaponTurretSound {
 }
 
 impl WeaponTurretSound {
+    /// The sound, keyed as `turret_sound`.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        vec![("turret_sound".to_string(), self.anim_turret_sound.to_string())]
+    }
+
     /// Get weapon turret sound for a specific weapon name. Possible that it can't be found.
+    #[allow(clippy::manual_unwrap_or_default)]
     pub fn get_for_weapon(connection: &mut SqliteConnection, name: &String) -> Option<Self> {
         use crate::schema::weapon_turret_sound::dsl::*;
 
@@ -81,6 +79,7 @@ impl WeaponTurretSoundCollection {
 ///
 
 /// Class Ready to be Inserted via Diesel
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Default, Insertable, Debug)]
 #[diesel(table_name = weapon_turret_sound)]
 pub struct NewWeaponTurretSound {
@@ -89,18 +88,15 @@ pub struct NewWeaponTurretSound {
 }
 
 impl NewWeaponTurretSound {
-    /// Parse the `addAnimTurretSound(...)` lua call into an instance.
-    /// This whole process needs to be optimized at some point.
-    pub fn from_string(weapon_name: &String, body: &String) -> Option<Self> {
-        let rx = add_anim_turret_regex();
-
-        if let Some(caps) = rx.captures(body) {
-            Some(Self {
+    /// Build from the parsed `addAnimTurretSound(...)` lua call in `chunk`.
+    pub fn from_chunk(weapon_name: &str, chunk: &Chunk) -> Result<Option<Self>, ParseError> {
+        if let Some(call) = chunk.find_call("addAnimTurretSound") {
+            Ok(Some(Self {
                 weapon_name: weapon_name.to_string(),
-                anim_turret_sound: caps.get(1).unwrap().as_str().to_string()
-            })
+                anim_turret_sound: call.string_arg(1)?
+            }))
         } else {
-            None
+            Ok(None)
         }
     }
 }
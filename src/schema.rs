@@ -1,5 +1,119 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    armour_families (name) {
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    attack_families (name) {
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    display_families (name) {
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    missile_extra_statements (id) {
+        id -> Integer,
+        missile_name -> Text,
+        position -> Integer,
+        statement -> Text,
+    }
+}
+
+diesel::table! {
+    missiles (name) {
+        name -> Text,
+        max_health -> Nullable<Float>,
+        armour_family -> Nullable<Text>,
+        main_engine_max_speed -> Nullable<Float>,
+        rotation_max_speed -> Nullable<Float>,
+    }
+}
+
+diesel::table! {
+    ship_abilities (id) {
+        id -> Integer,
+        ship_name -> Text,
+        ability -> Text,
+        arguments -> Text,
+    }
+}
+
+diesel::table! {
+    ship_extra_statements (id) {
+        id -> Integer,
+        ship_name -> Text,
+        position -> Integer,
+        statement -> Text,
+        anchor -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    ship_target_filters (id) {
+        id -> Integer,
+        ship_name -> Text,
+        arguments -> Text,
+    }
+}
+
+diesel::table! {
+    ship_weapons (id) {
+        id -> Integer,
+        ship_name -> Text,
+        weapon_name -> Text,
+        hardpoint -> Text,
+        extra_arguments -> Text,
+    }
+}
+
+diesel::table! {
+    ships (name) {
+        name -> Text,
+        max_health -> Nullable<Float>,
+        build_cost -> Nullable<Float>,
+        build_time -> Nullable<Float>,
+        main_engine_max_speed -> Nullable<Float>,
+        armour_family -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    subsystem_extra_statements (id) {
+        id -> Integer,
+        subsystem_name -> Text,
+        position -> Integer,
+        statement -> Text,
+        anchor -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    subsystem_weapons (id) {
+        id -> Integer,
+        subsystem_name -> Text,
+        weapon_name -> Text,
+        hardpoint -> Text,
+        extra_arguments -> Text,
+    }
+}
+
+diesel::table! {
+    subsystems (name) {
+        name -> Text,
+        max_health -> Nullable<Float>,
+        cost_to_build -> Nullable<Float>,
+        time_to_build -> Nullable<Float>,
+    }
+}
+
 diesel::table! {
     weapon_accuracy (id) {
         id -> Integer,
@@ -22,6 +136,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    weapon_extra_statements (id) {
+        id -> Integer,
+        weapon_name -> Text,
+        position -> Integer,
+        statement -> Text,
+        anchor -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     weapon_misc (id) {
         id -> Integer,
@@ -53,6 +177,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    weapon_sources (id) {
+        id -> Integer,
+        weapon_name -> Text,
+        layer -> Integer,
+        source -> Text,
+        rendered -> Text,
+    }
+}
+
 diesel::table! {
     weapon_turret_sound (id) {
         id -> Integer,
@@ -95,19 +229,44 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(missile_extra_statements -> missiles (missile_name));
+diesel::joinable!(ship_abilities -> ships (ship_name));
+diesel::joinable!(ship_extra_statements -> ships (ship_name));
+diesel::joinable!(ship_target_filters -> ships (ship_name));
+diesel::joinable!(ship_weapons -> ships (ship_name));
+diesel::joinable!(ship_weapons -> weapons (weapon_name));
+diesel::joinable!(subsystem_extra_statements -> subsystems (subsystem_name));
+diesel::joinable!(subsystem_weapons -> subsystems (subsystem_name));
+diesel::joinable!(subsystem_weapons -> weapons (weapon_name));
 diesel::joinable!(weapon_accuracy -> weapons (weapon_name));
 diesel::joinable!(weapon_angles -> weapons (weapon_name));
+diesel::joinable!(weapon_extra_statements -> weapons (weapon_name));
 diesel::joinable!(weapon_misc -> weapons (weapon_name));
 diesel::joinable!(weapon_penetrations -> weapons (weapon_name));
 diesel::joinable!(weapon_results -> weapons (weapon_name));
 diesel::joinable!(weapon_turret_sound -> weapons (weapon_name));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    armour_families,
+    attack_families,
+    display_families,
+    missile_extra_statements,
+    missiles,
+    ship_abilities,
+    ship_extra_statements,
+    ship_target_filters,
+    ship_weapons,
+    ships,
+    subsystem_extra_statements,
+    subsystem_weapons,
+    subsystems,
     weapon_accuracy,
     weapon_angles,
+    weapon_extra_statements,
     weapon_misc,
     weapon_penetrations,
     weapon_results,
+    weapon_sources,
     weapon_turret_sound,
     weapons,
 );
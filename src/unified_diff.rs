@@ -0,0 +1,154 @@
+//! Line-based unified diffs, used to preview what an export would change on disk.
+//!
+//! Lines are compared without their line endings, so a file that only differs in `\r\n` vs `\n` produces no hunks.
+
+/// Number of unchanged lines shown around each change.
+pub const DEFAULT_CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tag {
+    Equal,
+    Delete,
+    Insert
+}
+
+/// One step in the edit script, with the (0-based) line it starts at in each file.
+#[derive(Debug, Clone, Copy)]
+struct Op {
+    tag: Tag,
+    old_line: usize,
+    new_line: usize
+}
+
+/// Render a unified diff of `old` against `new`, labelled with `old_name` and `new_name`. Returns an empty string if
+/// the lines are the same.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = edit_script(&old_lines, &new_lines);
+
+    let mut out = String::new();
+
+    for (start, end) in hunks(&ops, context) {
+        if out.is_empty() {
+            out.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+        }
+
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|op| op.tag != Tag::Insert).count();
+        let new_len = hunk.iter().filter(|op| op.tag != Tag::Delete).count();
+
+        out.push_str(&format!("@@ -{} +{} @@\n",
+            hunk_range(hunk[0].old_line, old_len),
+            hunk_range(hunk[0].new_line, new_len)
+        ));
+
+        for op in hunk {
+            match op.tag {
+                Tag::Equal => out.push_str(&format!(" {}\n", old_lines[op.old_line])),
+                Tag::Delete => out.push_str(&format!("-{}\n", old_lines[op.old_line])),
+                Tag::Insert => out.push_str(&format!("+{}\n", new_lines[op.new_line]))
+            }
+        }
+    }
+
+    out
+}
+
+/// `start,len` as written in a hunk header. An empty range points at the line before it, as `diff -u` does.
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len)
+    }
+}
+
+/// `(old, new)` index pairs of the items `old` and `new` have in common, in order, as matched for a unified diff.
+pub fn common_items<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+    edit_script(old, new)
+        .into_iter()
+        .filter(|op| op.tag == Tag::Equal)
+        .map(|op| (op.old_line, op.new_line))
+        .collect()
+}
+
+/// Shortest edit script from `old` to `new`, via the longest common subsequence. The common prefix and suffix are
+/// trimmed first, so files with a few edits stay cheap.
+fn edit_script<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Op> {
+    let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..].
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+
+    for k in 0..prefix {
+        ops.push(Op { tag: Tag::Equal, old_line: k, new_line: k });
+    }
+
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() || j < b.len() {
+        let op = if i < a.len() && j < b.len() && a[i] == b[j] {
+            Tag::Equal
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            Tag::Delete
+        } else {
+            Tag::Insert
+        };
+
+        ops.push(Op { tag: op, old_line: prefix + i, new_line: prefix + j });
+
+        match op {
+            Tag::Equal => { i += 1; j += 1; },
+            Tag::Delete => i += 1,
+            Tag::Insert => j += 1
+        }
+    }
+
+    for k in 0..suffix {
+        ops.push(Op { tag: Tag::Equal, old_line: prefix + a.len() + k, new_line: prefix + b.len() + k });
+    }
+
+    ops
+}
+
+/// Group the changes in `ops` into hunks with `context` lines either side, merging hunks that would overlap.
+/// Returns `(start, end)` ranges into `ops`.
+fn hunks(ops: &[Op], context: usize) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for (index, op) in ops.iter().enumerate() {
+        if op.tag == Tag::Equal {
+            continue;
+        }
+
+        let start = index.saturating_sub(context);
+        let end = (index + 1 + context).min(ops.len());
+
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end))
+        }
+    }
+
+    ranges
+}